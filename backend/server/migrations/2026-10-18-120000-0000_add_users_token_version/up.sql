@@ -0,0 +1,2 @@
+-- Bumped whenever every outstanding access token of a user must stop working.
+ALTER TABLE users ADD COLUMN token_version INT NOT NULL DEFAULT 0;
@@ -0,0 +1,2 @@
+ALTER TABLE follows DROP CONSTRAINT IF EXISTS follows_no_self_follow;
+DROP TABLE IF EXISTS blocks;
@@ -0,0 +1,13 @@
+CREATE TABLE blocks (
+  blocker_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  blocked_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (blocker_id, blocked_id),
+  CHECK (blocker_id <> blocked_id)
+);
+
+CREATE INDEX blocks_blocked_id_idx ON blocks (blocked_id);
+
+-- Self-follows were possible while the client chose both ids.
+DELETE FROM follows WHERE user_id = target_id;
+ALTER TABLE follows ADD CONSTRAINT follows_no_self_follow CHECK (user_id <> target_id);
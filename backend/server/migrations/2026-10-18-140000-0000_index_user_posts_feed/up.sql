@@ -0,0 +1,2 @@
+-- Keyset pagination of the home feed walks (created_at, id) per author.
+CREATE INDEX user_posts_user_id_created_at_idx ON user_posts (user_id, created_at DESC, id DESC);
@@ -0,0 +1,5 @@
+DROP INDEX IF EXISTS users_created_at_idx;
+DROP INDEX IF EXISTS follows_user_id_created_at_idx;
+DROP INDEX IF EXISTS follows_target_id_created_at_idx;
+
+ALTER TABLE follows ALTER COLUMN created_at DROP NOT NULL;
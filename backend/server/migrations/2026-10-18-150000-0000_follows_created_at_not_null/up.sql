@@ -0,0 +1,8 @@
+-- Follower lists paginate on (created_at, id), which needs a value on every row.
+UPDATE follows SET created_at = NOW() WHERE created_at IS NULL;
+ALTER TABLE follows ALTER COLUMN created_at SET NOT NULL;
+
+CREATE INDEX follows_target_id_created_at_idx ON follows (target_id, status, created_at DESC, id DESC);
+CREATE INDEX follows_user_id_created_at_idx ON follows (user_id, status, created_at DESC, id DESC);
+
+CREATE INDEX users_created_at_idx ON users (created_at DESC, id DESC);
@@ -0,0 +1,8 @@
+CREATE TABLE post_likes (
+  post_id UUID NOT NULL REFERENCES user_posts(id) ON DELETE CASCADE,
+  user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  PRIMARY KEY (post_id, user_id)
+);
+
+CREATE INDEX post_likes_user_id_idx ON post_likes (user_id);
@@ -0,0 +1,13 @@
+CREATE TABLE post_comments (
+  id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  post_id UUID NOT NULL REFERENCES user_posts(id) ON DELETE CASCADE,
+  user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  -- Replies are one level deep; the handlers reject replies to replies.
+  parent_id UUID REFERENCES post_comments(id) ON DELETE CASCADE,
+  body TEXT NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+  updated_at TIMESTAMP
+);
+
+CREATE INDEX post_comments_post_id_created_at_idx ON post_comments (post_id, created_at DESC, id DESC) WHERE parent_id IS NULL;
+CREATE INDEX post_comments_parent_id_created_at_idx ON post_comments (parent_id, created_at DESC, id DESC);
@@ -0,0 +1,4 @@
+DROP INDEX users_lower_name_idx;
+DROP TABLE post_mentions;
+DROP TABLE post_hashtags;
+DROP TABLE hashtags;
@@ -0,0 +1,51 @@
+CREATE TABLE hashtags (
+  id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  -- Lowercased, without the leading '#'.
+  tag TEXT NOT NULL UNIQUE,
+  created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE post_hashtags (
+  post_id UUID NOT NULL REFERENCES user_posts(id) ON DELETE CASCADE,
+  hashtag_id UUID NOT NULL REFERENCES hashtags(id) ON DELETE CASCADE,
+  PRIMARY KEY (post_id, hashtag_id)
+);
+
+CREATE INDEX post_hashtags_hashtag_id_idx ON post_hashtags (hashtag_id);
+
+CREATE TABLE post_mentions (
+  post_id UUID NOT NULL REFERENCES user_posts(id) ON DELETE CASCADE,
+  user_id UUID NOT NULL REFERENCES users(id) ON DELETE CASCADE,
+  PRIMARY KEY (post_id, user_id)
+);
+
+CREATE INDEX post_mentions_user_id_idx ON post_mentions (user_id);
+
+-- Mentions are resolved by case-insensitive name.
+CREATE INDEX users_lower_name_idx ON users (lower(name));
+
+-- Index the posts that already exist, using the same rules as src/entities.rs.
+INSERT INTO hashtags (tag)
+SELECT DISTINCT lower(m[2])
+FROM user_posts p,
+     regexp_matches(p.description, '(^|[^[:alnum:]_])#([[:alnum:]_]{1,100})(?![[:alnum:]_])', 'g') AS m
+WHERE m[2] !~ '^[0-9]+$'
+ON CONFLICT (tag) DO NOTHING;
+
+INSERT INTO post_hashtags (post_id, hashtag_id)
+SELECT DISTINCT p.id, h.id
+FROM user_posts p,
+     regexp_matches(p.description, '(^|[^[:alnum:]_])#([[:alnum:]_]{1,100})(?![[:alnum:]_])', 'g') AS m
+JOIN hashtags h ON h.tag = lower(m[2]);
+
+INSERT INTO post_mentions (post_id, user_id)
+SELECT DISTINCT p.id, u.id
+FROM user_posts p,
+     regexp_matches(p.description, '(^|[^[:alnum:]_])@([[:alnum:]_.]{1,100})(?![[:alnum:]_.])', 'g') AS m
+JOIN users u ON lower(u.name) = lower(rtrim(m[2], '.'))
+WHERE (SELECT count(*) FROM users v WHERE lower(v.name) = lower(u.name)) = 1
+  AND NOT EXISTS (
+    SELECT 1 FROM blocks b
+    WHERE (b.blocker_id = u.id AND b.blocked_id = p.user_id)
+       OR (b.blocker_id = p.user_id AND b.blocked_id = u.id)
+  );
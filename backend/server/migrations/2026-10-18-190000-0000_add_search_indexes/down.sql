@@ -0,0 +1,7 @@
+DROP INDEX hashtags_tag_trgm_idx;
+DROP INDEX users_name_trgm_idx;
+DROP INDEX user_posts_description_tsv_idx;
+DROP INDEX users_name_tsv_idx;
+
+ALTER TABLE user_posts DROP COLUMN description_tsv;
+ALTER TABLE users DROP COLUMN name_tsv;
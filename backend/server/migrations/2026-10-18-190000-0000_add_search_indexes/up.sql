@@ -0,0 +1,16 @@
+CREATE EXTENSION IF NOT EXISTS pg_trgm;
+
+-- Diesel has no tsvector type, so these columns are left out of
+-- src/schema.rs and only used through SQL fragments in search_handler.rs.
+ALTER TABLE users
+  ADD COLUMN name_tsv TSVECTOR GENERATED ALWAYS AS (to_tsvector('simple', name)) STORED;
+
+ALTER TABLE user_posts
+  ADD COLUMN description_tsv TSVECTOR GENERATED ALWAYS AS (to_tsvector('english', description)) STORED;
+
+CREATE INDEX users_name_tsv_idx ON users USING GIN (name_tsv);
+CREATE INDEX user_posts_description_tsv_idx ON user_posts USING GIN (description_tsv);
+
+-- Partial names ("ali" for "alice") don't match as words; trigrams cover them.
+CREATE INDEX users_name_trgm_idx ON users USING GIN (name gin_trgm_ops);
+CREATE INDEX hashtags_tag_trgm_idx ON hashtags USING GIN (tag gin_trgm_ops);
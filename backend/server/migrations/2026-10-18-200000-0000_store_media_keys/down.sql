@@ -0,0 +1,31 @@
+UPDATE user_posts
+SET videos = ARRAY(
+  SELECT CASE
+           WHEN v LIKE 'userpost/%' THEN substr(v, length('userpost/') + 1)
+           ELSE v
+         END
+  FROM unnest(videos) WITH ORDINALITY AS t(v, n)
+  ORDER BY n
+);
+
+UPDATE users
+SET profile_pic = substr(profile_pic, length('userprofile/') + 1)
+WHERE profile_pic LIKE 'userprofile/%';
+
+UPDATE jobs
+SET payload = jsonb_set(payload, '{video}', to_jsonb(substr(payload->>'video', length('userpost/') + 1)))
+WHERE status <> 'done' AND payload->>'video' LIKE 'userpost/%';
+
+UPDATE jobs
+SET payload = jsonb_set(payload, '{videos}', (
+  SELECT COALESCE(jsonb_agg(
+           CASE WHEN v LIKE 'userpost/%' THEN substr(v, length('userpost/') + 1) ELSE v END
+           ORDER BY n
+         ), '[]'::jsonb)
+  FROM jsonb_array_elements_text(payload->'videos') WITH ORDINALITY AS t(v, n)
+))
+WHERE status <> 'done' AND jsonb_typeof(payload->'videos') = 'array';
+
+UPDATE jobs
+SET payload = jsonb_set(payload, '{profile_pic}', to_jsonb(substr(payload->>'profile_pic', length('userprofile/') + 1)))
+WHERE status <> 'done' AND payload->>'profile_pic' LIKE 'userprofile/%';
@@ -0,0 +1,35 @@
+-- Media columns now hold storage keys instead of bare filenames. The key
+-- prefixes match the old upload directories, so a local store rooted at
+-- ./files needs no files moved.
+UPDATE user_posts
+SET videos = ARRAY(
+  SELECT CASE
+           WHEN v IS NULL OR v LIKE 'userpost/%' THEN v
+           ELSE 'userpost/' || v
+         END
+  FROM unnest(videos) WITH ORDINALITY AS t(v, n)
+  ORDER BY n
+);
+
+UPDATE users
+SET profile_pic = 'userprofile/' || profile_pic
+WHERE profile_pic <> '' AND profile_pic NOT LIKE 'userprofile/%';
+
+-- Queued jobs still refer to bare filenames.
+UPDATE jobs
+SET payload = jsonb_set(payload, '{video}', to_jsonb('userpost/' || (payload->>'video')))
+WHERE status <> 'done' AND payload->>'video' NOT LIKE 'userpost/%';
+
+UPDATE jobs
+SET payload = jsonb_set(payload, '{videos}', (
+  SELECT COALESCE(jsonb_agg(
+           CASE WHEN v LIKE 'userpost/%' THEN v ELSE 'userpost/' || v END
+           ORDER BY n
+         ), '[]'::jsonb)
+  FROM jsonb_array_elements_text(payload->'videos') WITH ORDINALITY AS t(v, n)
+))
+WHERE status <> 'done' AND jsonb_typeof(payload->'videos') = 'array';
+
+UPDATE jobs
+SET payload = jsonb_set(payload, '{profile_pic}', to_jsonb('userprofile/' || (payload->>'profile_pic')))
+WHERE status <> 'done' AND payload->>'profile_pic' NOT LIKE 'userprofile/%';
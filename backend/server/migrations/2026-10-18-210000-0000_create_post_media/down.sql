@@ -0,0 +1,3 @@
+DELETE FROM jobs WHERE kind = 'probe_video' AND status <> 'done';
+
+DROP TABLE post_media;
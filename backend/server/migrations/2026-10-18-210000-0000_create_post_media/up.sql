@@ -0,0 +1,21 @@
+-- Container metadata of each uploaded video, read when it is uploaded.
+CREATE TABLE post_media (
+  id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+  post_id UUID NOT NULL REFERENCES user_posts(id) ON DELETE CASCADE,
+  storage_key TEXT NOT NULL UNIQUE,
+  byte_size BIGINT NOT NULL,
+  -- NULL when the container doesn't record it (e.g. live WebM).
+  duration_ms BIGINT,
+  width INTEGER NOT NULL,
+  height INTEGER NOT NULL,
+  codec TEXT NOT NULL,
+  created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX post_media_post_id_idx ON post_media (post_id);
+
+-- Videos uploaded before this table existed are probed by the job queue.
+INSERT INTO jobs (kind, payload)
+SELECT 'probe_video', jsonb_build_object('kind', 'probe_video', 'post_id', p.id, 'video', v)
+FROM user_posts p, unnest(p.videos) AS v
+WHERE v IS NOT NULL;
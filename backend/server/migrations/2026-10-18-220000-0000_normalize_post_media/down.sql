@@ -0,0 +1,22 @@
+ALTER TABLE user_posts ADD COLUMN videos TEXT[] NOT NULL DEFAULT '{}';
+
+UPDATE user_posts p
+SET videos = ARRAY(SELECT m.storage_key FROM post_media m WHERE m.post_id = p.id ORDER BY m.position);
+
+ALTER TABLE user_posts ALTER COLUMN videos DROP DEFAULT;
+
+-- The old table only held probed videos.
+DELETE FROM post_media WHERE status <> 'ready' OR kind <> 'video';
+
+ALTER TABLE post_media DROP CONSTRAINT post_media_post_id_position_key;
+CREATE INDEX post_media_post_id_idx ON post_media (post_id);
+
+ALTER TABLE post_media
+  DROP COLUMN position,
+  DROP COLUMN kind,
+  DROP COLUMN checksum,
+  DROP COLUMN status,
+  ALTER COLUMN byte_size SET NOT NULL,
+  ALTER COLUMN width SET NOT NULL,
+  ALTER COLUMN height SET NOT NULL,
+  ALTER COLUMN codec SET NOT NULL;
@@ -0,0 +1,48 @@
+-- One row per attachment replaces user_posts.videos. Rows of videos that
+-- predate probing start out 'pending' with their metadata unknown.
+ALTER TABLE post_media
+  ADD COLUMN position INTEGER,
+  ADD COLUMN kind TEXT NOT NULL DEFAULT 'video' CHECK (kind IN ('video', 'image')),
+  -- Hex SHA-256 of the stored file.
+  ADD COLUMN checksum TEXT,
+  ADD COLUMN status TEXT NOT NULL DEFAULT 'ready' CHECK (status IN ('pending', 'ready', 'failed')),
+  ALTER COLUMN byte_size DROP NOT NULL,
+  ALTER COLUMN width DROP NOT NULL,
+  ALTER COLUMN height DROP NOT NULL,
+  ALTER COLUMN codec DROP NOT NULL;
+
+INSERT INTO post_media (post_id, storage_key, status)
+SELECT p.id, v, 'pending'
+FROM user_posts p, unnest(p.videos) AS v
+WHERE v IS NOT NULL
+ON CONFLICT (storage_key) DO NOTHING;
+
+UPDATE post_media m
+SET position = t.n - 1
+FROM user_posts p, unnest(p.videos) WITH ORDINALITY AS t(v, n)
+WHERE m.post_id = p.id AND m.storage_key = t.v;
+
+-- Rows the array no longer listed.
+DELETE FROM post_media WHERE position IS NULL;
+
+ALTER TABLE post_media
+  ALTER COLUMN position SET NOT NULL,
+  ALTER COLUMN kind DROP DEFAULT,
+  ALTER COLUMN status SET DEFAULT 'pending';
+
+-- Deferred so a reorder can swap positions within one transaction.
+DROP INDEX post_media_post_id_idx;
+ALTER TABLE post_media
+  ADD CONSTRAINT post_media_post_id_position_key UNIQUE (post_id, position) DEFERRABLE INITIALLY DEFERRED;
+
+-- Pending rows the probe backfill isn't already working on.
+INSERT INTO jobs (kind, payload)
+SELECT 'probe_video', jsonb_build_object('kind', 'probe_video', 'post_id', m.post_id, 'video', m.storage_key)
+FROM post_media m
+WHERE m.status = 'pending'
+  AND NOT EXISTS (
+    SELECT 1 FROM jobs j
+    WHERE j.kind = 'probe_video' AND j.status IN ('pending', 'running') AND j.payload->>'video' = m.storage_key
+  );
+
+ALTER TABLE user_posts DROP COLUMN videos;
@@ -0,0 +1,2 @@
+-- Processed pictures stay processed; only drop jobs that haven't run yet.
+DELETE FROM jobs WHERE kind = 'process_avatar' AND status = 'pending';
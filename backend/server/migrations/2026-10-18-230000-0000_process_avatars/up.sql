@@ -0,0 +1,8 @@
+-- Profile pictures uploaded before they were processed still carry their
+-- EXIF metadata; the job queue re-encodes them into square variants.
+INSERT INTO jobs (kind, payload)
+SELECT 'process_avatar', jsonb_build_object('kind', 'process_avatar', 'user_id', u.id, 'profile_pic', u.profile_pic)
+FROM users u
+WHERE u.profile_pic IS NOT NULL
+  AND u.profile_pic <> ''
+  AND u.profile_pic NOT LIKE 'userprofile/avatars/%';
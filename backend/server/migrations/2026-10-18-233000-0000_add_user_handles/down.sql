@@ -0,0 +1,2 @@
+-- Mentions resolved by handle are kept; they point at the same users.
+ALTER TABLE users DROP COLUMN handle;
@@ -0,0 +1,39 @@
+-- Display names may contain spaces and needn't be unique, so mentions link
+-- to a handle instead: lowercase a-z, 0-9, '_' and '.', not starting or
+-- ending with '.'. Existing users get one derived from their name, the same
+-- way src/handles.rs does for new users who don't pick one.
+ALTER TABLE users ADD COLUMN handle VARCHAR(30);
+
+WITH derived AS (
+  SELECT id,
+         COALESCE(NULLIF(btrim(left(regexp_replace(lower(name), '[^a-z0-9_.]', '', 'g'), 24), '.'), ''), 'user') AS stem,
+         row_number() OVER (
+           PARTITION BY COALESCE(NULLIF(btrim(left(regexp_replace(lower(name), '[^a-z0-9_.]', '', 'g'), 24), '.'), ''), 'user')
+           ORDER BY created_at, id
+         ) AS n
+  FROM users
+)
+UPDATE users u
+SET handle = CASE WHEN d.n = 1 THEN d.stem ELSE d.stem || '_' || left(md5(u.id::text), 5) END
+FROM derived d
+WHERE d.id = u.id;
+
+ALTER TABLE users
+  ALTER COLUMN handle SET NOT NULL,
+  ADD CONSTRAINT users_handle_key UNIQUE (handle),
+  ADD CONSTRAINT users_handle_check CHECK (handle ~ '^[a-z0-9_]([a-z0-9_.]*[a-z0-9_])?$');
+
+-- Re-resolve existing mentions against handles, using the same rules as
+-- src/entities.rs.
+DELETE FROM post_mentions;
+
+INSERT INTO post_mentions (post_id, user_id)
+SELECT DISTINCT p.id, u.id
+FROM user_posts p,
+     regexp_matches(p.description, '(^|[^[:alnum:]_])@([[:alnum:]_.]{1,100})(?![[:alnum:]_.])', 'g') AS m
+JOIN users u ON u.handle = lower(rtrim(m[2], '.'))
+WHERE NOT EXISTS (
+    SELECT 1 FROM blocks b
+    WHERE (b.blocker_id = u.id AND b.blocked_id = p.user_id)
+       OR (b.blocker_id = p.user_id AND b.blocked_id = u.id)
+  );
@@ -3,7 +3,9 @@ use utoipa::Modify;
 use utoipa::openapi::security::{SecurityScheme, HttpBuilder, HttpAuthScheme}; // JWT scheme
 use crate::handlers::user_handler; 
 use crate::handlers::post_handler;
-use crate::models::{user, post};
+use crate::handlers::comment_handler;
+use crate::handlers::search_handler;
+use crate::handlers::session_handler;
 
 #[derive(OpenApi)]
 #[openapi(
@@ -23,14 +25,38 @@ use crate::models::{user, post};
         user_handler::following_list,
         user_handler::follow_requests,
         user_handler::handle_follow_request,
+        user_handler::block_user,
+        user_handler::unblock_user,
+        user_handler::delete_account,
+        user_handler::change_password,
+        session_handler::refresh,
+        session_handler::list_sessions,
+        session_handler::revoke_session,
+        session_handler::logout,
+        session_handler::logout_all,
         post_handler::create_user_post,
-        post_handler::get_user_posts
+        post_handler::get_user_posts,
+        post_handler::home_feed,
+        post_handler::list_user_posts,
+        post_handler::hashtag_posts,
+        search_handler::search,
+        post_handler::get_post,
+        post_handler::update_post,
+        post_handler::delete_post,
+        post_handler::like_post,
+        post_handler::unlike_post,
+        comment_handler::list_comments,
+        comment_handler::list_replies,
+        comment_handler::create_comment,
+        comment_handler::update_comment,
+        comment_handler::delete_comment
     ),
     components(
         schemas(
             crate::models::user::LoginRequest,
             crate::models::user::ForgotPasswordRequest,
             crate::models::user::ResetPasswordRequest,
+            crate::models::user::ChangePasswordRequest,
             crate::models::user::UserUpdateRequest,
             crate::models::user::FollowBody,
             crate::models::user::UserListItem,
@@ -44,12 +70,33 @@ use crate::models::{user, post};
             crate::models::user::User,
             crate::models::user::NewFollow,
             crate::models::user::UserUpdate,
-            crate::models::user::PaginationParams,
             crate::models::user::HandleFollowRequest,
             crate::models::post::NewUserPost,
+            crate::models::post::NewPostForm,
             crate::models::post::UserPostWithUser,
             crate::models::post::UserPostResponse,
-            crate::models::post::UserPost   
+            crate::models::media::MediaInfo,
+            crate::models::post::UserPost,
+            crate::models::post::UpdatePostRequest,
+            crate::pagination::PageParams,
+            crate::pagination::UserPage,
+            crate::pagination::FollowerPage,
+            crate::pagination::PendingRequestPage,
+            crate::pagination::PostPage,
+            crate::pagination::CommentPage,
+            crate::entities::Entity,
+            crate::entities::EntityKind,
+            crate::models::search::SearchType,
+            crate::models::search::SearchResults,
+            crate::models::search::UserSearchItem,
+            crate::models::search::HashtagSearchItem,
+            crate::models::comment::CreateCommentRequest,
+            crate::models::comment::UpdateCommentRequest,
+            crate::models::comment::CommentResponse,
+            crate::models::session::RefreshRequest,
+            crate::models::session::SessionInfo,
+            crate::models::session::TokenPair,
+            crate::errors::ErrorBody
         )
     ),
     modifiers(&ApiDocModifier)
@@ -0,0 +1,137 @@
+//! Profile pictures.
+//!
+//! Uploads are decoded and re-encoded like every picture (see
+//! [`crate::images`]). The picture is turned upright first, cropped to its
+//! centre square and stored at each of [`SIZES`], capped at the square's
+//! own size, under [`storage::avatar_key`]. `users.profile_pic` holds the
+//! [`DEFAULT_SIZE`] key; [`storage::avatar_variant`] gives the others and
+//! [`storage::avatar_url`] the URL clients are sent.
+//!
+//! Variants are JPEG: the `image` crate only writes lossless WebP, which
+//! comes out several times larger for photos.
+
+use std::path::Path;
+
+use actix_multipart::Field;
+use actix_web::web;
+use image::imageops::{self, FilterType};
+use serde::Deserialize;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::errors::ApiError;
+use crate::images;
+use crate::probe::ProbeError;
+use crate::storage;
+use crate::upload::{FileRules, MediaKind, Uploads};
+
+/// Edge lengths of the stored variants, in pixels.
+pub const SIZES: [u32; 3] = [64, 256, 512];
+
+/// The variant stored in `users.profile_pic` and returned by default.
+pub const DEFAULT_SIZE: u32 = 256;
+
+const JPEG_QUALITY: u8 = 85;
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AvatarParams {
+    /// Edge length of the returned profile pictures: 64, 256 (default) or
+    /// 512. Pictures smaller than the requested size come back at their own
+    /// size; pictures uploaded before resizing existed have one size only.
+    pub avatar_size: Option<u32>,
+}
+
+impl AvatarParams {
+    pub fn size(&self) -> Result<u32, ApiError> {
+        match self.avatar_size {
+            None => Ok(DEFAULT_SIZE),
+            Some(size) if SIZES.contains(&size) => Ok(size),
+            Some(_) => Err(ApiError::BadRequest(format!(
+                "avatar_size must be one of {}",
+                SIZES.map(|size| size.to_string()).join(", ")
+            ))),
+        }
+    }
+}
+
+/// Decodes the picture at `path`, already sniffed as `format`, and encodes
+/// every variant, largest first. A variant larger than the source square
+/// is stored at the square's own size instead of being upscaled. Blocking;
+/// call from `web::block` or a worker thread.
+pub fn render(path: &Path, format: crate::upload::Format) -> Result<Vec<(u32, Vec<u8>)>, ProbeError> {
+    let image = images::decode(path, format)?;
+    let (width, height) = (image.width(), image.height());
+    let side = width.min(height);
+    let square = images::flatten(image.crop_imm((width - side) / 2, (height - side) / 2, side, side));
+
+    // Each size is scaled from the one above it, so only the first resize
+    // reads the full-resolution picture.
+    let mut sizes = SIZES;
+    sizes.sort_unstable_by(|a, b| b.cmp(a));
+    let mut current = square;
+    let mut variants = Vec::with_capacity(sizes.len());
+    for size in sizes {
+        let edge = size.min(side);
+        if current.width() != edge {
+            current = imageops::resize(&current, edge, edge, FilterType::Lanczos3);
+        }
+        variants.push((size, images::encode_jpeg(&current, JPEG_QUALITY)?));
+    }
+
+    Ok(variants)
+}
+
+/// Reads a profile picture from `field` and stores its variants through
+/// `uploads`. Returns the key for `users.profile_pic`.
+pub async fn save(uploads: &mut Uploads, field: &mut Field, max_bytes: u64) -> Result<String, ApiError> {
+    let name = field.name().to_string();
+    let rules = FileRules { max_video_bytes: None, max_image_bytes: Some(max_bytes), max_files: 1 };
+    let received = uploads.receive(field, rules).await?;
+    let (path, format) = (received.path.clone(), received.format);
+
+    let variants = web::block(move || render(&path, format))
+        .await?
+        .map_err(|e| e.for_field(&name, MediaKind::Image))?;
+
+    let id = Uuid::new_v4().to_string();
+    for (size, bytes) in variants {
+        uploads.put_bytes(storage::avatar_key(&id, size), &bytes, "image/jpeg").await?;
+    }
+    Ok(storage::avatar_key(&id, DEFAULT_SIZE))
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{ImageFormat, RgbImage};
+
+    use super::*;
+    use crate::upload::Format;
+
+    /// Renders a `width`×`height` PNG and returns each variant's size and
+    /// edge length.
+    fn edges(width: u32, height: u32) -> Vec<(u32, u32)> {
+        let path = std::env::temp_dir().join(format!("avatar-test-{}.png", Uuid::new_v4()));
+        RgbImage::new(width, height).save_with_format(&path, ImageFormat::Png).unwrap();
+        let variants = render(&path, Format::Png);
+        std::fs::remove_file(&path).unwrap();
+
+        variants
+            .unwrap()
+            .into_iter()
+            .map(|(size, bytes)| (size, image::load_from_memory(&bytes).unwrap().width()))
+            .collect()
+    }
+
+    #[test]
+    fn variants_are_cropped_and_never_upscaled() {
+        let cases = [
+            ((1000, 800), [(512, 512), (256, 256), (64, 64)]),
+            ((300, 200), [(512, 200), (256, 200), (64, 64)]),
+            ((40, 40), [(512, 40), (256, 40), (64, 40)]),
+        ];
+        for ((width, height), expected) in cases {
+            assert_eq!(edges(width, height), expected, "{}x{}", width, height);
+        }
+    }
+}
@@ -0,0 +1,643 @@
+use serde::Deserialize;
+use std::env;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+/// Default location of the optional TOML config file. Override with `CONFIG_FILE`.
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub auth: AuthConfig,
+    pub smtp: SmtpConfig,
+    pub mail: MailConfig,
+    pub storage: StorageConfig,
+    pub jobs: JobsConfig,
+    /// Base URL of the web client, used to build links in emails.
+    pub frontend_url: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+    pub cors_origins: Vec<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct DatabaseConfig {
+    pub url: String,
+    pub pool_size: u32,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct AuthConfig {
+    pub jwt_secret: String,
+    /// Lifetime of access tokens. Keep this short; clients renew via `/refresh`.
+    pub access_token_ttl_minutes: i64,
+    pub refresh_token_ttl_days: i64,
+    /// Lifetime of the signed media URLs handed out with posts.
+    pub media_url_ttl_minutes: i64,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct SmtpConfig {
+    pub host: String,
+    pub username: String,
+    pub password: String,
+    pub from: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MailBackend {
+    Smtp,
+    Spool,
+}
+
+impl std::str::FromStr for MailBackend {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "smtp" => Ok(MailBackend::Smtp),
+            "spool" => Ok(MailBackend::Spool),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MailConfig {
+    pub backend: MailBackend,
+    /// Directory the `spool` backend writes `.eml` files into.
+    pub spool_dir: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum StorageBackend {
+    Local,
+    S3,
+}
+
+impl std::str::FromStr for StorageBackend {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_ascii_lowercase().as_str() {
+            "local" => Ok(StorageBackend::Local),
+            "s3" => Ok(StorageBackend::S3),
+            _ => Err(()),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct StorageConfig {
+    pub backend: StorageBackend,
+    /// Directory the `local` backend keeps objects under.
+    pub local_root: String,
+    pub s3: S3Config,
+    /// Per-file upload caps, in bytes.
+    pub max_video_bytes: u64,
+    pub max_image_bytes: u64,
+    /// Videos and images together.
+    #[serde(alias = "max_videos_per_post")]
+    pub max_media_per_post: usize,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct S3Config {
+    /// Service URL, e.g. `https://s3.eu-west-1.amazonaws.com` or a MinIO host.
+    pub endpoint: String,
+    pub bucket: String,
+    pub region: String,
+    pub access_key: String,
+    pub secret_key: String,
+    /// Address the bucket as `endpoint/bucket` rather than `bucket.endpoint`.
+    /// MinIO and most self-hosted services need this.
+    pub path_style: bool,
+    /// Lifetime of the URLs media requests are redirected to.
+    pub presign_ttl_secs: u64,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct JobsConfig {
+    /// Worker threads started inside the HTTP server. Set to 0 when running
+    /// `server worker` as a separate process instead.
+    pub workers: usize,
+    pub poll_interval_ms: u64,
+    /// How long a job may stay `running` before another worker reclaims it.
+    pub lease_secs: i64,
+    pub backoff_base_secs: i64,
+    pub backoff_max_secs: i64,
+    pub ffmpeg_path: String,
+    /// ffmpeg runs longer than this are killed and the job retried.
+    pub ffmpeg_timeout_secs: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            server: ServerConfig::default(),
+            database: DatabaseConfig::default(),
+            auth: AuthConfig::default(),
+            smtp: SmtpConfig::default(),
+            mail: MailConfig::default(),
+            storage: StorageConfig::default(),
+            jobs: JobsConfig::default(),
+            frontend_url: "http://localhost:5173".to_string(),
+        }
+    }
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        Self {
+            host: "127.0.0.1".to_string(),
+            port: 8081,
+            cors_origins: vec![
+                "http://localhost:5173".to_string(),
+                "http://127.0.0.1:5173".to_string(),
+                "http://127.0.0.1:8081".to_string(),
+            ],
+        }
+    }
+}
+
+impl Default for DatabaseConfig {
+    fn default() -> Self {
+        Self {
+            url: String::new(),
+            pool_size: 10,
+        }
+    }
+}
+
+impl Default for AuthConfig {
+    fn default() -> Self {
+        Self {
+            jwt_secret: String::new(),
+            access_token_ttl_minutes: 15,
+            refresh_token_ttl_days: 30,
+            media_url_ttl_minutes: 60,
+        }
+    }
+}
+
+impl SmtpConfig {
+    /// Address used in the `From` header: `from`, falling back to the SMTP login.
+    pub fn sender(&self) -> &str {
+        if !self.from.is_empty() {
+            &self.from
+        } else if !self.username.is_empty() {
+            &self.username
+        } else {
+            "no-reply@localhost"
+        }
+    }
+}
+
+impl Default for MailConfig {
+    fn default() -> Self {
+        Self {
+            backend: MailBackend::Smtp,
+            spool_dir: "./files/mail_spool".to_string(),
+        }
+    }
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self {
+            backend: StorageBackend::Local,
+            local_root: "./files".to_string(),
+            s3: S3Config::default(),
+            max_video_bytes: 200 * 1024 * 1024,
+            max_image_bytes: 10 * 1024 * 1024,
+            max_media_per_post: 10,
+        }
+    }
+}
+
+impl Default for S3Config {
+    fn default() -> Self {
+        Self {
+            endpoint: String::new(),
+            bucket: String::new(),
+            region: "us-east-1".to_string(),
+            access_key: String::new(),
+            secret_key: String::new(),
+            path_style: true,
+            presign_ttl_secs: 900,
+        }
+    }
+}
+
+impl Default for JobsConfig {
+    fn default() -> Self {
+        Self {
+            workers: 2,
+            poll_interval_ms: 1000,
+            lease_secs: 600,
+            backoff_base_secs: 10,
+            backoff_max_secs: 3600,
+            ffmpeg_path: "ffmpeg".to_string(),
+            ffmpeg_timeout_secs: 120,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(String, std::io::Error),
+    Parse(String, toml::de::Error),
+    Env(&'static str, String),
+    Invalid(Vec<String>),
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(path, e) => write!(f, "failed to read config file {}: {}", path, e),
+            ConfigError::Parse(path, e) => write!(f, "failed to parse config file {}: {}", path, e),
+            ConfigError::Env(key, value) => write!(f, "invalid value for {}: {:?}", key, value),
+            ConfigError::Invalid(problems) => write!(f, "invalid configuration: {}", problems.join("; ")),
+        }
+    }
+}
+
+impl std::error::Error for ConfigError {}
+
+impl Config {
+    /// Builds the configuration from defaults, then the optional TOML file, then
+    /// environment variables (including `.env`), and validates the result.
+    pub fn load() -> Result<Self, ConfigError> {
+        dotenvy::dotenv().ok();
+
+        let explicit_path = env::var("CONFIG_FILE").ok();
+        let path = explicit_path.clone().unwrap_or_else(|| DEFAULT_CONFIG_FILE.to_string());
+
+        let mut config = if explicit_path.is_some() || Path::new(&path).exists() {
+            Self::from_file(&path)?
+        } else {
+            Config::default()
+        };
+
+        config.apply_env()?;
+        config.validate()?;
+        Ok(config)
+    }
+
+    pub fn from_file(path: &str) -> Result<Self, ConfigError> {
+        let raw = fs::read_to_string(path).map_err(|e| ConfigError::Io(path.to_string(), e))?;
+        toml::from_str(&raw).map_err(|e| ConfigError::Parse(path.to_string(), e))
+    }
+
+    fn apply_env(&mut self) -> Result<(), ConfigError> {
+        self.apply_vars(&Vars(|key: &str| env::var(key).ok()))
+    }
+
+    fn apply_vars(&mut self, vars: &Vars<impl Fn(&str) -> Option<String>>) -> Result<(), ConfigError> {
+        if let Some(v) = vars.string("SERVER_HOST") {
+            self.server.host = v;
+        }
+        if let Some(v) = vars.parsed("SERVER_PORT")? {
+            self.server.port = v;
+        }
+        if let Some(v) = vars.string("CORS_ORIGINS") {
+            self.server.cors_origins = split_list(&v);
+        }
+        if let Some(v) = vars.string("DATABASE_URL") {
+            self.database.url = v;
+        }
+        if let Some(v) = vars.parsed("DATABASE_POOL_SIZE")? {
+            self.database.pool_size = v;
+        }
+        if let Some(v) = vars.string("JWT_SECRET") {
+            self.auth.jwt_secret = v;
+        }
+        if let Some(v) = vars.parsed("JWT_ACCESS_TTL_MINUTES")? {
+            self.auth.access_token_ttl_minutes = v;
+        }
+        if let Some(v) = vars.parsed("JWT_REFRESH_TTL_DAYS")? {
+            self.auth.refresh_token_ttl_days = v;
+        }
+        if let Some(v) = vars.parsed("MEDIA_URL_TTL_MINUTES")? {
+            self.auth.media_url_ttl_minutes = v;
+        }
+        if let Some(v) = vars.string("SMTP_HOST") {
+            self.smtp.host = v;
+        }
+        if let Some(v) = vars.string("SMTP_USERNAME") {
+            self.smtp.username = v;
+        }
+        if let Some(v) = vars.string("SMTP_PASSWORD") {
+            self.smtp.password = v;
+        }
+        if let Some(v) = vars.string("SMTP_FROM") {
+            self.smtp.from = v;
+        }
+        if let Some(v) = vars.parsed("MAIL_BACKEND")? {
+            self.mail.backend = v;
+        }
+        if let Some(v) = vars.string("MAIL_SPOOL_DIR") {
+            self.mail.spool_dir = v;
+        }
+        if let Some(v) = vars.parsed("STORAGE_BACKEND")? {
+            self.storage.backend = v;
+        }
+        if let Some(v) = vars.string("STORAGE_LOCAL_ROOT") {
+            self.storage.local_root = v;
+        }
+        if let Some(v) = vars.string("S3_ENDPOINT") {
+            self.storage.s3.endpoint = v;
+        }
+        if let Some(v) = vars.string("S3_BUCKET") {
+            self.storage.s3.bucket = v;
+        }
+        if let Some(v) = vars.string("S3_REGION") {
+            self.storage.s3.region = v;
+        }
+        if let Some(v) = vars.string("S3_ACCESS_KEY") {
+            self.storage.s3.access_key = v;
+        }
+        if let Some(v) = vars.string("S3_SECRET_KEY") {
+            self.storage.s3.secret_key = v;
+        }
+        if let Some(v) = vars.parsed("S3_PATH_STYLE")? {
+            self.storage.s3.path_style = v;
+        }
+        if let Some(v) = vars.parsed("S3_PRESIGN_TTL_SECS")? {
+            self.storage.s3.presign_ttl_secs = v;
+        }
+        if let Some(v) = vars.parsed("UPLOAD_MAX_VIDEO_BYTES")? {
+            self.storage.max_video_bytes = v;
+        }
+        if let Some(v) = vars.parsed("UPLOAD_MAX_IMAGE_BYTES")? {
+            self.storage.max_image_bytes = v;
+        }
+        if let Some(v) = vars.parsed("UPLOAD_MAX_MEDIA_PER_POST")? {
+            self.storage.max_media_per_post = v;
+        }
+        if let Some(v) = vars.parsed("JOBS_WORKERS")? {
+            self.jobs.workers = v;
+        }
+        if let Some(v) = vars.parsed("JOBS_POLL_INTERVAL_MS")? {
+            self.jobs.poll_interval_ms = v;
+        }
+        if let Some(v) = vars.parsed("JOBS_LEASE_SECS")? {
+            self.jobs.lease_secs = v;
+        }
+        if let Some(v) = vars.parsed("JOBS_BACKOFF_BASE_SECS")? {
+            self.jobs.backoff_base_secs = v;
+        }
+        if let Some(v) = vars.parsed("JOBS_BACKOFF_MAX_SECS")? {
+            self.jobs.backoff_max_secs = v;
+        }
+        if let Some(v) = vars.string("FFMPEG_PATH") {
+            self.jobs.ffmpeg_path = v;
+        }
+        if let Some(v) = vars.parsed("FFMPEG_TIMEOUT_SECS")? {
+            self.jobs.ffmpeg_timeout_secs = v;
+        }
+        if let Some(v) = vars.string("FRONTEND_URL") {
+            self.frontend_url = v;
+        }
+        Ok(())
+    }
+
+    /// Collects every problem at once so a bad deploy reports all of them.
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+
+        if self.server.host.trim().is_empty() {
+            problems.push("server.host must not be empty".to_string());
+        }
+        if self.server.port == 0 {
+            problems.push("server.port must be non-zero".to_string());
+        }
+        for origin in &self.server.cors_origins {
+            if !is_http_url(origin) {
+                problems.push(format!("server.cors_origins entry {:?} is not an http(s) URL", origin));
+            }
+        }
+        if self.database.url.trim().is_empty() {
+            problems.push("database.url (DATABASE_URL) must be set".to_string());
+        }
+        if self.database.pool_size == 0 {
+            problems.push("database.pool_size must be at least 1".to_string());
+        }
+        if self.auth.jwt_secret.trim().is_empty() {
+            problems.push("auth.jwt_secret (JWT_SECRET) must be set".to_string());
+        }
+        if self.auth.access_token_ttl_minutes <= 0
+            || self.auth.refresh_token_ttl_days <= 0
+            || self.auth.media_url_ttl_minutes <= 0
+        {
+            problems.push("auth token lifetimes must be positive".to_string());
+        }
+        if self.mail.backend == MailBackend::Smtp && self.smtp.host.trim().is_empty() {
+            problems.push("smtp.host (SMTP_HOST) must be set".to_string());
+        }
+        if self.smtp.sender().parse::<lettre::message::Mailbox>().is_err() {
+            problems.push(format!("smtp.from {:?} is not a valid mailbox", self.smtp.sender()));
+        }
+        if self.mail.backend == MailBackend::Spool && self.mail.spool_dir.trim().is_empty() {
+            problems.push("mail.spool_dir must not be empty".to_string());
+        }
+        if self.storage.backend == StorageBackend::Local && self.storage.local_root.trim().is_empty() {
+            problems.push("storage.local_root must not be empty".to_string());
+        }
+        if self.storage.backend == StorageBackend::S3 {
+            let s3 = &self.storage.s3;
+            if !is_http_url(&s3.endpoint) {
+                problems.push(format!("storage.s3.endpoint {:?} is not an http(s) URL", s3.endpoint));
+            }
+            if s3.bucket.trim().is_empty() || s3.region.trim().is_empty() {
+                problems.push("storage.s3.bucket and storage.s3.region must be set".to_string());
+            }
+            if s3.access_key.is_empty() || s3.secret_key.is_empty() {
+                problems.push("storage.s3.access_key and storage.s3.secret_key must be set".to_string());
+            }
+            if !(1..=7 * 24 * 60 * 60).contains(&s3.presign_ttl_secs) {
+                problems.push("storage.s3.presign_ttl_secs must be between 1 second and 7 days".to_string());
+            }
+        }
+        if self.storage.max_video_bytes == 0 || self.storage.max_image_bytes == 0 || self.storage.max_media_per_post == 0 {
+            problems.push("storage upload limits must be positive".to_string());
+        }
+        if self.jobs.poll_interval_ms == 0 {
+            problems.push("jobs.poll_interval_ms must be positive".to_string());
+        }
+        if self.jobs.lease_secs <= 0 || self.jobs.backoff_base_secs <= 0 || self.jobs.backoff_max_secs <= 0 {
+            problems.push("jobs lease and backoff durations must be positive".to_string());
+        }
+        if self.jobs.ffmpeg_timeout_secs == 0 || self.jobs.ffmpeg_timeout_secs as i64 >= self.jobs.lease_secs {
+            problems.push("jobs.ffmpeg_timeout_secs must be positive and shorter than jobs.lease_secs".to_string());
+        }
+        if !is_http_url(&self.frontend_url) {
+            problems.push(format!("frontend_url {:?} is not an http(s) URL", self.frontend_url));
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+
+    pub fn bind_addr(&self) -> (String, u16) {
+        (self.server.host.clone(), self.server.port)
+    }
+}
+
+/// Looks up environment variables; tests pass a map instead of the process
+/// environment. Blank values count as unset.
+struct Vars<F>(F);
+
+impl<F: Fn(&str) -> Option<String>> Vars<F> {
+    fn string(&self, key: &str) -> Option<String> {
+        (self.0)(key).filter(|v| !v.trim().is_empty())
+    }
+
+    fn parsed<T: std::str::FromStr>(&self, key: &'static str) -> Result<Option<T>, ConfigError> {
+        match self.string(key) {
+            Some(v) => v.trim().parse().map(Some).map_err(|_| ConfigError::Env(key, v)),
+            None => Ok(None),
+        }
+    }
+}
+
+fn split_list(value: &str) -> Vec<String> {
+    value
+        .split(',')
+        .map(|s| s.trim().to_string())
+        .filter(|s| !s.is_empty())
+        .collect()
+}
+
+fn is_http_url(value: &str) -> bool {
+    value.starts_with("http://") || value.starts_with("https://")
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn vars(pairs: &[(&str, &str)]) -> Vars<impl Fn(&str) -> Option<String>> {
+        let map: HashMap<String, String> = pairs.iter().map(|(k, v)| (k.to_string(), v.to_string())).collect();
+        Vars(move |key: &str| map.get(key).cloned())
+    }
+
+    fn problems(config: &Config) -> Vec<String> {
+        match config.validate() {
+            Ok(()) => Vec::new(),
+            Err(ConfigError::Invalid(problems)) => problems,
+            Err(e) => panic!("unexpected error: {}", e),
+        }
+    }
+
+    #[test]
+    fn env_overrides_toml_which_overrides_defaults() {
+        let mut config: Config = toml::from_str(
+            r#"
+            [server]
+            host = "0.0.0.0"
+            port = 9000
+
+            [database]
+            url = "postgresql://toml/db"
+            "#,
+        )
+        .unwrap();
+        // Sections and fields the file leaves out keep their defaults.
+        assert_eq!(config.database.pool_size, DatabaseConfig::default().pool_size);
+        assert_eq!(config.jobs.workers, JobsConfig::default().workers);
+
+        config
+            .apply_vars(&vars(&[
+                ("SERVER_PORT", "9100"),
+                ("DATABASE_URL", "  "),
+                ("JWT_SECRET", "from-env"),
+                ("CORS_ORIGINS", "https://a.example, ,https://b.example"),
+                ("JOBS_LEASE_SECS", "900"),
+                ("JOBS_BACKOFF_BASE_SECS", "5"),
+                ("JOBS_BACKOFF_MAX_SECS", "60"),
+            ]))
+            .unwrap();
+
+        assert_eq!(config.server.port, 9100);
+        assert_eq!(config.server.host, "0.0.0.0");
+        assert_eq!(config.database.url, "postgresql://toml/db", "blank variables are ignored");
+        assert_eq!(config.auth.jwt_secret, "from-env");
+        assert_eq!(config.server.cors_origins, ["https://a.example", "https://b.example"]);
+        assert_eq!((config.jobs.lease_secs, config.jobs.backoff_base_secs, config.jobs.backoff_max_secs), (900, 5, 60));
+    }
+
+    #[test]
+    fn unparsable_env_values_name_the_variable() {
+        let mut config = Config::default();
+        match config.apply_vars(&vars(&[("SERVER_PORT", "eighty")])) {
+            Err(ConfigError::Env(key, value)) => assert_eq!((key, value.as_str()), ("SERVER_PORT", "eighty")),
+            other => panic!("expected an env error, got {:?}", other),
+        }
+        assert!(matches!(config.apply_vars(&vars(&[("MAIL_BACKEND", "pigeon")])), Err(ConfigError::Env("MAIL_BACKEND", _))));
+    }
+
+    #[test]
+    fn example_file_is_valid() {
+        let config: Config = toml::from_str(include_str!("../config.example.toml")).unwrap();
+        assert_eq!(problems(&config), Vec::<String>::new());
+    }
+
+    #[test]
+    fn validate_reports_every_problem() {
+        let mut config: Config = toml::from_str(include_str!("../config.example.toml")).unwrap();
+        config.auth.jwt_secret = " ".to_string();
+        config.database.pool_size = 0;
+        config.auth.refresh_token_ttl_days = 0;
+        config.storage.max_media_per_post = 0;
+        config.jobs.poll_interval_ms = 0;
+        config.frontend_url = "localhost".to_string();
+
+        assert_eq!(
+            problems(&config),
+            [
+                "database.pool_size must be at least 1",
+                "auth.jwt_secret (JWT_SECRET) must be set",
+                "auth token lifetimes must be positive",
+                "storage upload limits must be positive",
+                "jobs.poll_interval_ms must be positive",
+                "frontend_url \"localhost\" is not an http(s) URL",
+            ]
+        );
+    }
+
+    #[test]
+    fn defaults_alone_need_the_secrets_filled_in() {
+        assert_eq!(
+            problems(&Config::default()),
+            [
+                "database.url (DATABASE_URL) must be set",
+                "auth.jwt_secret (JWT_SECRET) must be set",
+                "smtp.host (SMTP_HOST) must be set",
+            ]
+        );
+    }
+}
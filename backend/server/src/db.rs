@@ -1,17 +1,14 @@
 use diesel::r2d2::{self, ConnectionManager};
 use diesel::pg::PgConnection;
-use dotenvy::dotenv;
-use std::env;
 
-pub type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
+use crate::config::DatabaseConfig;
 
-pub fn connection() -> DbPool {
-   dotenv().ok();
+pub type DbPool = r2d2::Pool<ConnectionManager<PgConnection>>;
 
-    let database_url = env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let manager = ConnectionManager::<PgConnection>::new(database_url);
+pub fn connection(config: &DatabaseConfig) -> DbPool {
+    let manager = ConnectionManager::<PgConnection>::new(&config.url);
     r2d2::Pool::builder()
+        .max_size(config.pool_size)
         .build(manager)
         .expect("Failed to create pool.")
 }
-
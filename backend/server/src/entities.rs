@@ -0,0 +1,344 @@
+//! `#hashtags` and `@mentions` in post descriptions.
+//!
+//! A sigil starts an entity when it is not glued to a preceding word
+//! character, so `a#b` and `me@example.com` are plain text.
+//!
+//! - Hashtags are letters, digits and `_`. All-digit tags like `#1` are
+//!   ignored. Tags are stored lowercased, so `#Rust` and `#rust` match.
+//! - Mentions may also contain `.`, but a trailing `.` ends the sentence, not
+//!   the handle. A mention links to the user with that handle (see
+//!   [`crate::handles`]), unless either has blocked the other. Display names
+//!   are never matched: they may contain spaces, so `@John Smith` can't be
+//!   told apart from a mention of `@john`.
+//!
+//! The migration backfilling existing posts uses the same rules.
+
+use diesel::prelude::*;
+use serde::Serialize;
+use std::collections::HashMap;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::policy;
+use crate::schema::{hashtags, post_hashtags, post_mentions, users};
+
+/// Longest tag or name, in characters, that is recognised.
+pub const MAX_ENTITY_LEN: usize = 100;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum EntityKind {
+    Hashtag,
+    Mention,
+}
+
+#[derive(Debug, Clone, Serialize, ToSchema)]
+pub struct Entity {
+    pub kind: EntityKind,
+    /// The tag or name as written, without the `#` or `@`.
+    pub value: String,
+    /// Offsets into the description in UTF-16 code units, the way JavaScript
+    /// indexes strings. The range includes the `#` or `@`.
+    pub start: usize,
+    pub end: usize,
+    /// The mentioned user, when the mention resolved to one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<Uuid>,
+}
+
+impl Entity {
+    /// Lowercased value, as stored and looked up.
+    pub fn key(&self) -> String {
+        self.value.to_lowercase()
+    }
+}
+
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_'
+}
+
+/// Finds every hashtag and mention in `text`, in order.
+pub fn parse(text: &str) -> Vec<Entity> {
+    let chars: Vec<char> = text.chars().collect();
+    let mut entities = Vec::new();
+
+    let mut i = 0;
+    let mut offset = 0;
+    let mut prev: Option<char> = None;
+
+    while i < chars.len() {
+        let c = chars[i];
+        let kind = match c {
+            '#' => Some(EntityKind::Hashtag),
+            '@' => Some(EntityKind::Mention),
+            _ => None,
+        };
+
+        let Some(kind) = kind.filter(|_| !prev.is_some_and(is_word_char)) else {
+            offset += c.len_utf16();
+            prev = Some(c);
+            i += 1;
+            continue;
+        };
+
+        let allowed = |c: char| is_word_char(c) || (kind == EntityKind::Mention && c == '.');
+        let mut len = chars[i + 1..].iter().take_while(|&&c| allowed(c)).count();
+        let run = len;
+        if kind == EntityKind::Mention {
+            len -= chars[i + 1..i + 1 + len].iter().rev().take_while(|&&c| c == '.').count();
+        }
+
+        let value: String = chars[i + 1..i + 1 + len].iter().collect();
+        let valid = (1..=MAX_ENTITY_LEN).contains(&len)
+            && run <= MAX_ENTITY_LEN
+            && !(kind == EntityKind::Hashtag && value.chars().all(|c| c.is_ascii_digit()));
+
+        if valid {
+            entities.push(Entity {
+                kind,
+                start: offset,
+                end: offset + 1 + value.encode_utf16().count(),
+                value,
+                user_id: None,
+            });
+        }
+
+        // Skip the whole run so a rejected entity doesn't start another one.
+        for &c in &chars[i..=i + run] {
+            offset += c.len_utf16();
+        }
+        prev = Some(chars[i + run]);
+        i += run + 1;
+    }
+
+    entities
+}
+
+/// Mentioned handle -> user, for each post.
+pub type MentionLinks = HashMap<Uuid, HashMap<String, Uuid>>;
+
+/// Users linked from each of `post_ids`, keyed by their current handle.
+pub fn linked_mentions(conn: &mut PgConnection, post_ids: Vec<Uuid>) -> QueryResult<MentionLinks> {
+    let rows = post_mentions::table
+        .inner_join(users::table)
+        .filter(post_mentions::post_id.eq_any(post_ids))
+        .select((post_mentions::post_id, users::handle, users::id))
+        .load::<(Uuid, String, Uuid)>(conn)?;
+
+    let mut links = MentionLinks::new();
+    for (post_id, handle, user_id) in rows {
+        links.entry(post_id).or_default().insert(handle, user_id);
+    }
+    Ok(links)
+}
+
+/// Parses `description` and fills in the users its mentions link to.
+pub fn resolve(description: &str, links: Option<&HashMap<String, Uuid>>) -> Vec<Entity> {
+    let mut entities = parse(description);
+    if let Some(links) = links {
+        for e in entities.iter_mut().filter(|e| e.kind == EntityKind::Mention) {
+            e.user_id = links.get(&e.key()).copied();
+        }
+    }
+    entities
+}
+
+/// Replaces the post's hashtag and mention rows with the ones found in
+/// `description`. Call inside the transaction that writes the description.
+pub fn sync_post(conn: &mut PgConnection, post_id: Uuid, author: Uuid, description: &str) -> QueryResult<Vec<Entity>> {
+    let entities = parse(description);
+
+    let mut tags: Vec<String> = entities
+        .iter()
+        .filter(|e| e.kind == EntityKind::Hashtag)
+        .map(Entity::key)
+        .collect();
+    tags.sort();
+    tags.dedup();
+
+    let mut names: Vec<String> = entities
+        .iter()
+        .filter(|e| e.kind == EntityKind::Mention)
+        .map(Entity::key)
+        .collect();
+    names.sort();
+    names.dedup();
+
+    diesel::delete(post_hashtags::table.filter(post_hashtags::post_id.eq(post_id))).execute(conn)?;
+    diesel::delete(post_mentions::table.filter(post_mentions::post_id.eq(post_id))).execute(conn)?;
+
+    if !tags.is_empty() {
+        diesel::insert_into(hashtags::table)
+            .values(tags.iter().map(|t| hashtags::tag.eq(t)).collect::<Vec<_>>())
+            .on_conflict(hashtags::tag)
+            .do_nothing()
+            .execute(conn)?;
+
+        let ids = hashtags::table
+            .filter(hashtags::tag.eq_any(&tags))
+            .select(hashtags::id)
+            .load::<Uuid>(conn)?;
+
+        diesel::insert_into(post_hashtags::table)
+            .values(
+                ids.into_iter()
+                    .map(|id| (post_hashtags::post_id.eq(post_id), post_hashtags::hashtag_id.eq(id)))
+                    .collect::<Vec<_>>(),
+            )
+            .execute(conn)?;
+    }
+
+    let mut links = HashMap::new();
+    if !names.is_empty() {
+        let candidates = users::table
+            .filter(users::handle.eq_any(&names))
+            .select((users::handle, users::id))
+            .load::<(String, Uuid)>(conn)?;
+
+        for (handle, id) in candidates {
+            if !policy::is_blocked_between(conn, author, id)? {
+                links.insert(handle, id);
+            }
+        }
+
+        if !links.is_empty() {
+            diesel::insert_into(post_mentions::table)
+                .values(
+                    links
+                        .values()
+                        .map(|&id| (post_mentions::post_id.eq(post_id), post_mentions::user_id.eq(id)))
+                        .collect::<Vec<_>>(),
+                )
+                .execute(conn)?;
+        }
+    }
+
+    Ok(resolve(description, Some(&links)))
+}
+
+/// `tag` as stored: without a leading `#`, lowercased.
+pub fn normalize_tag(tag: &str) -> String {
+    tag.trim_start_matches('#').to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `#` or `@`, value, start, end.
+    type Expected<'a> = &'a [(char, &'a str, usize, usize)];
+
+    fn check(text: &str, expected: Expected) {
+        let found: Vec<(char, String, usize, usize)> = parse(text)
+            .into_iter()
+            .map(|e| {
+                let sigil = if e.kind == EntityKind::Hashtag { '#' } else { '@' };
+                (sigil, e.value, e.start, e.end)
+            })
+            .collect();
+        let expected: Vec<(char, String, usize, usize)> =
+            expected.iter().map(|&(sigil, value, start, end)| (sigil, value.to_string(), start, end)).collect();
+        assert_eq!(found, expected, "parsing {:?}", text);
+    }
+
+    #[test]
+    fn finds_hashtags_and_mentions() {
+        let cases: &[(&str, Expected)] = &[
+            ("#rust", &[('#', "rust", 0, 5)]),
+            ("hi #Rust and @ann", &[('#', "Rust", 3, 8), ('@', "ann", 13, 17)]),
+            ("#a_b #c1", &[('#', "a_b", 0, 4), ('#', "c1", 5, 8)]),
+            ("@ann.lee", &[('@', "ann.lee", 0, 8)]),
+            ("#日本語", &[('#', "日本語", 0, 4)]),
+            ("", &[]),
+            ("no entities here", &[]),
+        ];
+        for (text, expected) in cases {
+            check(text, expected);
+        }
+    }
+
+    #[test]
+    fn offsets_count_utf16_units() {
+        let cases: &[(&str, Expected)] = &[
+            // Emoji outside the BMP take two units.
+            ("😀 #tag", &[('#', "tag", 3, 7)]),
+            ("👍🏽👍🏽 @ann", &[('@', "ann", 9, 13)]),
+            // An astral letter is a word character and part of the tag.
+            ("#𝒳y", &[('#', "𝒳y", 0, 4)]),
+            ("𝒳 @ann", &[('@', "ann", 3, 7)]),
+            ("é #a", &[('#', "a", 2, 4)]),
+        ];
+        for (text, expected) in cases {
+            check(text, expected);
+        }
+    }
+
+    #[test]
+    fn trailing_punctuation_is_not_part_of_the_entity() {
+        let cases: &[(&str, Expected)] = &[
+            ("#tag!", &[('#', "tag", 0, 4)]),
+            ("(#tag)", &[('#', "tag", 1, 5)]),
+            ("#tag.", &[('#', "tag", 0, 4)]),
+            ("cc @ann.", &[('@', "ann", 3, 7)]),
+            ("@ann...", &[('@', "ann", 0, 4)]),
+            ("@ann, @bob?", &[('@', "ann", 0, 4), ('@', "bob", 6, 10)]),
+            ("#café☕", &[('#', "café", 0, 5)]),
+        ];
+        for (text, expected) in cases {
+            check(text, expected);
+        }
+    }
+
+    #[test]
+    fn glued_or_empty_sigils_are_text() {
+        let cases: &[(&str, Expected)] = &[
+            ("a#b", &[]),
+            ("me@example.com", &[]),
+            ("#a#b", &[('#', "a", 0, 2)]),
+            ("#", &[]),
+            ("@", &[]),
+            ("@.", &[]),
+            ("# tag", &[]),
+            ("##tag", &[('#', "tag", 1, 5)]),
+        ];
+        for (text, expected) in cases {
+            check(text, expected);
+        }
+    }
+
+    #[test]
+    fn all_digit_hashtags_are_ignored() {
+        let cases: &[(&str, Expected)] = &[
+            ("#1", &[]),
+            ("#2024 #1a #a1", &[('#', "1a", 6, 9), ('#', "a1", 10, 13)]),
+            ("#１２", &[('#', "１２", 0, 3)]),
+            ("@123", &[('@', "123", 0, 4)]),
+        ];
+        for (text, expected) in cases {
+            check(text, expected);
+        }
+    }
+
+    #[test]
+    fn entities_are_capped_at_max_len() {
+        let at_cap = "a".repeat(MAX_ENTITY_LEN);
+        let over_cap = "a".repeat(MAX_ENTITY_LEN + 1);
+        let end = MAX_ENTITY_LEN + 1;
+
+        check(&format!("#{}", at_cap), &[('#', &at_cap, 0, end)]);
+        check(&format!("@{}", at_cap), &[('@', &at_cap, 0, end)]);
+        // Too long is dropped whole; no entity starts partway through it.
+        check(&format!("#{} #ok", over_cap), &[('#', "ok", end + 2, end + 5)]);
+        check(&format!("@{}", over_cap), &[]);
+        // Trailing dots count towards the run.
+        check(&format!("@{}.", at_cap), &[]);
+        let short = &at_cap[1..];
+        check(&format!("@{}.", short), &[('@', short, 0, end - 1)]);
+    }
+
+    #[test]
+    fn key_is_lowercased() {
+        let entities = parse("#RuSt @Ann");
+        assert_eq!(entities.iter().map(Entity::key).collect::<Vec<_>>(), ["rust", "ann"]);
+    }
+}
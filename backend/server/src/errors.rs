@@ -0,0 +1,202 @@
+use std::fmt;
+
+use actix_web::{error::BlockingError, http::StatusCode, HttpResponse, ResponseError};
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use serde::Serialize;
+use serde_json::Value;
+use utoipa::ToSchema;
+
+/// Every handler error goes through this type so clients always get the same
+/// `{code, message, details}` body.
+#[derive(Debug)]
+pub enum ApiError {
+    BadRequest(String),
+    Validation { message: String, details: Value },
+    Unauthorized(String),
+    Forbidden(String),
+    NotFound(String),
+    Conflict(String),
+    PayloadTooLarge(String),
+    UnsupportedMedia(String),
+    Database(DieselError),
+    Pool(diesel::r2d2::PoolError),
+    Multipart(actix_multipart::MultipartError),
+    Hash(bcrypt::BcryptError),
+    Internal(String),
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct ErrorBody {
+    #[schema(example = "not_found")]
+    pub code: String,
+    #[schema(example = "User not found")]
+    pub message: String,
+    #[schema(value_type = Option<Object>)]
+    pub details: Option<Value>,
+}
+
+impl ApiError {
+    /// Stable machine-readable code. Clients may switch on these, so don't rename them.
+    pub fn code(&self) -> &'static str {
+        match self {
+            ApiError::BadRequest(_) => "bad_request",
+            ApiError::Validation { .. } => "validation_failed",
+            ApiError::Unauthorized(_) => "unauthorized",
+            ApiError::Forbidden(_) => "forbidden",
+            ApiError::NotFound(_) => "not_found",
+            ApiError::Conflict(_) => "conflict",
+            ApiError::PayloadTooLarge(_) => "payload_too_large",
+            ApiError::UnsupportedMedia(_) => "unsupported_media_type",
+            ApiError::Database(_) => "database_error",
+            ApiError::Pool(_) => "database_unavailable",
+            ApiError::Multipart(_) => "invalid_multipart",
+            ApiError::Hash(_) => "password_hash_failed",
+            ApiError::Internal(_) => "internal_error",
+        }
+    }
+
+    /// Message safe to show to clients. Internal failures are replaced by a
+    /// generic text; the real cause is logged instead.
+    pub fn public_message(&self) -> String {
+        match self {
+            ApiError::BadRequest(m)
+            | ApiError::Unauthorized(m)
+            | ApiError::Forbidden(m)
+            | ApiError::NotFound(m)
+            | ApiError::Conflict(m)
+            | ApiError::PayloadTooLarge(m)
+            | ApiError::UnsupportedMedia(m) => m.clone(),
+            ApiError::Validation { message, .. } => message.clone(),
+            ApiError::Multipart(e) => format!("Invalid multipart upload: {}", e),
+            ApiError::Database(_) => "Database error".to_string(),
+            ApiError::Pool(_) => "Database connection failed".to_string(),
+            ApiError::Hash(_) => "Password hashing failed".to_string(),
+            ApiError::Internal(_) => "Internal server error".to_string(),
+        }
+    }
+
+    pub fn validation(message: impl Into<String>, details: Value) -> Self {
+        ApiError::Validation { message: message.into(), details }
+    }
+}
+
+impl fmt::Display for ApiError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ApiError::Database(e) => write!(f, "database error: {}", e),
+            ApiError::Pool(e) => write!(f, "pool error: {}", e),
+            ApiError::Hash(e) => write!(f, "bcrypt error: {}", e),
+            ApiError::Internal(m) => write!(f, "internal error: {}", m),
+            other => write!(f, "{}", other.public_message()),
+        }
+    }
+}
+
+impl ResponseError for ApiError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            ApiError::BadRequest(_) | ApiError::Validation { .. } | ApiError::Multipart(_) => StatusCode::BAD_REQUEST,
+            ApiError::Unauthorized(_) => StatusCode::UNAUTHORIZED,
+            ApiError::Forbidden(_) => StatusCode::FORBIDDEN,
+            ApiError::NotFound(_) => StatusCode::NOT_FOUND,
+            ApiError::Conflict(_) => StatusCode::CONFLICT,
+            ApiError::PayloadTooLarge(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            ApiError::UnsupportedMedia(_) => StatusCode::UNSUPPORTED_MEDIA_TYPE,
+            ApiError::Pool(_) => StatusCode::SERVICE_UNAVAILABLE,
+            ApiError::Database(_) | ApiError::Hash(_) | ApiError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let status = self.status_code();
+        if status.is_server_error() {
+            eprintln!("❌ {}", self);
+        }
+
+        let details = match self {
+            ApiError::Validation { details, .. } => Some(details.clone()),
+            _ => None,
+        };
+
+        HttpResponse::build(status).json(ErrorBody {
+            code: self.code().to_string(),
+            message: self.public_message(),
+            details,
+        })
+    }
+}
+
+impl From<DieselError> for ApiError {
+    fn from(e: DieselError) -> Self {
+        match e {
+            DieselError::NotFound => ApiError::NotFound("Resource not found".to_string()),
+            DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+                ApiError::Conflict("Resource already exists".to_string())
+            }
+            DieselError::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, _) => {
+                ApiError::BadRequest("Referenced resource does not exist".to_string())
+            }
+            other => ApiError::Database(other),
+        }
+    }
+}
+
+impl From<diesel::r2d2::PoolError> for ApiError {
+    fn from(e: diesel::r2d2::PoolError) -> Self {
+        ApiError::Pool(e)
+    }
+}
+
+impl From<actix_multipart::MultipartError> for ApiError {
+    fn from(e: actix_multipart::MultipartError) -> Self {
+        ApiError::Multipart(e)
+    }
+}
+
+impl From<bcrypt::BcryptError> for ApiError {
+    fn from(e: bcrypt::BcryptError) -> Self {
+        ApiError::Hash(e)
+    }
+}
+
+impl From<BlockingError> for ApiError {
+    fn from(e: BlockingError) -> Self {
+        ApiError::Internal(format!("blocking task failed: {}", e))
+    }
+}
+
+impl From<std::io::Error> for ApiError {
+    fn from(e: std::io::Error) -> Self {
+        ApiError::Internal(format!("io error: {}", e))
+    }
+}
+
+impl From<jsonwebtoken::errors::Error> for ApiError {
+    fn from(e: jsonwebtoken::errors::Error) -> Self {
+        ApiError::Internal(format!("token error: {}", e))
+    }
+}
+
+impl From<validator::ValidationErrors> for ApiError {
+    fn from(e: validator::ValidationErrors) -> Self {
+        let details = serde_json::to_value(e.field_errors()).unwrap_or(Value::Null);
+        ApiError::Validation {
+            message: "Validation failed".to_string(),
+            details,
+        }
+    }
+}
+
+/// Makes actix's own extractor failures (bad JSON, query or path) use the
+/// same envelope as handler errors.
+pub fn json_error_handler(err: actix_web::error::JsonPayloadError, _req: &actix_web::HttpRequest) -> actix_web::Error {
+    ApiError::BadRequest(format!("Invalid JSON body: {}", err)).into()
+}
+
+pub fn query_error_handler(err: actix_web::error::QueryPayloadError, _req: &actix_web::HttpRequest) -> actix_web::Error {
+    ApiError::BadRequest(format!("Invalid query string: {}", err)).into()
+}
+
+pub fn path_error_handler(err: actix_web::error::PathError, _req: &actix_web::HttpRequest) -> actix_web::Error {
+    ApiError::BadRequest(format!("Invalid path parameter: {}", err)).into()
+}
@@ -0,0 +1,357 @@
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use diesel::prelude::*;
+use std::collections::HashMap;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::db::DbPool;
+use crate::errors::ApiError;
+use crate::middleware::AuthUser;
+use crate::models::comment::{
+    CommentResponse, CreateCommentRequest, NewPostComment, PostComment, UpdateCommentRequest,
+};
+use crate::pagination::{self, Cursor, Page, PageParams};
+use crate::policy;
+use crate::schema::{post_comments, user_posts, users};
+
+type CommentRow = (PostComment, String, Option<String>);
+
+/// Which comments a listing walks.
+enum Thread {
+    /// Top-level comments of a post.
+    Post(Uuid),
+    /// Replies to one comment.
+    Replies(Uuid),
+}
+
+fn comment_response((comment, user_name, profile_pic): CommentRow, reply_count: i64) -> CommentResponse {
+    CommentResponse {
+        id: comment.id,
+        post_id: comment.post_id,
+        user_id: comment.user_id,
+        user_name,
+        profile_pic,
+        parent_id: comment.parent_id,
+        body: comment.body,
+        created_at: comment.created_at,
+        updated_at: comment.updated_at,
+        reply_count,
+    }
+}
+
+fn find_comment(conn: &mut PgConnection, comment_id: Uuid) -> Result<PostComment, ApiError> {
+    post_comments::table
+        .filter(post_comments::id.eq(comment_id))
+        .select(PostComment::as_select())
+        .first(conn)
+        .optional()?
+        .ok_or_else(|| ApiError::NotFound("Comment not found".to_string()))
+}
+
+/// Replies under each of `parents`, leaving out those `hidden` wrote.
+fn reply_counts(conn: &mut PgConnection, parents: Vec<Uuid>, hidden: &[Uuid]) -> QueryResult<HashMap<Uuid, i64>> {
+    let rows = post_comments::table
+        .filter(post_comments::parent_id.eq_any(parents))
+        .filter(post_comments::user_id.ne_all(hidden))
+        .group_by(post_comments::parent_id)
+        .select((post_comments::parent_id.assume_not_null(), diesel::dsl::count_star()))
+        .load::<(Uuid, i64)>(conn)?;
+
+    Ok(rows.into_iter().collect())
+}
+
+/// One page of `thread` as `viewer` sees it: comments by users they have
+/// blocked, or been blocked by, are left out.
+fn load_thread(
+    conn: &mut PgConnection,
+    viewer: Uuid,
+    thread: Thread,
+    limit: i64,
+    after: Option<Cursor>,
+) -> Result<Page<CommentResponse>, ApiError> {
+    let hidden = policy::blocked_with(conn, viewer)?;
+
+    let mut q = post_comments::table
+        .inner_join(users::table)
+        .filter(post_comments::user_id.ne_all(&hidden))
+        .select((PostComment::as_select(), users::name, users::profile_pic))
+        .order((post_comments::created_at.desc(), post_comments::id.desc()))
+        .limit(limit + 1)
+        .into_boxed();
+
+    q = match thread {
+        Thread::Post(post_id) => q
+            .filter(post_comments::post_id.eq(post_id))
+            .filter(post_comments::parent_id.is_null()),
+        Thread::Replies(parent_id) => q.filter(post_comments::parent_id.eq(parent_id)),
+    };
+
+    if let Some(c) = after {
+        q = q.filter(pagination::keyset_before(post_comments::created_at, post_comments::id, &c));
+    }
+
+    let rows = q.load::<CommentRow>(conn)?;
+    let page = Page::from_rows(rows, limit, |(c, _, _)| Cursor::new(c.created_at, c.id));
+
+    let counts = reply_counts(conn, page.items.iter().map(|(c, _, _)| c.id).collect(), &hidden)?;
+
+    Ok(page.map(|row| {
+        let replies = counts.get(&row.0.id).copied().unwrap_or(0);
+        comment_response(row, replies)
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/user/auth/posts/{post_id}/comments",
+    params(
+        ("post_id" = Uuid, Path, description = "Post whose comments to list"),
+        PageParams
+    ),
+    responses(
+        (status = 200, description = "Top-level comments, newest first", body = CommentPage),
+        (status = 400, description = "Invalid cursor", body = ErrorBody),
+        (status = 401, description = "Unauthorized user", body = ErrorBody),
+        (status = 403, description = "Author's account is private", body = ErrorBody),
+        (status = 404, description = "Post not found", body = ErrorBody)
+    ),
+    tag = "Comments",
+    security(
+        ("bearerAuth" = [])
+    )
+)]
+
+pub async fn list_comments(
+    pool: web::Data<DbPool>,
+    auth: AuthUser,
+    path: web::Path<Uuid>,
+    query: web::Query<PageParams>,
+) -> Result<HttpResponse, ApiError> {
+    let post_id = path.into_inner();
+    let viewer = auth.id;
+    let limit = query.limit();
+    let after = query.cursor()?;
+
+    let page = web::block(move || {
+        let conn = &mut pool.get()?;
+        policy::require_post_access(conn, Some(viewer), post_id)?;
+        load_thread(conn, viewer, Thread::Post(post_id), limit, after)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(page))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/user/auth/comments/{comment_id}/replies",
+    params(
+        ("comment_id" = Uuid, Path, description = "Comment whose replies to list"),
+        PageParams
+    ),
+    responses(
+        (status = 200, description = "Replies, newest first", body = CommentPage),
+        (status = 400, description = "Invalid cursor", body = ErrorBody),
+        (status = 401, description = "Unauthorized user", body = ErrorBody),
+        (status = 403, description = "Author's account is private", body = ErrorBody),
+        (status = 404, description = "Comment not found", body = ErrorBody)
+    ),
+    tag = "Comments",
+    security(
+        ("bearerAuth" = [])
+    )
+)]
+
+pub async fn list_replies(
+    pool: web::Data<DbPool>,
+    auth: AuthUser,
+    path: web::Path<Uuid>,
+    query: web::Query<PageParams>,
+) -> Result<HttpResponse, ApiError> {
+    let comment_id = path.into_inner();
+    let viewer = auth.id;
+    let limit = query.limit();
+    let after = query.cursor()?;
+
+    let page = web::block(move || {
+        let conn = &mut pool.get()?;
+        let comment = find_comment(conn, comment_id)?;
+        policy::require_post_access(conn, Some(viewer), comment.post_id)?;
+        load_thread(conn, viewer, Thread::Replies(comment_id), limit, after)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(page))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/user/auth/posts/{post_id}/comments",
+    params(
+        ("post_id" = Uuid, Path, description = "Post to comment on")
+    ),
+    request_body = CreateCommentRequest,
+    responses(
+        (status = 201, description = "Comment created", body = CommentResponse),
+        (status = 400, description = "Invalid body, or parent is a reply or on another post", body = ErrorBody),
+        (status = 401, description = "Unauthorized user", body = ErrorBody),
+        (status = 403, description = "Author's account is private", body = ErrorBody),
+        (status = 404, description = "Post or parent comment not found", body = ErrorBody)
+    ),
+    tag = "Comments",
+    security(
+        ("bearerAuth" = [])
+    )
+)]
+
+pub async fn create_comment(
+    pool: web::Data<DbPool>,
+    auth: AuthUser,
+    path: web::Path<Uuid>,
+    body: web::Json<CreateCommentRequest>,
+) -> Result<HttpResponse, ApiError> {
+    body.validate()?;
+    let body = body.into_inner();
+
+    let post_id = path.into_inner();
+    let author = auth.id;
+
+    let comment = web::block(move || {
+        let conn = &mut pool.get()?;
+        policy::require_post_access(conn, Some(author), post_id)?;
+
+        if let Some(parent_id) = body.parent_id {
+            let parent = find_comment(conn, parent_id)?;
+            if parent.post_id != post_id {
+                return Err(ApiError::BadRequest("Parent comment belongs to another post".to_string()));
+            }
+            if parent.parent_id.is_some() {
+                return Err(ApiError::BadRequest("Replies can't be replied to".to_string()));
+            }
+        }
+
+        diesel::insert_into(post_comments::table)
+            .values(&NewPostComment {
+                post_id,
+                user_id: author,
+                parent_id: body.parent_id,
+                body: body.body,
+            })
+            .returning(PostComment::as_returning())
+            .get_result(conn)
+            .map_err(ApiError::from)
+    })
+    .await??;
+
+    let row = (comment, auth.name.clone(), auth.profile_pic.clone());
+    Ok(HttpResponse::Created().json(comment_response(row, 0)))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/user/auth/comments/{comment_id}",
+    params(
+        ("comment_id" = Uuid, Path, description = "Comment to edit")
+    ),
+    request_body = UpdateCommentRequest,
+    responses(
+        (status = 200, description = "Updated comment", body = CommentResponse),
+        (status = 400, description = "Invalid body", body = ErrorBody),
+        (status = 401, description = "Unauthorized user", body = ErrorBody),
+        (status = 403, description = "Not the comment's author, or the post's author's account is private", body = ErrorBody),
+        (status = 404, description = "Comment or post not found", body = ErrorBody)
+    ),
+    tag = "Comments",
+    security(
+        ("bearerAuth" = [])
+    )
+)]
+
+pub async fn update_comment(
+    pool: web::Data<DbPool>,
+    auth: AuthUser,
+    path: web::Path<Uuid>,
+    body: web::Json<UpdateCommentRequest>,
+) -> Result<HttpResponse, ApiError> {
+    body.validate()?;
+    let body = body.into_inner();
+
+    let comment_id = path.into_inner();
+    let author = auth.id;
+
+    let (comment, replies) = web::block(move || {
+        let conn = &mut pool.get()?;
+        let comment = find_comment(conn, comment_id)?;
+
+        if comment.user_id != author {
+            return Err(ApiError::Forbidden("You can only edit your own comments".to_string()));
+        }
+        // The post may have gone private, or its author may have blocked the
+        // commenter, since the comment was written.
+        policy::require_post_access(conn, Some(author), comment.post_id)?;
+
+        let updated = diesel::update(post_comments::table.filter(post_comments::id.eq(comment_id)))
+            .set((
+                post_comments::body.eq(body.body),
+                post_comments::updated_at.eq(Some(Utc::now().naive_utc())),
+            ))
+            .returning(PostComment::as_returning())
+            .get_result(conn)?;
+
+        let hidden = policy::blocked_with(conn, author)?;
+        let replies = reply_counts(conn, vec![comment_id], &hidden)?.get(&comment_id).copied().unwrap_or(0);
+        Ok((updated, replies))
+    })
+    .await??;
+
+    let row = (comment, auth.name.clone(), auth.profile_pic.clone());
+    Ok(HttpResponse::Ok().json(comment_response(row, replies)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/user/auth/comments/{comment_id}",
+    params(
+        ("comment_id" = Uuid, Path, description = "Comment to delete; its replies go with it")
+    ),
+    responses(
+        (status = 200, description = "Comment deleted", body = serde_json::Value),
+        (status = 401, description = "Unauthorized user", body = ErrorBody),
+        (status = 403, description = "Neither the comment's author nor the post's author", body = ErrorBody),
+        (status = 404, description = "Comment not found", body = ErrorBody)
+    ),
+    tag = "Comments",
+    security(
+        ("bearerAuth" = [])
+    )
+)]
+
+pub async fn delete_comment(pool: web::Data<DbPool>, auth: AuthUser, path: web::Path<Uuid>) -> Result<HttpResponse, ApiError> {
+    let comment_id = path.into_inner();
+    let caller = auth.id;
+
+    web::block(move || {
+        let conn = &mut pool.get()?;
+        let comment = find_comment(conn, comment_id)?;
+
+        // Post authors moderate the comments under their posts.
+        let post_author = user_posts::table
+            .filter(user_posts::id.eq(comment.post_id))
+            .select(user_posts::user_id)
+            .first::<Option<Uuid>>(conn)?;
+
+        if comment.user_id != caller && post_author != Some(caller) {
+            return Err(ApiError::Forbidden("You can't delete this comment".to_string()));
+        }
+
+        diesel::delete(post_comments::table.filter(post_comments::id.eq(comment_id))).execute(conn)?;
+        Ok(())
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "Comment deleted"
+    })))
+}
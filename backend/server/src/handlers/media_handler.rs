@@ -0,0 +1,205 @@
+use actix_web::http::header::{self, EntityTag, HttpDate, IfModifiedSince, IfNoneMatch, IfRange};
+use actix_web::http::Method;
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse, HttpResponseBuilder};
+use diesel::prelude::*;
+use serde::Deserialize;
+use std::time::{Duration, UNIX_EPOCH};
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::errors::ApiError;
+use crate::middleware::MaybeAuthUser;
+use crate::policy;
+use crate::storage::{self, ByteRange, MediaObject, MediaStore, ObjectMeta, StoreError};
+use crate::tokens;
+
+/// Signature from [`tokens::signed_media_url`].
+#[derive(Deserialize)]
+pub struct MediaQuery {
+    expires: Option<i64>,
+    signature: Option<String>,
+}
+
+fn not_found() -> ApiError {
+    ApiError::NotFound("File not found".to_string())
+}
+
+/// Author of the post that owns `video`, if any.
+fn video_owner(conn: &mut PgConnection, video: &str) -> Result<Uuid, ApiError> {
+    use crate::schema::{post_media, user_posts};
+
+    post_media::table
+        .inner_join(user_posts::table)
+        .filter(post_media::storage_key.eq(video))
+        .select(user_posts::user_id)
+        .first::<Option<Uuid>>(conn)
+        .optional()?
+        .flatten()
+        .ok_or_else(not_found)
+}
+
+/// Profile pictures are public. Videos and their thumbnails follow the same
+/// visibility rules as the post they belong to. Any other key is 404.
+async fn authorize(pool: web::Data<DbPool>, viewer: MaybeAuthUser, key: &str) -> Result<(), ApiError> {
+    if key.starts_with(storage::PROFILE_PREFIX) {
+        return Ok(());
+    }
+    if !key.starts_with(storage::POST_PREFIX) {
+        return Err(not_found());
+    }
+
+    let video = storage::video_of_thumbnail(key).unwrap_or_else(|| key.to_string());
+    let viewer_id = viewer.id();
+
+    web::block(move || {
+        let mut conn = pool.get()?;
+        let owner = video_owner(&mut conn, &video)?;
+        policy::require_full_access(&mut conn, viewer_id, owner)
+    })
+    .await?
+}
+
+/// Validators of the stored object, as sent in `ETag` and `Last-Modified`.
+struct Validators {
+    etag: Option<EntityTag>,
+    last_modified: Option<HttpDate>,
+}
+
+impl Validators {
+    fn new(meta: &ObjectMeta) -> Self {
+        Validators {
+            etag: meta.etag.clone().map(EntityTag::new_strong),
+            // HTTP dates have whole seconds; compare at that precision.
+            last_modified: meta
+                .last_modified
+                .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+                .map(|t| HttpDate::from(UNIX_EPOCH + Duration::from_secs(t.as_secs()))),
+        }
+    }
+
+    /// Whether the client's cached copy is current. `If-None-Match` wins
+    /// over `If-Modified-Since` when both are sent.
+    fn not_modified(&self, req: &HttpRequest) -> bool {
+        if let Some(condition) = req.get_header::<IfNoneMatch>() {
+            return match (condition, &self.etag) {
+                (IfNoneMatch::Any, _) => true,
+                (IfNoneMatch::Items(tags), Some(etag)) => tags.iter().any(|t| t.weak_eq(etag)),
+                (IfNoneMatch::Items(_), None) => false,
+            };
+        }
+        match (req.get_header::<IfModifiedSince>(), self.last_modified) {
+            (Some(IfModifiedSince(since)), Some(modified)) => modified <= since,
+            _ => false,
+        }
+    }
+
+    /// `If-Range` lets a client resume a download only if the object hasn't
+    /// changed; otherwise the range is ignored and the whole object is sent.
+    fn range_applies(&self, req: &HttpRequest) -> bool {
+        match req.get_header::<IfRange>() {
+            None => true,
+            Some(IfRange::EntityTag(tag)) => self.etag.as_ref().is_some_and(|etag| tag.strong_eq(etag)),
+            Some(IfRange::Date(date)) => self.last_modified == Some(date),
+        }
+    }
+
+    fn apply(&self, response: &mut HttpResponseBuilder) {
+        if let Some(etag) = &self.etag {
+            response.insert_header(header::ETag(etag.clone()));
+        }
+        if let Some(modified) = self.last_modified {
+            response.insert_header(header::LastModified(modified));
+        }
+        // Access can be revoked (unfollow, block), so caches must revalidate.
+        response.insert_header((header::CACHE_CONTROL, "private, no-cache"));
+    }
+}
+
+/// GET and HEAD /media/{key}. Redirects to a presigned URL when the store
+/// hands them out, otherwise streams the object itself: a single `Range` is
+/// honoured (subject to `If-Range`), and `If-None-Match` or
+/// `If-Modified-Since` get a 304. `<video>` and `<img>` tags can't send
+/// headers, so they use the signed URLs that come with posts instead; a
+/// valid signature stands in for the access check.
+pub async fn serve_media(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    store: web::Data<dyn MediaStore>,
+    viewer: MaybeAuthUser,
+    path: web::Path<String>,
+    query: web::Query<MediaQuery>,
+) -> Result<HttpResponse, ApiError> {
+    let key = path.into_inner();
+    if !storage::is_valid_key(&key) {
+        return Err(not_found());
+    }
+
+    match (query.expires, query.signature.as_deref()) {
+        (Some(expires), Some(signature)) => {
+            if !tokens::verify_media_signature(&config.auth, &key, expires, signature) {
+                return Err(ApiError::Forbidden("Media link is invalid or has expired".to_string()));
+            }
+        }
+        _ => authorize(pool, viewer, &key).await?,
+    }
+
+    let ttl = Duration::from_secs(config.storage.s3.presign_ttl_secs);
+    if let Some(url) = store.presigned_url(&key, ttl).await? {
+        return Ok(HttpResponse::Found()
+            .insert_header((header::LOCATION, url))
+            .insert_header((header::CACHE_CONTROL, "private, no-store"))
+            .finish());
+    }
+
+    // Keys are never reused for new content, so the object can't change
+    // between this and the read below.
+    let meta = store.stat(&key).await?;
+    let validators = Validators::new(&meta);
+
+    if validators.not_modified(&req) {
+        let mut response = HttpResponse::NotModified();
+        validators.apply(&mut response);
+        return Ok(response.finish());
+    }
+
+    let range = req
+        .headers()
+        .get(header::RANGE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(ByteRange::parse)
+        .filter(|_| validators.range_applies(&req));
+
+    // HEAD answers from the metadata alone.
+    let object = if req.method() == Method::HEAD {
+        MediaObject { body: Box::pin(futures_util::stream::empty()), len: meta.size, range: None, meta }
+    } else {
+        match store.get(&key, range).await {
+            Ok(object) => object,
+            Err(StoreError::RangeNotSatisfiable(total)) => {
+                return Ok(HttpResponse::RangeNotSatisfiable()
+                    .insert_header((header::CONTENT_RANGE, format!("bytes */{}", total)))
+                    .finish());
+            }
+            Err(e) => return Err(e.into()),
+        }
+    };
+
+    let mut response = match object.range {
+        Some((start, end)) => {
+            let mut partial = HttpResponse::PartialContent();
+            partial.insert_header((header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, object.meta.size)));
+            partial
+        }
+        None => HttpResponse::Ok(),
+    };
+    validators.apply(&mut response);
+
+    Ok(response
+        .insert_header((header::CONTENT_TYPE, object.meta.content_type))
+        .insert_header((header::ACCEPT_RANGES, "bytes"))
+        .insert_header(("X-Content-Type-Options", "nosniff"))
+        .no_chunking(object.len)
+        .streaming(object.body))
+}
@@ -1,2 +1,6 @@
 pub mod user_handler;
-pub mod post_handler;
\ No newline at end of file
+pub mod post_handler;
+pub mod comment_handler;
+pub mod search_handler;
+pub mod session_handler;
+pub mod media_handler;
@@ -1,18 +1,24 @@
 use actix_multipart::Multipart;
-use actix_web::{web, HttpResponse, Error, HttpRequest};
-use actix_web::HttpMessage;
-use serde::Serialize;
+use actix_web::{web, HttpResponse};
 use futures_util::TryStreamExt as _;
+use std::collections::HashMap;
 use uuid::Uuid;
 use diesel::prelude::*;
-use std::fs;
-use std::io::Write;
-use chrono::{NaiveDateTime, Utc, Duration};
-use crate::models::post::{NewUserPost, UserPostWithUser ,UserPostResponse, UserPost};
-use crate::models::user::User;
-use crate::schema::user_posts;
+use validator::Validate;
+use chrono::{NaiveDateTime, Utc};
+use crate::models::media::{MediaInfo, NewPostMedia, PostMedia};
+use crate::models::post::{NewUserPost, UpdatePostRequest, UserPost, UserPostResponse};
+use crate::pagination::{self, Cursor, Page, PageParams};
+use crate::middleware::AuthUser;
+use crate::schema::{blocks, hashtags, post_comments, post_hashtags, post_likes, post_media, user_posts, users};
 use crate::DbPool;
-use utoipa::path;
+use crate::config::{AuthConfig, Config};
+use crate::errors::ApiError;
+use crate::jobs::{self, Job};
+use crate::policy;
+use crate::storage::{self, MediaStore};
+use crate::upload::{self, FileRules, Uploads};
+use crate::entities::{self, MentionLinks};
 
 
 
@@ -20,14 +26,16 @@ use utoipa::path;
     post,
     path = "/api/user/auth/posts",
     request_body(
-        content = NewUserPost,
+        content = NewPostForm,
         content_type = "multipart/form-data",
-        description = "Multipart form data with description and videos"
+        description = "Multipart form data with a description and the post's videos and images, in display order"
     ),
     responses(
         (status = 201, description = "Post uploaded successfully"),
-        (status = 400, description = "Bad request: missing description or videos"),
-        (status = 401, description = "Unauthorized user")
+        (status = 400, description = "Bad request: missing or repeated description, missing media, or too many files", body = ErrorBody),
+        (status = 401, description = "Unauthorized user", body = ErrorBody),
+        (status = 413, description = "A file or the description is too large", body = ErrorBody),
+        (status = 415, description = "A file isn't a valid MP4, WebM or MOV video or JPEG, PNG or WebP image", body = ErrorBody)
     ),
     tag = "Posts",
      security(
@@ -37,82 +45,78 @@ use utoipa::path;
 
 pub async fn create_user_post(
     pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    store: web::Data<dyn MediaStore>,
     mut payload: Multipart,
-    req: HttpRequest,) -> Result<HttpResponse, Error> {
-    let user = match req.extensions().get::<User>() {
-        Some(u) => u.clone(),
-        None => {
-            return Ok(HttpResponse::Unauthorized().json(serde_json::json!({
-                "message": "Unauthorized"
-            })));
-        }
+    user: AuthUser,) -> Result<HttpResponse, ApiError> {
+    let mut description: Option<String> = None;
+    let mut uploads = Uploads::new(store.into_inner(), storage::POST_PREFIX);
+    let media_rules = FileRules {
+        max_video_bytes: Some(config.storage.max_video_bytes),
+        max_image_bytes: Some(config.storage.max_image_bytes),
+        max_files: config.storage.max_media_per_post,
     };
+    // `videos` predates image posts and still only takes videos.
+    let video_rules = FileRules { max_image_bytes: None, ..media_rules };
 
-    let mut description = String::new();
-    let mut video_paths: Vec<String> = Vec::new();
-
-    let upload_dir = "./files/userpost";
-    fs::create_dir_all(upload_dir).expect("Failed to create upload directory");
-
-    while let Ok(Some(mut field)) = payload.try_next().await {
-        let cd = field.content_disposition().clone();
-        let field_name = cd.get_name().unwrap_or_default();
-
-        if field_name == "description" {
-            // Text field
-            while let Some(chunk) = field.try_next().await? {
-                description.push_str(&String::from_utf8_lossy(&chunk));
+    while let Some(mut field) = payload.try_next().await? {
+        match field.name() {
+            // Each text field is capped on its own, so repeats would add up.
+            "description" if description.is_some() => {
+                return Err(ApiError::BadRequest("Field 'description' was sent more than once".to_string()));
             }
-        } else if field_name == "videos" {
-            // File upload
-            let original_filename = cd
-                .get_filename()
-                .map(|f| f.to_string())
-                .unwrap_or_else(|| "file.mp4".to_string());
-
-            let filename = format!("{}_{}", Uuid::new_v4(), original_filename);
-            let filepath = format!("{}/{}", upload_dir, filename);
-
-            let filepath_clone = filepath.clone();
-            let mut f = web::block(move || std::fs::File::create(filepath_clone)).await??;
-
-            while let Some(chunk) = field.try_next().await? {
-                f = web::block(move || {
-                    f.write_all(&chunk).map(|_| f)
-                })
-                .await??;
+            "description" => description = Some(upload::read_text(&mut field).await?),
+            "media" => {
+                uploads.save(&mut field, media_rules).await?;
             }
-
-            let public_path = format!("{}", filename);
-            video_paths.push(public_path);
+            "videos" => {
+                uploads.save(&mut field, video_rules).await?;
+            }
+            _ => {}
         }
     }
 
-    if description.is_empty() || video_paths.is_empty() {
-        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-            "message": "Description and videos are required."
-        })));
+    let description = description.unwrap_or_default();
+    let files = uploads.all();
+    if description.is_empty() || files.is_empty() {
+        return Err(ApiError::BadRequest("Description and at least one video or image are required.".to_string()));
     }
 
-    let conn = &mut pool.get().expect("Couldn't get DB connection");
-
-    let videos_with_option: Vec<Option<String>> = video_paths
-        .iter()
-        .map(|s| Some(s.clone()))
-        .collect();
+    let conn = &mut pool.get()?;
 
     let new_post = NewUserPost {
         id: Uuid::new_v4(),
         user_id: Some(user.id),
         description: description.clone(),
-        videos: videos_with_option,
         created_at: Some(Utc::now().naive_utc()),
     };
 
-    diesel::insert_into(user_posts::table)
-        .values(&new_post)
-        .execute(conn)
-        .expect("Failed to insert post");
+    let (post_entities, media) = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        diesel::insert_into(user_posts::table)
+            .values(&new_post)
+            .execute(conn)?;
+
+        let post_entities = entities::sync_post(conn, new_post.id, user.id, &new_post.description)?;
+
+        let media: Vec<NewPostMedia> = files
+            .iter()
+            .enumerate()
+            .map(|(position, f)| NewPostMedia::uploaded(new_post.id, position, f))
+            .collect();
+        let media = diesel::insert_into(post_media::table)
+            .values(&media)
+            .returning(PostMedia::as_returning())
+            .get_results(conn)?;
+
+        for f in files.iter().filter(|f| f.video.is_some()) {
+            jobs::enqueue(conn, &Job::GenerateThumbnail {
+                post_id: new_post.id,
+                video: f.key.clone(),
+            })?;
+        }
+        Ok((post_entities, media))
+    })?;
+    uploads.keep();
 
     Ok(HttpResponse::Created().json(serde_json::json!({
         "message": "Post uploaded successfully!",
@@ -120,23 +124,138 @@ pub async fn create_user_post(
             "id": new_post.id,
             "user_id": new_post.user_id,
             "description": new_post.description,
-            "videos": video_paths,
-            "created_at": new_post.created_at
+            "created_at": new_post.created_at,
+            "entities": post_entities,
+            "media": media.into_iter().map(|m| MediaInfo::new(m, &config.auth)).collect::<Vec<_>>()
         }
     })))
 }
 
+pub(crate) type PostRow = (Uuid, Option<Uuid>, String, Option<NaiveDateTime>, Option<String>, Option<String>, i64, bool, i64);
+
+/// Posts joined with their author, selecting a `PostRow` as seen by `viewer`.
+/// The comment count leaves out authors blocked either way, as the comment
+/// listings do.
+#[diesel::dsl::auto_type(no_type_alias)]
+pub(crate) fn posts_with_author(viewer: Uuid) -> _ {
+    let like_count: diesel::dsl::count_star = diesel::dsl::count_star();
+    let comment_count: diesel::dsl::count_star = diesel::dsl::count_star();
+
+    user_posts::table
+        .inner_join(users::table.on(user_posts::user_id.eq(users::id.nullable())))
+        .select((
+            user_posts::id,
+            user_posts::user_id,
+            user_posts::description,
+            user_posts::created_at,
+            users::name.nullable(),
+            users::profile_pic,
+            post_likes::table
+                .filter(post_likes::post_id.eq(user_posts::id))
+                .select(like_count)
+                .single_value()
+                .assume_not_null(),
+            diesel::dsl::exists(
+                post_likes::table
+                    .filter(post_likes::post_id.eq(user_posts::id))
+                    .filter(post_likes::user_id.eq(viewer)),
+            ),
+            post_comments::table
+                .filter(post_comments::post_id.eq(user_posts::id))
+                .filter(diesel::dsl::not(diesel::dsl::exists(
+                    blocks::table.filter(
+                        blocks::blocker_id.eq(viewer).and(blocks::blocked_id.eq(post_comments::user_id))
+                            .or(blocks::blocked_id.eq(viewer).and(blocks::blocker_id.eq(post_comments::user_id))),
+                    ),
+                )))
+                .select(comment_count)
+                .single_value()
+                .assume_not_null(),
+        ))
+}
+
+/// Attachments of each of `post_ids`, in display order.
+fn load_media(conn: &mut PgConnection, post_ids: Vec<Uuid>, auth: &AuthConfig) -> QueryResult<HashMap<Uuid, Vec<MediaInfo>>> {
+    let rows = post_media::table
+        .filter(post_media::post_id.eq_any(post_ids))
+        .order((post_media::post_id, post_media::position))
+        .select(PostMedia::as_select())
+        .load::<PostMedia>(conn)?;
+
+    let mut media: HashMap<Uuid, Vec<MediaInfo>> = HashMap::new();
+    for row in rows {
+        media.entry(row.post_id).or_default().push(MediaInfo::new(row, auth));
+    }
+    Ok(media)
+}
+
+/// Storage keys of a post's attachments, in display order.
+fn media_keys(conn: &mut PgConnection, post_id: Uuid) -> QueryResult<Vec<String>> {
+    post_media::table
+        .filter(post_media::post_id.eq(post_id))
+        .order(post_media::position)
+        .select(post_media::storage_key)
+        .load(conn)
+}
+
+fn post_response(
+    (id, user_id, description, created_at, user_name, profile_pic, like_count, liked_by_me, comment_count): PostRow,
+    links: &MentionLinks,
+    media: &mut HashMap<Uuid, Vec<MediaInfo>>,
+) -> UserPostResponse {
+    UserPostResponse {
+        entities: entities::resolve(&description, links.get(&id)),
+        media: media.remove(&id).unwrap_or_default(),
+        id,
+        user_id,
+        description,
+        created_at,
+        user_name,
+        profile_pic,
+        like_count,
+        liked_by_me,
+        comment_count,
+    }
+}
+
+fn post_cursor(row: &PostRow) -> Cursor {
+    Cursor::new(row.3.unwrap_or_default(), row.0)
+}
+
+pub(crate) fn post_responses(
+    conn: &mut PgConnection,
+    rows: Vec<PostRow>,
+    auth: &AuthConfig,
+) -> QueryResult<Vec<UserPostResponse>> {
+    let ids: Vec<Uuid> = rows.iter().map(|row| row.0).collect();
+    let links = entities::linked_mentions(conn, ids.clone())?;
+    let mut media = load_media(conn, ids, auth)?;
+    Ok(rows.into_iter().map(|row| post_response(row, &links, &mut media)).collect())
+}
+
+/// Builds a page of responses from up to `limit + 1` rows.
+fn post_page(conn: &mut PgConnection, rows: Vec<PostRow>, limit: i64, auth: &AuthConfig) -> QueryResult<Page<UserPostResponse>> {
+    let Page { items, next_cursor, total } = Page::from_rows(rows, limit, post_cursor);
+    Ok(Page { items: post_responses(conn, items, auth)?, next_cursor, total })
+}
+
+fn single_post_response(conn: &mut PgConnection, row: PostRow, auth: &AuthConfig) -> QueryResult<UserPostResponse> {
+    let links = entities::linked_mentions(conn, vec![row.0])?;
+    let mut media = load_media(conn, vec![row.0], auth)?;
+    Ok(post_response(row, &links, &mut media))
+}
+
 #[utoipa::path(
-    post,
+    get,
     path = "/api/user/auth/getpost",
-    request_body(
-        content = Multipart,
-        description = "Multipart form data with description and videos"
+    params(
+        PageParams
     ),
     responses(
-        (status = 201, description = "Post uploaded successfully", body = PostResponse),
-        (status = 400, description = "Bad request: missing description or videos"),
-        (status = 401, description = "Unauthorized user")
+        (status = 200, description = "All posts visible to the caller, newest first", body = PostPage),
+        (status = 400, description = "Invalid cursor", body = ErrorBody),
+        (status = 401, description = "Unauthorized user", body = ErrorBody),
+        (status = 500, description = "Database error", body = ErrorBody)
     ),
     tag = "Posts",
     security(
@@ -144,44 +263,503 @@ pub async fn create_user_post(
     )
 )]
 
-pub async fn get_user_posts(pool: web::Data<DbPool>) -> Result<HttpResponse, Error> {
-    let conn = &mut pool.get().expect("Couldn't get DB connection");
+pub async fn get_user_posts(pool: web::Data<DbPool>, config: web::Data<Config>, auth: AuthUser,
+    query: web::Query<PageParams>,) -> Result<HttpResponse, ApiError> {
+    use crate::schema::user_posts::dsl as post_dsl;
+
+    let limit = query.limit();
+    let after = query.cursor()?;
 
+    let conn = &mut pool.get()?;
+
+    let mut q = posts_with_author(auth.id)
+        .filter(policy::visible_posts(Some(auth.id)))
+        .filter(post_dsl::created_at.is_not_null())
+        .order((post_dsl::created_at.desc(), post_dsl::id.desc()))
+        .limit(limit + 1)
+        .into_boxed();
+
+    if let Some(c) = after {
+        q = q.filter(pagination::keyset_before(post_dsl::created_at.assume_not_null(), post_dsl::id, &c));
+    }
+
+    let rows = q.load::<PostRow>(conn)?;
+
+    Ok(HttpResponse::Ok().json(post_page(conn, rows, limit, &config.auth)?))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/user/auth/feed",
+    params(
+        PageParams
+    ),
+    responses(
+        (status = 200, description = "Posts by the caller and the accounts they follow, newest first", body = PostPage),
+        (status = 400, description = "Invalid cursor", body = ErrorBody),
+        (status = 401, description = "Unauthorized user", body = ErrorBody)
+    ),
+    tag = "Posts",
+    security(
+        ("bearerAuth" = [])
+    )
+)]
+
+pub async fn home_feed(
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    auth: AuthUser,
+    query: web::Query<PageParams>,
+) -> Result<HttpResponse, ApiError> {
+    use crate::schema::follows::dsl as follow_dsl;
     use crate::schema::user_posts::dsl as post_dsl;
-    use crate::schema::users::dsl as user_dsl;
 
-    // Perform the join query
-    let results = post_dsl::user_posts
-        .left_join(user_dsl::users.on(post_dsl::user_id.eq(user_dsl::id.nullable())))
-        .select((
-            post_dsl::id,
-            post_dsl::user_id,
-            post_dsl::description,
-            post_dsl::videos,
-            post_dsl::created_at,
-            user_dsl::name.nullable(),
-            user_dsl::profile_pic.nullable(),
-        ))
-        .order(post_dsl::created_at.desc())
-        .load::<(Uuid, Option<Uuid>, String, Vec<Option<String>>, Option<NaiveDateTime>, Option<String>, Option<String>)>(conn)
-        .expect("Failed to fetch posts");
-
-    // Transform the results to clean up the Option<String> in videos
-    let response: Vec<UserPostResponse> = results
-        .into_iter()
-        .map(|(id, user_id, description, videos, created_at, user_name, profile_pic)| {
-            UserPostResponse {
-                id,
-                user_id,
-                description,
-                videos: videos.into_iter().filter_map(|v| v).collect(),  // Remove None values
-                created_at,
-                user_name,
-                profile_pic,
+    let me = auth.id;
+    let limit = query.limit();
+    let after = query.cursor()?;
+
+    let page = web::block(move || {
+        let conn = &mut pool.get()?;
+
+        let followed = follow_dsl::follows
+            .filter(follow_dsl::user_id.eq(me))
+            .filter(follow_dsl::status.eq("accepted"))
+            .select(follow_dsl::target_id.nullable());
+
+        let mut q = posts_with_author(me)
+            .filter(post_dsl::user_id.eq(me).or(post_dsl::user_id.eq_any(followed)))
+            .filter(post_dsl::created_at.is_not_null())
+            .order((post_dsl::created_at.desc(), post_dsl::id.desc()))
+            .limit(limit + 1)
+            .into_boxed();
+
+        if let Some(c) = after {
+            q = q.filter(pagination::keyset_before(post_dsl::created_at.assume_not_null(), post_dsl::id, &c));
+        }
+
+        let rows = q.load::<PostRow>(conn)?;
+        post_page(conn, rows, limit, &config.auth).map_err(ApiError::from)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(page))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/user/auth/users/{user_id}/posts",
+    params(
+        ("user_id" = Uuid, Path, description = "Author whose posts to list"),
+        PageParams
+    ),
+    responses(
+        (status = 200, description = "The user's posts, newest first, with `total` set to their post count", body = PostPage),
+        (status = 400, description = "Invalid cursor", body = ErrorBody),
+        (status = 401, description = "Unauthorized user", body = ErrorBody),
+        (status = 403, description = "Private account the caller doesn't follow", body = ErrorBody),
+        (status = 404, description = "User not found", body = ErrorBody)
+    ),
+    tag = "Posts",
+    security(
+        ("bearerAuth" = [])
+    )
+)]
+
+pub async fn list_user_posts(
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    auth: AuthUser,
+    path: web::Path<Uuid>,
+    query: web::Query<PageParams>,
+) -> Result<HttpResponse, ApiError> {
+    use crate::schema::user_posts::dsl as post_dsl;
+
+    let author = path.into_inner();
+    let viewer = auth.id;
+    let limit = query.limit();
+    let after = query.cursor()?;
+
+    let (page, total) = web::block(move || {
+        let conn = &mut pool.get()?;
+        policy::require_full_access(conn, Some(viewer), author)?;
+
+        let total: i64 = post_dsl::user_posts
+            .filter(post_dsl::user_id.eq(author))
+            .count()
+            .get_result(conn)?;
+
+        let mut q = posts_with_author(viewer)
+            .filter(post_dsl::user_id.eq(author))
+            .filter(post_dsl::created_at.is_not_null())
+            .order((post_dsl::created_at.desc(), post_dsl::id.desc()))
+            .limit(limit + 1)
+            .into_boxed();
+
+        if let Some(c) = after {
+            q = q.filter(pagination::keyset_before(post_dsl::created_at.assume_not_null(), post_dsl::id, &c));
+        }
+
+        let rows = q.load::<PostRow>(conn)?;
+        Ok::<_, ApiError>((post_page(conn, rows, limit, &config.auth)?, total))
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(page.with_total(total)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/user/auth/hashtags/{tag}/posts",
+    params(
+        ("tag" = String, Path, description = "Hashtag, with or without the leading `#`; case-insensitive"),
+        PageParams
+    ),
+    responses(
+        (status = 200, description = "Visible posts with the hashtag, newest first. Unknown tags give an empty page", body = PostPage),
+        (status = 400, description = "Invalid cursor", body = ErrorBody),
+        (status = 401, description = "Unauthorized user", body = ErrorBody)
+    ),
+    tag = "Posts",
+    security(
+        ("bearerAuth" = [])
+    )
+)]
+
+pub async fn hashtag_posts(
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    auth: AuthUser,
+    path: web::Path<String>,
+    query: web::Query<PageParams>,
+) -> Result<HttpResponse, ApiError> {
+    use crate::schema::user_posts::dsl as post_dsl;
+
+    let tag = entities::normalize_tag(&path.into_inner());
+    let viewer = auth.id;
+    let limit = query.limit();
+    let after = query.cursor()?;
+
+    let page = web::block(move || {
+        let conn = &mut pool.get()?;
+
+        let tagged = post_hashtags::table
+            .inner_join(hashtags::table)
+            .filter(hashtags::tag.eq(tag))
+            .select(post_hashtags::post_id);
+
+        let mut q = posts_with_author(viewer)
+            .filter(post_dsl::id.eq_any(tagged))
+            .filter(policy::visible_posts(Some(viewer)))
+            .filter(post_dsl::created_at.is_not_null())
+            .order((post_dsl::created_at.desc(), post_dsl::id.desc()))
+            .limit(limit + 1)
+            .into_boxed();
+
+        if let Some(c) = after {
+            q = q.filter(pagination::keyset_before(post_dsl::created_at.assume_not_null(), post_dsl::id, &c));
+        }
+
+        let rows = q.load::<PostRow>(conn)?;
+        post_page(conn, rows, limit, &config.auth).map_err(ApiError::from)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(page))
+}
+
+fn like_count(conn: &mut PgConnection, post_id: Uuid) -> QueryResult<i64> {
+    post_likes::table
+        .filter(post_likes::post_id.eq(post_id))
+        .count()
+        .get_result(conn)
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/user/auth/posts/{post_id}/like",
+    params(
+        ("post_id" = Uuid, Path, description = "Post to like")
+    ),
+    responses(
+        (status = 200, description = "Post liked. Liking twice is a no-op", body = serde_json::Value),
+        (status = 401, description = "Unauthorized user", body = ErrorBody),
+        (status = 403, description = "Author's account is private", body = ErrorBody),
+        (status = 404, description = "Post not found", body = ErrorBody)
+    ),
+    tag = "Posts",
+    security(
+        ("bearerAuth" = [])
+    )
+)]
+
+pub async fn like_post(pool: web::Data<DbPool>, auth: AuthUser, path: web::Path<Uuid>) -> Result<HttpResponse, ApiError> {
+    let post_id = path.into_inner();
+    let viewer = auth.id;
+
+    let count = web::block(move || {
+        let conn = &mut pool.get()?;
+        policy::require_post_access(conn, Some(viewer), post_id)?;
+
+        diesel::insert_into(post_likes::table)
+            .values((post_likes::post_id.eq(post_id), post_likes::user_id.eq(viewer)))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+
+        like_count(conn, post_id).map_err(ApiError::from)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "liked_by_me": true,
+        "like_count": count
+    })))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/user/auth/posts/{post_id}/like",
+    params(
+        ("post_id" = Uuid, Path, description = "Post to unlike")
+    ),
+    responses(
+        (status = 200, description = "Like removed. Unliking a post that isn't liked is a no-op", body = serde_json::Value),
+        (status = 401, description = "Unauthorized user", body = ErrorBody),
+        (status = 403, description = "Author's account is private", body = ErrorBody),
+        (status = 404, description = "Post not found", body = ErrorBody)
+    ),
+    tag = "Posts",
+    security(
+        ("bearerAuth" = [])
+    )
+)]
+
+pub async fn unlike_post(pool: web::Data<DbPool>, auth: AuthUser, path: web::Path<Uuid>) -> Result<HttpResponse, ApiError> {
+    let post_id = path.into_inner();
+    let viewer = auth.id;
+
+    let count = web::block(move || {
+        let conn = &mut pool.get()?;
+        policy::require_post_access(conn, Some(viewer), post_id)?;
+
+        diesel::delete(
+            post_likes::table
+                .filter(post_likes::post_id.eq(post_id))
+                .filter(post_likes::user_id.eq(viewer)),
+        )
+        .execute(conn)?;
+
+        like_count(conn, post_id).map_err(ApiError::from)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "liked_by_me": false,
+        "like_count": count
+    })))
+}
+
+/// One post with its author as seen by `viewer`, or 404.
+fn load_post(conn: &mut PgConnection, post_id: Uuid, viewer: Uuid) -> Result<PostRow, ApiError> {
+    posts_with_author(viewer)
+        .filter(user_posts::id.eq(post_id))
+        .first::<PostRow>(conn)
+        .optional()?
+        .ok_or_else(|| ApiError::NotFound("Post not found".to_string()))
+}
+
+/// Locks the post for the rest of the transaction and checks that `owner` wrote it.
+fn lock_own_post(conn: &mut PgConnection, post_id: Uuid, owner: Uuid) -> Result<UserPost, ApiError> {
+    use crate::schema::user_posts::dsl::*;
+
+    let post = user_posts
+        .filter(id.eq(post_id))
+        .for_update()
+        .select(UserPost::as_select())
+        .first::<UserPost>(conn)
+        .optional()?
+        .ok_or_else(|| ApiError::NotFound("Post not found".to_string()))?;
+
+    if post.user_id != Some(owner) {
+        return Err(ApiError::Forbidden("You can only modify your own posts".to_string()));
+    }
+
+    Ok(post)
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/user/auth/posts/{post_id}",
+    params(
+        ("post_id" = Uuid, Path, description = "Post to fetch")
+    ),
+    responses(
+        (status = 200, description = "The post", body = UserPostResponse),
+        (status = 401, description = "Unauthorized user", body = ErrorBody),
+        (status = 403, description = "Author's account is private", body = ErrorBody),
+        (status = 404, description = "Post not found", body = ErrorBody)
+    ),
+    tag = "Posts",
+    security(
+        ("bearerAuth" = [])
+    )
+)]
+
+pub async fn get_post(pool: web::Data<DbPool>, config: web::Data<Config>, auth: AuthUser,
+    path: web::Path<Uuid>,) -> Result<HttpResponse, ApiError> {
+    let post_id = path.into_inner();
+    let viewer = auth.id;
+
+    let post = web::block(move || {
+        let conn = &mut pool.get()?;
+        let row = load_post(conn, post_id, viewer)?;
+
+        // load_post inner-joins users, so the author is always set.
+        if let Some(owner) = row.1 {
+            policy::require_full_access(conn, Some(viewer), owner)?;
+        }
+        single_post_response(conn, row, &config.auth).map_err(ApiError::from)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(post))
+}
+
+#[utoipa::path(
+    patch,
+    path = "/api/user/auth/posts/{post_id}",
+    params(
+        ("post_id" = Uuid, Path, description = "Post to edit")
+    ),
+    request_body = UpdatePostRequest,
+    responses(
+        (status = 200, description = "Updated post", body = UserPostResponse),
+        (status = 400, description = "Invalid or empty update, or no media left", body = ErrorBody),
+        (status = 401, description = "Unauthorized user", body = ErrorBody),
+        (status = 403, description = "Not the author", body = ErrorBody),
+        (status = 404, description = "Post not found", body = ErrorBody)
+    ),
+    tag = "Posts",
+    security(
+        ("bearerAuth" = [])
+    )
+)]
+
+pub async fn update_post(
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    auth: AuthUser,
+    path: web::Path<Uuid>,
+    body: web::Json<UpdatePostRequest>,
+) -> Result<HttpResponse, ApiError> {
+    use crate::schema::user_posts::dsl::*;
+
+    body.validate()?;
+    let body = body.into_inner();
+
+    if body.description.is_none() && body.media.is_none() {
+        return Err(ApiError::BadRequest("Nothing to update".to_string()));
+    }
+
+    let post_id = path.into_inner();
+    let owner = auth.id;
+
+    let post = web::block(move || {
+        let conn = &mut pool.get()?;
+
+        conn.transaction::<_, ApiError, _>(|conn| {
+            lock_own_post(conn, post_id, owner)?;
+
+            if let Some(order) = &body.media {
+                // Only listed media are kept, and a post can't be left with none.
+                if order.is_empty() {
+                    return Err(ApiError::BadRequest("A post needs at least one attachment".to_string()));
+                }
+                let current = media_keys(conn, post_id)?;
+                for (i, key) in order.iter().enumerate() {
+                    if !current.contains(key) {
+                        return Err(ApiError::BadRequest(format!("'{}' is not part of this post", key)));
+                    }
+                    if order[..i].contains(key) {
+                        return Err(ApiError::BadRequest(format!("'{}' is listed twice", key)));
+                    }
+                }
+                let removed: Vec<String> = current.into_iter().filter(|k| !order.contains(k)).collect();
+
+                // Positions are unique per post, checked at commit.
+                for (position, key) in order.iter().enumerate() {
+                    diesel::update(post_media::table.filter(post_media::storage_key.eq(key)))
+                        .set(post_media::position.eq(position as i32))
+                        .execute(conn)?;
+                }
+
+                // 🧹 Files go once no row points at them
+                if !removed.is_empty() {
+                    diesel::delete(post_media::table.filter(post_media::storage_key.eq_any(&removed))).execute(conn)?;
+                    jobs::enqueue(conn, &Job::RemovePostFiles { post_id, media: removed })?;
+                }
             }
+
+            if let Some(text) = &body.description {
+                entities::sync_post(conn, post_id, owner, text)?;
+                diesel::update(user_posts.filter(id.eq(post_id)))
+                    .set(description.eq(text))
+                    .execute(conn)?;
+            }
+
+            let row = load_post(conn, post_id, owner)?;
+            single_post_response(conn, row, &config.auth).map_err(ApiError::from)
         })
-        .collect();
+    })
+    .await??;
 
-    Ok(HttpResponse::Ok().json(response))
+    Ok(HttpResponse::Ok().json(post))
 }
 
+#[utoipa::path(
+    delete,
+    path = "/api/user/auth/posts/{post_id}",
+    params(
+        ("post_id" = Uuid, Path, description = "Post to delete")
+    ),
+    responses(
+        (status = 200, description = "Post deleted; its files are removed in the background", body = serde_json::Value),
+        (status = 401, description = "Unauthorized user", body = ErrorBody),
+        (status = 403, description = "Not the author", body = ErrorBody),
+        (status = 404, description = "Post not found", body = ErrorBody)
+    ),
+    tag = "Posts",
+    security(
+        ("bearerAuth" = [])
+    )
+)]
+
+pub async fn delete_post(pool: web::Data<DbPool>, auth: AuthUser, path: web::Path<Uuid>) -> Result<HttpResponse, ApiError> {
+    use crate::schema::user_posts::dsl::*;
+
+    let post_id = path.into_inner();
+    let owner = auth.id;
+
+    web::block(move || {
+        let conn = &mut pool.get()?;
+
+        conn.transaction::<_, ApiError, _>(|conn| {
+            lock_own_post(conn, post_id, owner)?;
+            let keys = media_keys(conn, post_id)?;
+
+            // Its media rows go with it via ON DELETE CASCADE.
+            diesel::delete(user_posts.filter(id.eq(post_id))).execute(conn)?;
+
+            // Enqueued in the same transaction: a rollback keeps the files too.
+            jobs::enqueue(conn, &Job::RemovePostFiles { post_id, media: keys })?;
+            Ok(())
+        })
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "Post deleted"
+    })))
+}
@@ -0,0 +1,169 @@
+use actix_web::{web, HttpResponse};
+use diesel::dsl::{count_star, exists, not, sql};
+use diesel::prelude::*;
+use diesel::sql_types::{Bool, Float, Text};
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::entities;
+use crate::errors::ApiError;
+use crate::handlers::post_handler::{self, PostRow};
+use crate::middleware::AuthUser;
+use crate::models::search::{HashtagSearchItem, SearchParams, SearchResults, SearchType, UserSearchItem};
+use crate::policy;
+use crate::schema::{blocks, hashtags, post_hashtags, user_posts, users};
+
+const MAX_QUERY_LEN: usize = 100;
+
+/// Escapes `LIKE` wildcards so `text` only matches itself.
+fn like_escape(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+/// Whole-word matches on the name rank first; trigram similarity and
+/// substring matches catch partial names.
+fn search_users(conn: &mut PgConnection, viewer: Uuid, q: &str, limit: i64) -> QueryResult<Vec<UserSearchItem>> {
+    let matches = sql::<Bool>("(users.name_tsv @@ plainto_tsquery('simple', ")
+        .bind::<Text, _>(q.to_string())
+        .sql(") OR users.name % ")
+        .bind::<Text, _>(q.to_string())
+        .sql(" OR users.name ILIKE ")
+        .bind::<Text, _>(format!("%{}%", like_escape(q)))
+        .sql(")");
+
+    let rank = sql::<Float>("ts_rank(users.name_tsv, plainto_tsquery('simple', ")
+        .bind::<Text, _>(q.to_string())
+        .sql(")) + similarity(users.name, ")
+        .bind::<Text, _>(q.to_string())
+        .sql(")");
+
+    // Private accounts are listed (name and avatar only); blocked ones aren't.
+    let blocked = blocks::table.filter(
+        blocks::blocker_id.eq(viewer).and(blocks::blocked_id.eq(users::id))
+            .or(blocks::blocked_id.eq(viewer).and(blocks::blocker_id.eq(users::id))),
+    );
+
+    users::table
+        .filter(matches)
+        .filter(not(exists(blocked)))
+        .select((users::id, users::name, users::profile_pic, users::account_type))
+        .order((rank.desc(), users::created_at.desc(), users::id.desc()))
+        .limit(limit)
+        .load(conn)
+}
+
+fn search_posts(conn: &mut PgConnection, viewer: Uuid, q: &str, limit: i64) -> QueryResult<Vec<PostRow>> {
+    let matches = sql::<Bool>("user_posts.description_tsv @@ plainto_tsquery('english', ")
+        .bind::<Text, _>(q.to_string())
+        .sql(")");
+
+    let rank = sql::<Float>("ts_rank(user_posts.description_tsv, plainto_tsquery('english', ")
+        .bind::<Text, _>(q.to_string())
+        .sql("))");
+
+    post_handler::posts_with_author(viewer)
+        .filter(matches)
+        .filter(policy::visible_posts(Some(viewer)))
+        .order((rank.desc(), user_posts::created_at.desc(), user_posts::id.desc()))
+        .limit(limit)
+        .load(conn)
+}
+
+/// Tags starting with the query, then similar ones. Tags that only appear
+/// on posts the caller can't see are left out, and counts only include
+/// visible posts.
+fn search_hashtags(conn: &mut PgConnection, viewer: Uuid, q: &str, limit: i64) -> QueryResult<Vec<HashtagSearchItem>> {
+    let tag = entities::normalize_tag(q);
+
+    let matches = sql::<Bool>("(hashtags.tag LIKE ")
+        .bind::<Text, _>(format!("{}%", like_escape(&tag)))
+        .sql(" OR hashtags.tag % ")
+        .bind::<Text, _>(tag.clone())
+        .sql(")");
+
+    let rank = sql::<Float>("(hashtags.tag = ")
+        .bind::<Text, _>(tag.clone())
+        .sql(")::int + (hashtags.tag LIKE ")
+        .bind::<Text, _>(format!("{}%", like_escape(&tag)))
+        .sql(")::int + similarity(hashtags.tag, ")
+        .bind::<Text, _>(tag.clone())
+        .sql(")");
+
+    let visible = post_hashtags::table
+        .inner_join(user_posts::table)
+        .filter(post_hashtags::hashtag_id.eq(hashtags::id))
+        .filter(policy::visible_posts(Some(viewer)));
+
+    let found = hashtags::table
+        .filter(matches)
+        .filter(exists(visible))
+        .select((hashtags::id, hashtags::tag))
+        .order((rank.desc(), hashtags::tag.asc()))
+        .limit(limit)
+        .load::<(Uuid, String)>(conn)?;
+
+    let counts: std::collections::HashMap<Uuid, i64> = post_hashtags::table
+        .inner_join(user_posts::table)
+        .filter(post_hashtags::hashtag_id.eq_any(found.iter().map(|(id, _)| *id).collect::<Vec<_>>()))
+        .filter(policy::visible_posts(Some(viewer)))
+        .group_by(post_hashtags::hashtag_id)
+        .select((post_hashtags::hashtag_id, count_star()))
+        .load::<(Uuid, i64)>(conn)?
+        .into_iter()
+        .collect();
+
+    Ok(found
+        .into_iter()
+        .map(|(id, tag)| HashtagSearchItem {
+            post_count: counts.get(&id).copied().unwrap_or(0),
+            tag,
+        })
+        .collect())
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/user/auth/search",
+    params(
+        SearchParams
+    ),
+    responses(
+        (status = 200, description = "Best matches first, at most `limit`", body = SearchResults),
+        (status = 400, description = "Missing or too long query, or unknown type", body = ErrorBody),
+        (status = 401, description = "Unauthorized user", body = ErrorBody)
+    ),
+    tag = "Search",
+    security(
+        ("bearerAuth" = [])
+    )
+)]
+
+pub async fn search(pool: web::Data<DbPool>, config: web::Data<Config>, auth: AuthUser,
+    query: web::Query<SearchParams>,) -> Result<HttpResponse, ApiError> {
+    let q = query.q.trim().to_string();
+    if q.is_empty() || q.chars().count() > MAX_QUERY_LEN {
+        return Err(ApiError::BadRequest(format!("Search query must be 1-{} characters", MAX_QUERY_LEN)));
+    }
+
+    let kind = query.kind;
+    let limit = query.limit();
+    let viewer = auth.id;
+
+    let results = web::block(move || {
+        let conn = &mut pool.get()?;
+
+        let results = match kind {
+            SearchType::Users => SearchResults::Users(search_users(conn, viewer, &q, limit)?),
+            SearchType::Posts => {
+                let rows = search_posts(conn, viewer, &q, limit)?;
+                SearchResults::Posts(post_handler::post_responses(conn, rows, &config.auth)?)
+            }
+            SearchType::Hashtags => SearchResults::Hashtags(search_hashtags(conn, viewer, &q, limit)?),
+        };
+        Ok::<_, ApiError>(results)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(results))
+}
@@ -0,0 +1,184 @@
+use actix_web::{web, HttpResponse, HttpRequest};
+use chrono::Utc;
+use diesel::prelude::*;
+use serde_json::json;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::errors::ApiError;
+use crate::models::session::{RefreshRequest, Session, SessionInfo};
+use crate::middleware::AuthUser;
+use crate::tokens::{self, ClientMeta};
+
+#[utoipa::path(
+    post,
+    path = "/api/user/refresh",
+    tag = "ENTRY",
+    request_body = RefreshRequest,
+    responses(
+        (status = 200, description = "New access and refresh token", body = TokenPair),
+        (status = 401, description = "Refresh token invalid, expired or revoked", body = ErrorBody)
+    )
+)]
+
+pub async fn refresh(
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    req: HttpRequest,
+    body: web::Json<RefreshRequest>,
+) -> Result<HttpResponse, ApiError> {
+    body.validate()?;
+
+    let meta = ClientMeta::from_request(&req);
+    let pair = web::block(move || {
+        let mut conn = pool.get()?;
+        tokens::rotate_session(&mut conn, &config.auth, &body.refresh_token, meta)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(pair))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/user/auth/sessions",
+    responses(
+        (status = 200, description = "Active sessions of the current user", body = serde_json::Value),
+        (status = 401, description = "Unauthorized", body = ErrorBody)
+    ),
+    tag = "Sessions",
+    security(
+        ("bearerAuth" = [])
+    )
+)]
+
+pub async fn list_sessions(pool: web::Data<DbPool>, auth: AuthUser) -> Result<HttpResponse, ApiError> {
+    use crate::schema::sessions::dsl as s;
+
+    let user_id = auth.id;
+
+    let rows = web::block(move || {
+        let mut conn = pool.get()?;
+
+        s::sessions
+            .filter(s::user_id.eq(user_id))
+            .filter(s::revoked_at.is_null())
+            .filter(s::expires_at.gt(Utc::now().naive_utc()))
+            .order(s::last_used_at.desc())
+            .select(Session::as_select())
+            .load::<Session>(&mut conn)
+            .map_err(ApiError::from)
+    })
+    .await??;
+
+    let sessions: Vec<SessionInfo> = rows
+        .into_iter()
+        .map(|row| SessionInfo {
+            current: row.id == auth.session_id,
+            id: row.id,
+            user_agent: row.user_agent,
+            ip_address: row.ip_address,
+            created_at: row.created_at,
+            last_used_at: row.last_used_at,
+            expires_at: row.expires_at,
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(json!({ "sessions": sessions })))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/user/auth/sessions/{session_id}",
+    params(
+        ("session_id" = Uuid, Path, description = "Session to revoke")
+    ),
+    responses(
+        (status = 200, description = "Session revoked", body = serde_json::Value),
+        (status = 401, description = "Unauthorized", body = ErrorBody),
+        (status = 404, description = "No such active session", body = ErrorBody)
+    ),
+    tag = "Sessions",
+    security(
+        ("bearerAuth" = [])
+    )
+)]
+
+pub async fn revoke_session(pool: web::Data<DbPool>, auth: AuthUser, path: web::Path<Uuid>) -> Result<HttpResponse, ApiError> {
+    let user_id = auth.id;
+    let session_id = path.into_inner();
+
+    let revoked = web::block(move || {
+        let mut conn = pool.get()?;
+        tokens::revoke_session(&mut conn, user_id, session_id)
+    })
+    .await??;
+
+    if revoked == 0 {
+        return Err(ApiError::NotFound("Session not found".to_string()));
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Session revoked"
+    })))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/user/auth/logout",
+    responses(
+        (status = 200, description = "Current session revoked", body = serde_json::Value),
+        (status = 401, description = "Unauthorized", body = ErrorBody)
+    ),
+    tag = "Sessions",
+    security(
+        ("bearerAuth" = [])
+    )
+)]
+
+pub async fn logout(pool: web::Data<DbPool>, auth: AuthUser) -> Result<HttpResponse, ApiError> {
+    let (user_id, sid) = (auth.id, auth.session_id);
+
+    web::block(move || {
+        let mut conn = pool.get()?;
+        tokens::revoke_session(&mut conn, user_id, sid)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Logged out"
+    })))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/user/auth/logout-all",
+    responses(
+        (status = 200, description = "Every session of the current user revoked", body = serde_json::Value),
+        (status = 401, description = "Unauthorized", body = ErrorBody)
+    ),
+    tag = "Sessions",
+    security(
+        ("bearerAuth" = [])
+    )
+)]
+
+pub async fn logout_all(pool: web::Data<DbPool>, auth: AuthUser) -> Result<HttpResponse, ApiError> {
+    let user_id = auth.id;
+
+    let revoked = web::block(move || {
+        let mut conn = pool.get()?;
+        tokens::revoke_all_sessions(&mut conn, user_id)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "Logged out of all sessions",
+        "revoked": revoked
+    })))
+}
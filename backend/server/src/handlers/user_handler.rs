@@ -1,35 +1,34 @@
 use actix_multipart::Multipart;
-use actix_web::{web, HttpResponse, HttpRequest, HttpMessage, Responder, Error};
-use utoipa::path;
-use tokio::io::AsyncWriteExt;
+use actix_web::{web, HttpResponse, HttpRequest};
 use diesel::prelude::*;
-use diesel::associations::HasTable;
-use serde::{Serialize, Deserialize};
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
 use bcrypt::{hash, verify, DEFAULT_COST};
-use jsonwebtoken::{encode, Header, EncodingKey};
-use lettre::{
-    message::{header, Message},
-    transport::smtp::authentication::Credentials,
-    SmtpTransport, Transport,
-};
 use futures_util::StreamExt;
-use futures_util::TryStreamExt as _;
-use std::io::Write;
 use uuid::Uuid;
 use serde_json::json;
-use chrono::{NaiveDateTime, Utc, Duration};
+use chrono::{NaiveDateTime, Utc};
+use validator::Validate;
 
+use crate::config::Config;
 use crate::db::DbPool;
+use crate::errors::ApiError;
+use crate::handles;
+use crate::jobs::{self, Job};
+use crate::mailer::EmailTemplate;
+use crate::pagination::{self, Cursor, Page, PageParams};
+use crate::policy::{self, Access};
+use crate::middleware::AuthUser;
+use crate::tokens::{self, ClientMeta};
+use crate::storage::{self, MediaStore};
+use crate::upload::{self, Uploads};
+use crate::avatar::{self, AvatarParams};
 use crate::models::user::{
-    User, NewUser, LoginRequest, ForgotPasswordRequest, ResetPasswordRequest, Claims, PasswordResetToken, 
-    UserListItem, Follow, NewFollow, UserProfile, UserUpdate, UserUpdateRequest, PaginationParams, FollowBody,
-    PendingRequest, HandleFollowRequest, FollowerInfo, Followreq 
+    User, NewUser, LoginRequest, ForgotPasswordRequest, ResetPasswordRequest, ChangePasswordRequest, PasswordResetToken,
+    UserListItem, Follow, NewFollow, UserProfile, UserUpdate, UserUpdateRequest, FollowBody,
+    PendingRequest, HandleFollowRequest, FollowerInfo,
 };
 
-use crate::schema::{follows};
-use crate::schema::users::dsl::{users, id, name, email, account_type, phoneno, address};
 use crate::schema::users::dsl::*;
-// use crate::schema::password_reset_tokens::dsl as reset_dsl;
 
 #[utoipa::path(
     post,
@@ -42,38 +41,32 @@ use crate::schema::users::dsl::*;
     ),
     responses(
         (status = 200, description = "User registered successfully"),
-        (status = 400, description = "Invalid input or missing field"),
-        (status = 409, description = "Email already exists")
+        (status = 400, description = "Invalid input or missing field", body = ErrorBody),
+        (status = 409, description = "Email or handle already taken", body = ErrorBody),
+        (status = 413, description = "Profile picture or a field is too large", body = ErrorBody),
+        (status = 415, description = "Profile picture isn't JPEG, PNG or WebP", body = ErrorBody)
     )
 )]
-pub async fn register_user(pool: web::Data<DbPool>, mut payload: Multipart) -> impl Responder {
-    use crate::schema::users::dsl::*;
-    use diesel::prelude::*;
-    use futures_util::StreamExt;
-    use bcrypt::{hash, DEFAULT_COST};
-    use uuid::Uuid;
-
+pub async fn register_user(
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    store: web::Data<dyn MediaStore>,
+    mut payload: Multipart,
+) -> Result<HttpResponse, ApiError> {
     let mut user_name = String::new();
     let mut user_email = String::new();
     let mut user_password = String::new();
     let mut user_address = String::new();
     let mut user_phoneno = String::new();
     let mut user_account_type = String::from("public");
-    let mut profile_pic_filename: Option<String> = None;
+    let mut user_handle = String::new();
+    let mut profile_pic_key: Option<String> = None;
+    let mut uploads = Uploads::new(store.into_inner(), storage::PROFILE_PREFIX);
 
     let mut fields_received = Vec::new();
 
     while let Some(item) = payload.next().await {
-        let mut field = match item {
-            Ok(f) => f,
-            Err(e) => {
-                eprintln!("❌ Multipart read error: {}", e);
-                return HttpResponse::BadRequest().json(json!({
-                    "message": "Invalid multipart upload",
-                    "error": format!("{:?}", e)
-                }));
-            }
-        };
+        let mut field = item?;
 
         let field_name = field
             .content_disposition()
@@ -81,28 +74,12 @@ pub async fn register_user(pool: web::Data<DbPool>, mut payload: Multipart) -> i
             .unwrap_or("")
             .to_string();
 
-       
         fields_received.push(field_name.clone());
 
-        if ["name", "email", "password", "address", "phoneno", "account_type"]
+        if ["name", "email", "password", "address", "phoneno", "account_type", "handle"]
             .contains(&field_name.as_str())
         {
-            let mut data = Vec::new();
-            while let Some(chunk) = field.next().await {
-                match chunk {
-                    Ok(bytes) => data.extend_from_slice(&bytes),
-                    Err(e) => {
-                        eprintln!("❌ Error reading chunk: {}", e);
-                        return HttpResponse::BadRequest().json(json!({
-                            "message": "Error reading field data",
-                            "field": field_name
-                        }));
-                    }
-                }
-            }
-            
-            let value = String::from_utf8_lossy(&data).trim().to_string();
-        
+            let value = upload::read_text(&mut field).await?.trim().to_string();
 
             match field_name.as_str() {
                 "name" => user_name = value,
@@ -111,133 +88,58 @@ pub async fn register_user(pool: web::Data<DbPool>, mut payload: Multipart) -> i
                 "address" => user_address = value,
                 "phoneno" => user_phoneno = value,
                 "account_type" => user_account_type = value,
+                "handle" => user_handle = value,
                 _ => {}
             }
         } else if field_name == "profile_pic" {
             println!("   Processing profile picture...");
-            
-            let upload_dir = "./files/userprofile";
-            if let Err(e) = std::fs::create_dir_all(upload_dir) {
-                eprintln!("❌ Failed to create upload dir: {}", e);
-                return HttpResponse::InternalServerError().json(json!({
-                    "message": "Failed to create upload directory"
-                }));
-            }
 
-            let filename = field
-                .content_disposition()
-                .get_filename()
-                .map(|f| {
-                    println!("   Original filename: {}", f);
-                    format!("{}_{}", Uuid::new_v4(), f)
-                })
-                .unwrap_or_else(|| format!("{}.jpg", Uuid::new_v4()));
-
-            let filepath = format!("{}/{}", upload_dir, filename);
-            println!("   Saving to: {}", filepath);
-
-            match tokio::fs::File::create(&filepath).await {
-                Ok(mut file) => {
-                    let mut total_bytes = 0;
-                    while let Some(chunk) = field.next().await {
-                        match chunk {
-                            Ok(data) => {
-                                total_bytes += data.len();
-                                if let Err(e) = file.write_all(&data).await {
-                                    eprintln!("❌ File write error: {}", e);
-                                    return HttpResponse::InternalServerError().json(json!({
-                                        "message": "Failed to write file"
-                                    }));
-                                }
-                            }
-                            Err(e) => {
-                                eprintln!("❌ Error reading file chunk: {}", e);
-                                return HttpResponse::BadRequest().json(json!({
-                                    "message": "Error reading file data"
-                                }));
-                            }
-                        }
-                    }
-                    println!("   ✅ File saved: {} bytes", total_bytes);
-                    profile_pic_filename = Some(filename);
-                }
-                Err(e) => {
-                    eprintln!("❌ Could not create file at {}: {}", filepath, e);
-                    return HttpResponse::InternalServerError().json(json!({
-                        "message": "Failed to create file"
-                    }));
-                }
-            }
+            profile_pic_key = Some(avatar::save(&mut uploads, &mut field, config.storage.max_image_bytes).await?);
         } else {
             println!("   ⚠️  Unknown field, skipping...");
         }
     }
 
     // ✅ Validate all fields
-    if user_name.is_empty() {
-        return HttpResponse::BadRequest().json(json!({
-            "message": "Name is required",
-            "fields_received": fields_received
-        }));
-    }
-    if user_email.is_empty() {
-        return HttpResponse::BadRequest().json(json!({
-            "message": "Email is required",
-            "fields_received": fields_received
-        }));
-    }
-    if user_password.is_empty() {
-        return HttpResponse::BadRequest().json(json!({
-            "message": "Password is required",
-            "fields_received": fields_received
-        }));
-    }
-    if user_address.is_empty() {
-        return HttpResponse::BadRequest().json(json!({
-            "message": "Address is required",
-            "fields_received": fields_received
-        }));
-    }
-    if user_phoneno.is_empty() {
-        return HttpResponse::BadRequest().json(json!({
-            "message": "Phone number is required",
-            "fields_received": fields_received
-        }));
+    let required = [
+        (&user_name, "Name is required"),
+        (&user_email, "Email is required"),
+        (&user_password, "Password is required"),
+        (&user_address, "Address is required"),
+        (&user_phoneno, "Phone number is required"),
+    ];
+    if let Some((_, message)) = required.iter().find(|(value, _)| value.is_empty()) {
+        return Err(ApiError::validation(*message, json!({ "fields_received": fields_received })));
     }
 
     println!("✅ All validations passed");
 
-    let mut conn = match pool.get() {
-        Ok(c) => c,
-        Err(e) => {
-            eprintln!("❌ DB connection error: {}", e);
-            return HttpResponse::InternalServerError()
-                .json(json!({"message": "Database connection failed"}));
-        }
-    };
+    let mut conn = pool.get()?;
 
     println!("🔍 Checking for existing user...");
     let existing = users
         .filter(email.eq(&user_email))
         .first::<User>(&mut conn)
-        .optional()
-        .unwrap_or(None);
+        .optional()?;
 
     if existing.is_some() {
         println!("❌ Email already exists");
-        return HttpResponse::Conflict().json(json!({"message": "Email already exists"}));
+        return Err(ApiError::Conflict("Email already exists".to_string()));
     }
 
-    println!("🔐 Hashing password...");
-    let hashed = match hash(&user_password, DEFAULT_COST) {
-        Ok(h) => h,
-        Err(e) => {
-            eprintln!("❌ Hash error: {}", e);
-            return HttpResponse::InternalServerError()
-                .json(json!({"message": "Password hashing failed"}));
+    let user_handle = if user_handle.is_empty() {
+        handles::derive(&mut conn, &user_name)?
+    } else {
+        let chosen = handles::normalize(&user_handle)?;
+        if handles::is_taken(&mut conn, &chosen)? {
+            return Err(ApiError::Conflict("Handle already taken".to_string()));
         }
+        chosen
     };
 
+    println!("🔐 Hashing password...");
+    let hashed = hash(&user_password, DEFAULT_COST)?;
+
     let new_user = NewUser {
         name: user_name.clone(),
         email: user_email.clone(),
@@ -245,23 +147,32 @@ pub async fn register_user(pool: web::Data<DbPool>, mut payload: Multipart) -> i
         address: user_address.clone(),
         phoneno: user_phoneno.clone(),
         account_type: user_account_type.clone(),
-        profile_pic: profile_pic_filename.clone(),
+        profile_pic: profile_pic_key.clone(),
+        handle: user_handle,
     };
 
     println!("💾 Inserting user into database...");
-    if let Err(e) = diesel::insert_into(users).values(&new_user).execute(&mut conn) {
-        eprintln!("❌ DB insert error: {}", e);
-        return HttpResponse::InternalServerError().json(json!({
-            "message": "Database insert failed",
-            "error": format!("{:?}", e)
-        }));
-    }
+    diesel::insert_into(users).values(&new_user).execute(&mut conn)?;
+    uploads.keep();
 
     println!("✅ User registered successfully: {}", user_email);
-    HttpResponse::Ok().json(json!({
+
+    let welcome = Job::SendEmail {
+        to: user_email.clone(),
+        template: EmailTemplate::Welcome {
+            name: user_name.clone(),
+            app_link: format!("{}/login", config.frontend_url.trim_end_matches('/')),
+        },
+    };
+    if let Err(e) = jobs::enqueue(&mut conn, &welcome) {
+        eprintln!("❌ Failed to queue welcome email: {}", e);
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
         "message": "Registered successfully ✅",
-        "email": user_email
-    }))
+        "email": user_email,
+        "handle": new_user.handle
+    })))
 }
 
 #[utoipa::path(
@@ -271,63 +182,48 @@ pub async fn register_user(pool: web::Data<DbPool>, mut payload: Multipart) -> i
     request_body = LoginRequest,
     responses(
         (status = 200, description = "Login successful", body = serde_json::Value),
-        (status = 401, description = "Unauthorized"),
-        (status = 500, description = "Internal server error")
+        (status = 401, description = "Unauthorized", body = ErrorBody),
+        (status = 500, description = "Internal server error", body = ErrorBody)
     )
 )]
 
-pub async fn login(pool: web::Data<DbPool>, data: web::Json<LoginRequest>) -> impl Responder {
-    let mut conn = match pool.get() {
-        Ok(c) => c,
-        Err(_) => return HttpResponse::InternalServerError().json("Database connection failed"),
-    };
+pub async fn login(
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    req: HttpRequest,
+    data: web::Json<LoginRequest>,
+) -> Result<HttpResponse, ApiError> {
+    data.validate()?;
 
-    let result = users.filter(email.eq(&data.email)).first::<User>(&mut conn);
+    let mut conn = pool.get()?;
 
-    match result {
-        Ok(user) => {
-            let is_valid = verify(&data.password, &user.password).unwrap_or(false);
+    let user = users
+        .filter(email.eq(&data.email))
+        .first::<User>(&mut conn)
+        .optional()?
+        .ok_or_else(|| ApiError::Unauthorized("User not found".to_string()))?;
 
-            if !is_valid {
-                return HttpResponse::Unauthorized().json(serde_json::json!({
-                    "message": "Invalid password"
-                }));
-            }
+    let is_valid = verify(&data.password, &user.password).unwrap_or(false);
 
-    
-            let expiration = Utc::now()
-                .checked_add_signed(Duration::hours(24))
-                .expect("valid timestamp")
-                .timestamp() as usize;
-
-            let claims = Claims {
-                id: user.id.to_string(),
-                name: user.name.clone(),
-                email: user.email.clone(),
-                exp: expiration,
-            };
-
-            let secret = std::env::var("JWT_SECRET").unwrap_or_else(|_| "mysecretkey".into());
-
-            let token = match encode(&Header::default(), &claims, &EncodingKey::from_secret(secret.as_ref())) {
-                Ok(t) => t,
-                Err(_) => return HttpResponse::InternalServerError().json("Token creation failed"),
-            };
-
-            HttpResponse::Ok().json(serde_json::json!({
-                "message": "Login successful",
-                "token": token,
-                "user": {
-                    "id": user.id,
-                    "name": user.name,
-                    "email": user.email,
-                }
-            }))
-        }
-        Err(_) => HttpResponse::Unauthorized().json(serde_json::json!({
-            "message": "User not found"
-        })),
+    if !is_valid {
+        return Err(ApiError::Unauthorized("Invalid password".to_string()));
     }
+
+    let pair = tokens::start_session(&mut conn, &config.auth, &user, ClientMeta::from_request(&req))?;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "message": "Login successful",
+        "token": pair.token,
+        "refresh_token": pair.refresh_token,
+        "expires_in": pair.expires_in,
+        "session_id": pair.session_id,
+        "user": {
+            "id": user.id,
+            "name": user.name,
+            "handle": user.handle,
+            "email": user.email,
+        }
+    })))
 }
 
 #[utoipa::path(
@@ -341,65 +237,55 @@ pub async fn login(pool: web::Data<DbPool>, data: web::Json<LoginRequest>) -> im
     ),
     responses(
         (status = 200, description = "Reset link sent successfully", body = serde_json::Value),
-        (status = 400, description = "Invalid user email", body = serde_json::Value),
-        (status = 500, description = "Email sending failed or database error", body = serde_json::Value)
+        (status = 400, description = "Invalid user email", body = ErrorBody),
+        (status = 500, description = "Email sending failed or database error", body = ErrorBody)
     ),
 
 )]
 
-pub async fn forgot_password(pool: web::Data<DbPool>,body: web::Json<ForgotPasswordRequest>,) -> impl Responder {
-    let conn = &mut pool.get().unwrap();
-
-    let user_result = users.filter(email.eq(&body.email)).first::<User>(conn);
-
-    if let Ok(user) = user_result {
-        let token = Uuid::new_v4().to_string();
-        let expires_at = Utc::now().naive_utc() + chrono::Duration::hours(1);
+pub async fn forgot_password(
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    body: web::Json<ForgotPasswordRequest>,
+) -> Result<HttpResponse, ApiError> {
+    use crate::schema::password_reset_tokens::dsl as t;
+
+    body.validate()?;
+
+    let conn = &mut pool.get()?;
+
+    let user = users
+        .filter(email.eq(&body.email))
+        .first::<User>(conn)
+        .optional()?
+        .ok_or_else(|| ApiError::BadRequest("Invalid user email".to_string()))?;
+
+    let token = Uuid::new_v4().to_string();
+    let expires_at = Utc::now().naive_utc() + chrono::Duration::hours(1);
+    let reset_link = format!("{}/reset-password?token={}", config.frontend_url.trim_end_matches('/'), token);
+
+    let job = Job::SendEmail {
+        to: user.email.clone(),
+        template: EmailTemplate::PasswordReset {
+            name: user.name.clone(),
+            reset_link,
+            expires_in_minutes: 60,
+        },
+    };
 
-        let _ = diesel::insert_into(crate::schema::password_reset_tokens::table)
+    conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        diesel::insert_into(t::password_reset_tokens)
             .values((
-                crate::schema::password_reset_tokens::user_id.eq(user.id),
-                crate::schema::password_reset_tokens::token.eq(&token),
-                crate::schema::password_reset_tokens::expires_at.eq(expires_at),
+                t::user_id.eq(user.id),
+                t::token.eq(&token),
+                t::expires_at.eq(expires_at),
             ))
-            .execute(conn);
-
-        let reset_link = format!("http://localhost:5173/reset-password?token={}", token);
-
-        let email_result = {
-            let email_sender = "samuvel2k4@gmail.com";
-            let smtp_username = "samuvel2k4@gmail.com";
-            let smtp_password = "mbqy zkpi ybob xxyb";
-            let smtp_server = "smtp.gmail.com";
-
-            let mail_msg = Message::builder()
-                .from(email_sender.parse().unwrap())
-                .to(user.email.parse().unwrap())
-                .subject("Password Reset Request")
-                .header(header::ContentType::TEXT_HTML)
-                .body(format!(
-                    "<p>Hello, {}</p>\
-                     <p>Click below to reset your password:</p>\
-                     <a href=\"{}\">Reset Password</a>\
-                     <p>This link expires in 1 hour.</p>",
-                    user.name, reset_link
-                ))
-                .unwrap();
-
-            let creds = Credentials::new(smtp_username.to_string(), smtp_password.to_string());
-            let mailer = SmtpTransport::relay(smtp_server).unwrap().credentials(creds).build();
-
-            mailer.send(&mail_msg)
-        };
+            .execute(conn)?;
+        jobs::enqueue(conn, &job)?;
+        Ok(())
+    })?;
 
-        match email_result {
-            Ok(_) => HttpResponse::Ok().json(json!({ "message": "Reset link sent successfully" })),
-            Err(err) => HttpResponse::InternalServerError()
-                .json(json!({ "error": format!("Email send failed: {}", err) })),
-        }
-    } else {
-        HttpResponse::BadRequest().json(json!({ "error": "Invalid user email" }))
-    }
+    Ok(HttpResponse::Ok().json(json!({ "message": "Reset link sent successfully" })))
 }
 
 #[utoipa::path(
@@ -413,62 +299,106 @@ pub async fn forgot_password(pool: web::Data<DbPool>,body: web::Json<ForgotPassw
     ),
     responses(
         (status = 200, description = "Password reset successful", body = serde_json::Value),
-        (status = 400, description = "Token expired or invalid", body = serde_json::Value),
-        (status = 500, description = "Database error or password hashing failed", body = serde_json::Value)
+        (status = 400, description = "Token expired or invalid", body = ErrorBody),
+        (status = 500, description = "Database error or password hashing failed", body = ErrorBody)
     ),
-   
+
 )]
 
-pub async fn reset_password(pool: web::Data<DbPool>,body: web::Json<ResetPasswordRequest>,) -> impl Responder {
+pub async fn reset_password(pool: web::Data<DbPool>, body: web::Json<ResetPasswordRequest>,) -> Result<HttpResponse, ApiError> {
     use crate::schema::{users::dsl as u, password_reset_tokens::dsl as t};
 
-    let mut conn = match pool.get() {
-        Ok(c) => c,
-        Err(_) => return HttpResponse::InternalServerError().json(json!({ "error": "Database connection failed" })),
-    };
+    body.validate()?;
+
+    let mut conn = pool.get()?;
 
-    let token_row = t::password_reset_tokens
+    let reset_row = t::password_reset_tokens
         .filter(t::token.eq(&body.token))
         .filter(t::expires_at.gt(Utc::now().naive_utc()))
-        .first::<PasswordResetToken>(&mut conn);
+        .first::<PasswordResetToken>(&mut conn)
+        .optional()?
+        .ok_or_else(|| ApiError::BadRequest("Token expired or invalid".to_string()))?;
 
-    if let Ok(reset_row) = token_row {
-        let hashed = match hash(&body.new_password, 10) {
-            Ok(h) => h,
-            Err(_) => return HttpResponse::InternalServerError().json(json!({ "error": "Password hashing failed" })),
-        };
+    let hashed = hash(&body.new_password, 10)?;
 
-        if diesel::update(u::users.filter(u::id.eq(reset_row.user_id)))
+    conn.transaction::<_, ApiError, _>(|conn| {
+        diesel::update(u::users.filter(u::id.eq(reset_row.user_id)))
             .set(u::password.eq(&hashed))
-            .execute(&mut conn)
-            .is_err()
-        {
-            return HttpResponse::InternalServerError().json(json!({ "error": "Failed to update password" }));
-        }
+            .execute(conn)?;
 
-        if diesel::delete(t::password_reset_tokens.filter(t::token.eq(&body.token)))
-            .execute(&mut conn)
-            .is_err()
-        {
-            return HttpResponse::InternalServerError().json(json!({ "error": "Failed to delete token" }));
-        }
+        diesel::delete(t::password_reset_tokens.filter(t::token.eq(&body.token)))
+            .execute(conn)?;
 
-        HttpResponse::Ok().json(json!({ "message": "Password reset successful" }))
-    } else {
-        HttpResponse::BadRequest().json(json!({ "error": "Token expired or invalid" }))
+        // Whoever knew the old password may still hold tokens.
+        tokens::revoke_all_sessions(conn, reset_row.user_id)?;
+        Ok(())
+    })?;
+
+    Ok(HttpResponse::Ok().json(json!({ "message": "Password reset successful" })))
+}
+
+#[utoipa::path(
+    post,
+    path = "/api/user/auth/change-password",
+    request_body = ChangePasswordRequest,
+    responses(
+        (status = 200, description = "Password changed; other sessions are logged out and a new token pair is returned", body = TokenPair),
+        (status = 400, description = "New password is invalid", body = ErrorBody),
+        (status = 401, description = "Current password is wrong", body = ErrorBody)
+    ),
+    tag = "User",
+    security(
+        ("bearerAuth" = [])
+    )
+)]
+
+pub async fn change_password(
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    auth: AuthUser,
+    req: HttpRequest,
+    body: web::Json<ChangePasswordRequest>,
+) -> Result<HttpResponse, ApiError> {
+    body.validate()?;
+
+    let user = auth.user;
+    if !verify(&body.current_password, &user.password).unwrap_or(false) {
+        return Err(ApiError::Unauthorized("Current password is incorrect".to_string()));
     }
+
+    let hashed = hash(&body.new_password, DEFAULT_COST)?;
+    let meta = ClientMeta::from_request(&req);
+
+    let pair = web::block(move || {
+        let mut conn = pool.get()?;
+
+        conn.transaction::<_, ApiError, _>(|conn| {
+            diesel::update(users.filter(id.eq(user.id)))
+                .set(password.eq(&hashed))
+                .execute(conn)?;
+
+            tokens::revoke_all_sessions(conn, user.id)?;
+
+            let user = users.filter(id.eq(user.id)).first::<User>(conn)?;
+            tokens::start_session(conn, &config.auth, &user, meta)
+        })
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(pair))
 }
 
 #[utoipa::path(
     get,
     path = "/api/user/auth/get-users",
     params(
-        PaginationParams
+        PageParams
     ),
     responses(
-        (status = 200, description = "List of users with pagination", body = serde_json::Value),
-        (status = 401, description = "Unauthorized", body = serde_json::Value),
-        (status = 500, description = "Database error", body = serde_json::Value)
+        (status = 200, description = "Other users, newest first", body = UserPage),
+        (status = 400, description = "Invalid cursor", body = ErrorBody),
+        (status = 401, description = "Unauthorized", body = ErrorBody),
+        (status = 500, description = "Database error", body = ErrorBody)
     ),
     tag = "User",
      security(
@@ -476,38 +406,26 @@ pub async fn reset_password(pool: web::Data<DbPool>,body: web::Json<ResetPasswor
     )
 )]
 
-pub async fn get_users(pool: web::Data<DbPool>,req: HttpRequest,query: web::Query<PaginationParams>,) -> Result<HttpResponse, Error> {
-    let extensions = req.extensions();
-    let logged = match extensions.get::<User>() {
-        Some(u) => u.clone(), 
-        None => {
-            return Ok(HttpResponse::Unauthorized().json(json!({
-                "message": "Unauthorized"
-            })));
-        }
-    };
+pub async fn get_users(pool: web::Data<DbPool>, auth: AuthUser, query: web::Query<PageParams>,) -> Result<HttpResponse, ApiError> {
+    let limit = query.limit();
+    let after = query.cursor()?;
 
-    let mut conn = pool
-        .get()
-        .map_err(|_| actix_web::error::ErrorInternalServerError("Failed to get DB connection"))?;
+    let mut conn = pool.get()?;
 
-    let page = query.page.unwrap_or(1);
-    let limit = query.limit.unwrap_or(6);
-    let offset = (page - 1) * limit;
-    let rows = users
-        .filter(id.ne(logged.id))
+    let mut q = users
+        .filter(id.ne(auth.id))
         .select((id, name, email, account_type, profile_pic, created_at))
-        .order(id.asc())
-        .limit(limit)
-        .offset(offset)
-        .load::<UserListItem>(&mut conn)
-        .unwrap_or_default();
+        .order((created_at.desc(), id.desc()))
+        .limit(limit + 1)
+        .into_boxed();
 
-    Ok(HttpResponse::Ok().json(json!({
-        "page": page,
-        "limit": limit,
-        "users": rows
-    })))
+    if let Some(c) = after {
+        q = q.filter(pagination::keyset_before(created_at, id, &c));
+    }
+
+    let rows = q.load::<UserListItem>(&mut conn)?;
+
+    Ok(HttpResponse::Ok().json(Page::from_rows(rows, limit, |u| Cursor::new(u.created_at, u.id))))
 }
 
 #[utoipa::path(
@@ -516,8 +434,10 @@ pub async fn get_users(pool: web::Data<DbPool>,req: HttpRequest,query: web::Quer
     request_body = FollowBody,
     responses(
         (status = 200, description = "Follow action processed successfully", body = serde_json::Value),
-        (status = 400, description = "Bad request, invalid or missing fields", body = serde_json::Value),
-        (status = 500, description = "Database error", body = serde_json::Value)
+        (status = 400, description = "Unknown action or attempt to follow yourself", body = ErrorBody),
+        (status = 403, description = "userId is not the caller, or one of the users has blocked the other", body = ErrorBody),
+        (status = 404, description = "Target user or follow relationship not found", body = ErrorBody),
+        (status = 409, description = "Already following or request already sent", body = ErrorBody)
     ),
     tag = "User",
      security(
@@ -525,119 +445,116 @@ pub async fn get_users(pool: web::Data<DbPool>,req: HttpRequest,query: web::Quer
     )
 )]
 
-pub async fn follow_button(pool: web::Data<DbPool>,body: web::Json<FollowBody>,) -> Result<HttpResponse, Error> {
+pub async fn follow_button(
+    pool: web::Data<DbPool>,
+    config: web::Data<Config>,
+    auth: AuthUser,
+    body: web::Json<FollowBody>,
+) -> Result<HttpResponse, ApiError> {
     use crate::schema::follows::dsl::*;
-    use chrono::Utc;
-    use diesel::prelude::*;
-
-    let mut conn = pool.get().unwrap();
-
-    let uid = match Uuid::parse_str(&body.userId) {
-        Ok(u) => u,
-        Err(_) => {
-            return Ok(HttpResponse::BadRequest().json(json!({
-                "success": false,
-                "message": "Invalid userId"
-            })))
-        }
-    };
+    use crate::schema::users::dsl as u;
 
-    let tid = match Uuid::parse_str(&body.targetId) {
-        Ok(t) => t,
-        Err(_) => {
-            return Ok(HttpResponse::BadRequest().json(json!({
-                "success": false,
-                "message": "Invalid targetId"
-            })))
-        }
-    };
+    let uid = auth.id;
+    let tid = body.target_id;
 
-    if body.action.trim().is_empty() {
-        return Ok(HttpResponse::BadRequest().json(json!({
-            "success": false,
-            "message": "Missing or invalid fields"
-        })));
+    if body.user_id.is_some_and(|claimed| claimed != uid) {
+        return Err(ApiError::Forbidden("You can only follow or unfollow as yourself".to_string()));
     }
 
-    // 🧱 Handle rejected follow resend
-    let rejected = follows
-        .filter(user_id.eq(uid))
-        .filter(target_id.eq(tid))
-        .filter(status.eq("rejected"))
-        .first::<Follow>(&mut conn)
-        .optional()
-        .unwrap();
-
-    if let Some(r) = rejected {
-        diesel::update(follows.filter(id.eq(r.id)))
-            .set((status.eq("pending"), created_at.eq(Utc::now().naive_utc())))
-            .execute(&mut conn)
-            .unwrap();
-
-        return Ok(HttpResponse::Ok().json(json!({
-            "success": true,
-            "message": "Follow request sent again",
-            "status": "pending"
-        })));
+    if uid == tid {
+        return Err(ApiError::BadRequest("You cannot follow yourself".to_string()));
     }
 
-    if body.action == "unfollow" {
-        let deleted_count = diesel::delete(
-            follows
-                .filter(user_id.eq(uid))
-                .filter(target_id.eq(tid)),
-        )
-        .execute(&mut conn)
-        .unwrap();
+    let mut conn = pool.get()?;
+
+    match body.action.as_str() {
+        "follow" => {}
+        "unfollow" => {
+            // Also cancels a pending request.
+            let deleted_count = diesel::delete(
+                follows
+                    .filter(user_id.eq(uid))
+                    .filter(target_id.eq(tid)),
+            )
+            .execute(&mut conn)?;
+
+            if deleted_count == 0 {
+                return Err(ApiError::NotFound("No follow relationship found".to_string()));
+            }
 
-        if deleted_count == 0 {
             return Ok(HttpResponse::Ok().json(json!({
-                "success": false,
-                "message": "No follow relationship found"
+                "success": true,
+                "message": "Unfollowed successfully",
+                "deleted_count": deleted_count
             })));
         }
+        _ => return Err(ApiError::BadRequest("Invalid action. Use 'follow' or 'unfollow'".to_string())),
+    }
+
+    let target = u::users
+        .filter(u::id.eq(tid))
+        .first::<User>(&mut conn)
+        .optional()?
+        .ok_or_else(|| ApiError::NotFound("User not found".to_string()))?;
 
-        return Ok(HttpResponse::Ok().json(json!({
-            "success": true,
-            "message": "Unfollowed successfully",
-            "deleted_count": deleted_count
-        })));
+    if policy::is_blocked_between(&mut conn, uid, tid)? {
+        return Err(ApiError::Forbidden("You cannot follow this user".to_string()));
     }
 
-    // 🟢 Handle follow or follow-request
-    let status_val = if body.isRequest.unwrap_or(false) {
-        "pending"
-    } else {
-        "accepted"
-    };
+    // 🟢 Private accounts approve followers first; the client doesn't get a say.
+    let status_val = if target.account_type == "private" { "pending" } else { "accepted" };
 
-    let new_follow = NewFollow {
-        user_id: uid,
-        target_id: tid,
-        status: status_val.to_string(),
-    };
+    let existing = follows
+        .filter(user_id.eq(uid))
+        .filter(target_id.eq(tid))
+        .first::<Follow>(&mut conn)
+        .optional()?;
+
+    match existing {
+        // 🧱 A rejected request may be sent again
+        Some(r) if r.status == "rejected" => {
+            diesel::update(follows.filter(id.eq(r.id)))
+                .set((status.eq(status_val), created_at.eq(Utc::now())))
+                .execute(&mut conn)?;
+        }
+        Some(r) if r.status == "pending" => {
+            return Err(ApiError::Conflict("Follow request already sent".to_string()));
+        }
+        Some(_) => return Err(ApiError::Conflict("Already following this user".to_string())),
+        None => {
+            let inserted = diesel::insert_into(follows)
+                .values(&NewFollow {
+                    user_id: uid,
+                    target_id: tid,
+                    status: status_val.to_string(),
+                })
+                .on_conflict((user_id, target_id))
+                .do_nothing()
+                .execute(&mut conn)?;
 
-    let insert_res = diesel::insert_into(follows)
-        .values(&new_follow)
-        .on_conflict((user_id, target_id))
-        .do_nothing()
-        .execute(&mut conn)
-        .unwrap();
-
-    if insert_res == 0 {
-        return Ok(HttpResponse::Ok().json(json!({
-            "success": false,
-            "message": if body.isRequest.unwrap_or(false) {
-                "Follow request already sent"
-            } else {
-                "Already following this user"
+            if inserted == 0 {
+                return Err(ApiError::Conflict("Already following this user".to_string()));
             }
-        })));
+        }
+    }
+
+    if status_val == "accepted" {
+        let notify = Job::SendEmail {
+            to: target.email,
+            template: EmailTemplate::NewFollower {
+                name: target.name,
+                follower_name: auth.name.clone(),
+                profile_link: format!("{}/profile/{}", config.frontend_url.trim_end_matches('/'), uid),
+            },
+        };
+        if let Err(e) = jobs::enqueue(&mut conn, &notify) {
+            eprintln!("❌ Failed to queue follower email: {}", e);
+        }
     }
 
     Ok(HttpResponse::Ok().json(json!({
         "success": true,
-        "message": if body.isRequest.unwrap_or(false) {
+        "message": if status_val == "pending" {
             "Follow request sent"
         } else {
             "Now following this user"
@@ -646,34 +563,132 @@ pub async fn follow_button(pool: web::Data<DbPool>,body: web::Json<FollowBody>,)
     })))
 }
 
+#[utoipa::path(
+    post,
+    path = "/api/user/auth/block/{user_id}",
+    params(
+        ("user_id" = Uuid, Path, description = "User to block")
+    ),
+    responses(
+        (status = 200, description = "User blocked; follows in both directions are removed", body = serde_json::Value),
+        (status = 400, description = "Attempt to block yourself", body = ErrorBody),
+        (status = 404, description = "User not found", body = ErrorBody)
+    ),
+    tag = "User",
+    security(
+        ("bearerAuth" = [])
+    )
+)]
+
+pub async fn block_user(pool: web::Data<DbPool>, auth: AuthUser, path: web::Path<Uuid>) -> Result<HttpResponse, ApiError> {
+    use crate::schema::blocks::dsl as b;
+    use crate::schema::follows::dsl as f;
+
+    let blocker = auth.id;
+    let blocked = path.into_inner();
+
+    if blocker == blocked {
+        return Err(ApiError::BadRequest("You cannot block yourself".to_string()));
+    }
+
+    let mut conn = pool.get()?;
+
+    let exists: bool = diesel::select(diesel::dsl::exists(users.filter(id.eq(blocked)))).get_result(&mut conn)?;
+    if !exists {
+        return Err(ApiError::NotFound("User not found".to_string()));
+    }
+
+    conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        diesel::insert_into(b::blocks)
+            .values((b::blocker_id.eq(blocker), b::blocked_id.eq(blocked)))
+            .on_conflict_do_nothing()
+            .execute(conn)?;
+
+        diesel::delete(
+            f::follows.filter(
+                f::user_id.eq(blocker).and(f::target_id.eq(blocked))
+                    .or(f::user_id.eq(blocked).and(f::target_id.eq(blocker))),
+            ),
+        )
+        .execute(conn)?;
+        Ok(())
+    })?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "User blocked"
+    })))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/user/auth/block/{user_id}",
+    params(
+        ("user_id" = Uuid, Path, description = "User to unblock")
+    ),
+    responses(
+        (status = 200, description = "User unblocked", body = serde_json::Value),
+        (status = 404, description = "User is not blocked", body = ErrorBody)
+    ),
+    tag = "User",
+    security(
+        ("bearerAuth" = [])
+    )
+)]
+
+pub async fn unblock_user(pool: web::Data<DbPool>, auth: AuthUser, path: web::Path<Uuid>) -> Result<HttpResponse, ApiError> {
+    use crate::schema::blocks::dsl as b;
+
+    let mut conn = pool.get()?;
+
+    let deleted = diesel::delete(
+        b::blocks
+            .filter(b::blocker_id.eq(auth.id))
+            .filter(b::blocked_id.eq(path.into_inner())),
+    )
+    .execute(&mut conn)?;
+
+    if deleted == 0 {
+        return Err(ApiError::NotFound("User is not blocked".to_string()));
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "success": true,
+        "message": "User unblocked"
+    })))
+}
+
 #[utoipa::path(
     get,
-    path = "/api/user/auth/following/{user_id}",
+    path = "/api/user/auth/request/{user_id}",
     params(
-        ("user_id" = String, Path, description = "UUID of the user to fetch following list")
+        ("user_id" = Uuid, Path, description = "The caller's own id")
     ),
     responses(
-        (status = 200, description = "List of users this user is following", body = serde_json::Value),
-        (status = 400, description = "Invalid user ID provided", body = serde_json::Value),
-        (status = 500, description = "Database error", body = serde_json::Value)
+        (status = 200, description = "Ids the current user follows or has pending requests to", body = serde_json::Value),
+        (status = 401, description = "Unauthorized", body = ErrorBody),
+        (status = 403, description = "Path id is not the caller's own", body = ErrorBody),
+        (status = 500, description = "Database error", body = ErrorBody)
     ),
-    tag = "User"
+    tag = "User",
+    security(
+        ("bearerAuth" = [])
+    )
 )]
 
-pub async fn following(pool: web::Data<DbPool>, path: web::Path<String>) -> Result<HttpResponse, Error> {
-    let user_id_str = path.into_inner();
-    let mut conn = pool.get().unwrap();
+pub async fn following(pool: web::Data<DbPool>, auth: AuthUser, path: web::Path<Uuid>) -> Result<HttpResponse, ApiError> {
     use crate::schema::follows::dsl::*;
 
-    let uid = match Uuid::parse_str(&user_id_str) {
-        Ok(u) => u,
-        Err(_) => return Ok(HttpResponse::BadRequest().json(serde_json::json!({"message": "Invalid user id"}))),
-    };
+    let uid = auth.id;
+    if path.into_inner() != uid {
+        return Err(ApiError::Forbidden("You can only list your own follows".to_string()));
+    }
+
+    let mut conn = pool.get()?;
 
     let rows = follows
         .filter(user_id.eq(uid))
-        .load::<Follow>(&mut conn)
-        .unwrap_or_default();
+        .load::<Follow>(&mut conn)?;
 
     let following_list: Vec<uuid::Uuid> = rows.iter().filter(|r| r.status == "accepted").map(|r| r.target_id).collect();
     let pending: Vec<uuid::Uuid> = rows.iter().filter(|r| r.status == "pending").map(|r| r.target_id).collect();
@@ -687,10 +702,15 @@ pub async fn following(pool: web::Data<DbPool>, path: web::Path<String>) -> Resu
 #[utoipa::path(
     get,
     path = "/api/user/auth/profile/{user_id}",
+    params(
+        ("user_id" = Uuid, Path, description = "Profile to fetch"),
+        AvatarParams
+    ),
     responses(
-        (status = 200, description = "Fetch logged-in user's profile", body = serde_json::Value),
-        (status = 401, description = "Unauthorized", body = serde_json::Value),
-        (status = 404, description = "User not found", body = serde_json::Value)
+        (status = 200, description = "User profile. Contact details are null when the account is private and not followed by the caller", body = serde_json::Value),
+        (status = 400, description = "Unsupported avatar_size", body = ErrorBody),
+        (status = 401, description = "Unauthorized", body = ErrorBody),
+        (status = 404, description = "User not found", body = ErrorBody)
     ),
     tag = "User",
     security(
@@ -698,62 +718,62 @@ pub async fn following(pool: web::Data<DbPool>, path: web::Path<String>) -> Resu
     )
 )]
 
-pub async fn profile_get(pool: web::Data<DbPool>,req: HttpRequest,) -> Result<HttpResponse, Error> {
-    let extensions = req.extensions();
-    let user = match extensions.get::<User>() {
-    Some(u) => u,
-    None => {
-        return Ok(HttpResponse::Unauthorized().json(serde_json::json!({
-            "message": "Unauthorized"
-        })));
-    }
-      };
+pub async fn profile_get(pool: web::Data<DbPool>, auth: AuthUser, path: web::Path<Uuid>,
+    query: web::Query<AvatarParams>,) -> Result<HttpResponse, ApiError> {
+    use crate::schema::follows::dsl as f;
+
+    let uid = path.into_inner();
+    let avatar_size = query.size()?;
 
-    let uid = user.id;
+    let mut conn = pool.get()?;
 
-    let mut conn = pool.get().unwrap();
+    // ✅ 1. Check what the caller may see
+    let access = policy::access(&mut conn, Some(auth.id), uid)?;
 
     // ✅ 2. Fetch profile details
-    let user_opt = users
+    let user = users
         .filter(id.eq(uid))
         .select(UserProfile::as_select())
         .first::<UserProfile>(&mut conn)
-        .optional()
-        .unwrap();
-
-    if user_opt.is_none() {
-        return Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "message": "User not found"
-        })));
-    }
-
-    let user = user_opt.unwrap();
+        .optional()?
+        .ok_or_else(|| ApiError::NotFound("User not found".to_string()))?;
 
     // ✅ 3. Count followers & following
-    let followers_cnt: i64 = crate::schema::follows::dsl::follows
-        .filter(crate::schema::follows::dsl::target_id.eq(uid))
-        .filter(crate::schema::follows::dsl::status.eq("accepted"))
+    let followers_cnt: i64 = f::follows
+        .filter(f::target_id.eq(uid))
+        .filter(f::status.eq("accepted"))
         .count()
-        .get_result(&mut conn)
-        .unwrap_or(0);
+        .get_result(&mut conn)?;
 
-    let following_cnt: i64 = crate::schema::follows::dsl::follows
-        .filter(crate::schema::follows::dsl::user_id.eq(uid))
-        .filter(crate::schema::follows::dsl::status.eq("accepted"))
+    let following_cnt: i64 = f::follows
+        .filter(f::user_id.eq(uid))
+        .filter(f::status.eq("accepted"))
         .count()
-        .get_result(&mut conn)
-        .unwrap_or(0);
+        .get_result(&mut conn)?;
+
+    let posts_cnt: i64 = {
+        use crate::schema::user_posts::dsl as p;
+        p::user_posts
+            .filter(p::user_id.eq(uid))
+            .count()
+            .get_result(&mut conn)?
+    };
+
+    let full = access == Access::Full;
 
     Ok(HttpResponse::Ok().json(serde_json::json!({
         "id": user.id,
         "username": user.name,
-        "email": user.email,
-        "profile_pic": user.profile_pic,
+        "handle": user.handle,
+        "email": full.then_some(user.email),
+        "profile_pic": user.profile_pic.map(|key| storage::avatar_url(&key, avatar_size)),
         "accountType": user.account_type,
-        "phoneNo": user.phoneno,
-        "address": user.address,
+        "phoneNo": full.then_some(user.phoneno),
+        "address": if full { user.address } else { None },
         "FollowersCount": followers_cnt,
         "FollowingCount": following_cnt,
+        "PostsCount": posts_cnt,
+        "canViewContent": full,
     })))
 }
 
@@ -763,9 +783,10 @@ pub async fn profile_get(pool: web::Data<DbPool>,req: HttpRequest,) -> Result<Ht
     request_body = UserUpdateRequest,
     responses(
         (status = 200, description = "Profile updated successfully", body = serde_json::Value),
-        (status = 400, description = "Bad request, e.g., missing loggedInUserId", body = serde_json::Value),
-        (status = 401, description = "Unauthorized to update profile", body = serde_json::Value),
-        (status = 500, description = "Failed to update profile", body = serde_json::Value)
+        (status = 400, description = "Invalid or empty update", body = ErrorBody),
+        (status = 401, description = "Unauthorized", body = ErrorBody),
+        (status = 403, description = "Path id is not the caller's own", body = ErrorBody),
+        (status = 409, description = "Email or handle already in use", body = ErrorBody)
     ),
     tag = "User",
     security(
@@ -773,78 +794,82 @@ pub async fn profile_get(pool: web::Data<DbPool>,req: HttpRequest,) -> Result<Ht
     )
 )]
 
-pub async fn profile_update(pool: web::Data<DbPool>,req: HttpRequest,body: web::Json<UserUpdateRequest>,) -> Result<HttpResponse, Error> 
-    {
+pub async fn profile_update(pool: web::Data<DbPool>, auth: AuthUser, path: web::Path<Uuid>, body: web::Json<UserUpdateRequest>,) -> Result<HttpResponse, ApiError> {
+    if path.into_inner() != auth.id {
+        return Err(ApiError::Forbidden("You can only update your own profile".to_string()));
+    }
+
+    body.validate()?;
+    let body = body.into_inner();
 
-        let extensions = req.extensions();
-    let user = match extensions.get::<User>() {
-    Some(u) => u,
-    None => {
-        return Ok(HttpResponse::Unauthorized().json(serde_json::json!({
-            "message": "Unauthorized"
-        })));
+    if let Some(kind) = &body.account_type
+        && kind != "public"
+        && kind != "private"
+    {
+        return Err(ApiError::BadRequest("accountType must be 'public' or 'private'".to_string()));
     }
-      };
 
-    let uid = user.id;
+    let new_handle = body.handle.as_deref().map(handles::normalize).transpose()?;
 
-    let user_id = user.id;
-    let body = body.into_inner();
+    let changes = UserUpdate {
+        name: body.username,
+        email: body.email,
+        address: body.address,
+        account_type: body.account_type,
+        phoneno: body.phone_no,
+        handle: new_handle,
+    };
 
-    if let Some(logged_in_id) = body.loggedInUserId {
-        if logged_in_id != user_id {
-            return Ok(HttpResponse::Unauthorized().json(serde_json::json!({
-                "message": "Unauthorized to update this profile"
-            })));
-        }
-    } else {
-        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-            "message": "Missing loggedInUserId"
-        })));
+    if changes.name.is_none()
+        && changes.email.is_none()
+        && changes.address.is_none()
+        && changes.account_type.is_none()
+        && changes.phoneno.is_none()
+        && changes.handle.is_none()
+    {
+        return Err(ApiError::BadRequest("Nothing to update".to_string()));
     }
 
-    let mut conn = pool.get().unwrap();
+    let mut conn = pool.get()?;
 
-    let result = diesel::update(users.filter(id.eq(user_id)))
-        .set((
-            id.eq(body.loggedInUserId.unwrap_or_default()),
-            name.eq(body.username.unwrap_or_default()),
-            email.eq(body.email.unwrap_or_default()),
-            account_type.eq(body.accountType.unwrap_or_else(|| "public".to_string())),
-            phoneno.eq(body.phoneNo.unwrap_or_default()),
-            address.eq(body.address.unwrap_or_default()),
-        ))
-        .get_result::<User>(&mut conn);
+    let updated_user = diesel::update(users.filter(id.eq(auth.id)))
+        .set(&changes)
+        .get_result::<User>(&mut conn)
+        .map_err(|e| match &e {
+            DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, info) => {
+                let what = if info.constraint_name() == Some("users_handle_key") { "Handle" } else { "Email" };
+                ApiError::Conflict(format!("{} already in use", what))
+            }
+            _ => e.into(),
+        })?;
 
     // ✅ 4. Return response
-    match result {
-        Ok(updated_user) => Ok(HttpResponse::Ok().json(serde_json::json!({
-            "id": updated_user.id,
-            "username": updated_user.name,
-            "email": updated_user.email,
-            "accountType": updated_user.account_type,
-            "phoneNo": updated_user.phoneno,
-            "address": updated_user.address,
-        }))),
-        Err(e) => {
-            println!("❌ Diesel update error: {:?}", e);
-            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
-                "message": "Failed to update profile"
-            })))
-        }
-    }
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "id": updated_user.id,
+        "username": updated_user.name,
+        "handle": updated_user.handle,
+        "email": updated_user.email,
+        "accountType": updated_user.account_type,
+        "phoneNo": updated_user.phoneno,
+        "address": updated_user.address,
+    })))
 }
 
 #[utoipa::path(
     get,
     path = "/api/user/auth/followers/{user_id}",
     params(
-        PaginationParams, 
+        ("user_id" = Uuid, Path, description = "Whose list to fetch"),
+        PageParams,
+        AvatarParams
     ),
     responses(
-        (status = 200, description = "List of followers", body = serde_json::Value),
-        (status = 401, description = "Unauthorized", body = serde_json::Value),
-        (status = 500, description = "Internal server error", body = serde_json::Value)
+        (status = 200, description = "Users following this user, most recent first", body = FollowerPage),
+        (status = 400, description = "Invalid cursor or avatar_size", body = ErrorBody),
+        (status = 401, description = "Unauthorized", body = ErrorBody),
+        (status = 403, description = "Private account the caller doesn't follow", body = ErrorBody),
+        (status = 404, description = "User not found", body = ErrorBody),
+        (status = 500, description = "Internal server error", body = ErrorBody)
     ),
     tag = "User",
     security(
@@ -852,141 +877,123 @@ pub async fn profile_update(pool: web::Data<DbPool>,req: HttpRequest,body: web::
     )
 )]
 
-pub async fn followers_list(pool: web::Data<DbPool>,req: HttpRequest,
-    query: web::Query<PaginationParams>,) -> Result<HttpResponse, Error> {
-    use crate::schema::follows::dsl::{follows, user_id as f_user_id, target_id, status};
+pub async fn followers_list(pool: web::Data<DbPool>, auth: AuthUser, path: web::Path<Uuid>,
+    query: web::Query<PageParams>, avatar: web::Query<AvatarParams>,) -> Result<HttpResponse, ApiError> {
+    use crate::schema::follows::dsl::{follows, id as f_id, user_id as f_user_id, target_id, status, created_at as f_created_at};
     use crate::schema::users::dsl::{users, id as u_id, name as username, profile_pic};
 
- let extensions = req.extensions();
-    let user = match extensions.get::<User>() {
-    Some(u) => u,
-    None => {
-        return Ok(HttpResponse::Unauthorized().json(serde_json::json!({
-            "message": "Unauthorized"
-        })));
-    }
-      };
-
-    let uid = user.id;
+    let target_id_val = path.into_inner();
+    let viewer = auth.id;
 
-
-    let target_id_val = user.id;
-    let page = query.page.unwrap_or(1);
-    let limit_val = query.limit.unwrap_or(3);
-    let offset_val = (page - 1) * limit_val;
+    let limit = query.limit();
+    let after = query.cursor()?;
+    let avatar_size = avatar.size()?;
 
     let pool = pool.clone();
 
-    let results = web::block(move || {
-        let mut conn = pool.get().expect("Couldn't get DB connection");
+    let rows = web::block(move || {
+        let mut conn = pool.get()?;
+        policy::require_full_access(&mut conn, Some(viewer), target_id_val)?;
 
-        users
+        let mut q = users
             .inner_join(follows.on(f_user_id.eq(u_id)))
             .filter(target_id.eq(target_id_val))
             .filter(status.eq("accepted"))
-            .select((u_id, username, profile_pic))
-            .order(u_id.asc())
-            .limit(limit_val)
-            .offset(offset_val)
-            .load::<FollowerInfo>(&mut conn)
+            .select(((u_id, username, profile_pic), f_created_at, f_id))
+            .order((f_created_at.desc(), f_id.desc()))
+            .limit(limit + 1)
+            .into_boxed();
+
+        if let Some(c) = after {
+            q = q.filter(pagination::keyset_before(f_created_at, f_id, &c));
+        }
+
+        q.load::<(FollowerInfo, NaiveDateTime, Uuid)>(&mut conn)
+            .map_err(ApiError::from)
     })
-    .await
-    .map_err(|e| {
-        eprintln!("Blocking error: {:?}", e);
-        actix_web::error::ErrorInternalServerError("Blocking thread error")
-    })?
-    .map_err(|e| {
-        eprintln!("Diesel query error: {:?}", e);
-        actix_web::error::ErrorInternalServerError("Database query error")
-    })?;
+    .await??;
 
-    Ok(HttpResponse::Ok().json(serde_json::json!({
-        "page": page,
-        "limit": limit_val,
-        "followers": results
-    })))
+    let page = Page::from_rows(rows, limit, |(_, ts, fid)| Cursor::new(*ts, *fid));
+
+    Ok(HttpResponse::Ok().json(page.map(|(info, _, _)| info.with_avatar_size(avatar_size))))
 }
 
 #[utoipa::path(
     get,
     path = "/api/user/auth/followings/{user_id}",
     params(
-        PaginationParams
+        ("user_id" = Uuid, Path, description = "Whose list to fetch"),
+        PageParams,
+        AvatarParams
     ),
     responses(
-        (status = 200, description = "List of users the current user is following", body = serde_json::Value),
-        (status = 401, description = "Unauthorized", body = serde_json::Value),
-        (status = 500, description = "Internal server error", body = serde_json::Value)
+        (status = 200, description = "Users this user follows, most recent first", body = FollowerPage),
+        (status = 400, description = "Invalid cursor or avatar_size", body = ErrorBody),
+        (status = 401, description = "Unauthorized", body = ErrorBody),
+        (status = 403, description = "Private account the caller doesn't follow", body = ErrorBody),
+        (status = 404, description = "User not found", body = ErrorBody),
+        (status = 500, description = "Internal server error", body = ErrorBody)
     ),
     tag = "User",
     security(
         ("bearerAuth" = [])
     )
 )]
-        
-pub async fn following_list(pool: web::Data<DbPool>,req: HttpRequest,
-    query: web::Query<PaginationParams>,) -> Result<HttpResponse, Error> {
-    use crate::schema::follows::dsl::{follows, user_id as f_user_id, target_id, status};
-    use crate::schema::users::dsl::{users, id as u_id, name as username, profile_pic};
-
 
- let extensions = req.extensions();
-    let user = match extensions.get::<User>() {
-    Some(u) => u,
-    None => {
-        return Ok(HttpResponse::Unauthorized().json(serde_json::json!({
-            "message": "Unauthorized"
-        })));
-    }
-      };
-
-    let uid = user.id;
+pub async fn following_list(pool: web::Data<DbPool>, auth: AuthUser, path: web::Path<Uuid>,
+    query: web::Query<PageParams>, avatar: web::Query<AvatarParams>,) -> Result<HttpResponse, ApiError> {
+    use crate::schema::follows::dsl::{follows, id as f_id, user_id as f_user_id, target_id, status, created_at as f_created_at};
+    use crate::schema::users::dsl::{users, id as u_id, name as username, profile_pic};
 
+    let user_id_val = path.into_inner();
+    let viewer = auth.id;
 
-    let user_id_val = user.id;
-    let page = query.page.unwrap_or(1);
-    let limit_val = query.limit.unwrap_or(3);
-    let offset_val = (page - 1) * limit_val;
+    let limit = query.limit();
+    let after = query.cursor()?;
+    let avatar_size = avatar.size()?;
 
     let pool = pool.clone();
 
-    let results = web::block(move || {
-        let mut conn = pool.get().expect("Couldn't get DB connection");
+    let rows = web::block(move || {
+        let mut conn = pool.get()?;
+        policy::require_full_access(&mut conn, Some(viewer), user_id_val)?;
 
-        users
+        let mut q = users
             .inner_join(follows.on(target_id.eq(u_id)))
             .filter(f_user_id.eq(user_id_val))
             .filter(status.eq("accepted"))
-            .select((u_id, username, profile_pic))
-            .order(u_id.asc())
-            .limit(limit_val)
-            .offset(offset_val)
-            .load::<FollowerInfo>(&mut conn)
+            .select(((u_id, username, profile_pic), f_created_at, f_id))
+            .order((f_created_at.desc(), f_id.desc()))
+            .limit(limit + 1)
+            .into_boxed();
+
+        if let Some(c) = after {
+            q = q.filter(pagination::keyset_before(f_created_at, f_id, &c));
+        }
+
+        q.load::<(FollowerInfo, NaiveDateTime, Uuid)>(&mut conn)
+            .map_err(ApiError::from)
     })
-    .await
-    .map_err(|e| {
-        eprintln!("Blocking error: {:?}", e);
-        actix_web::error::ErrorInternalServerError("Blocking thread error")
-    })?
-    .map_err(|e| {
-        eprintln!("Diesel query error: {:?}", e);
-        actix_web::error::ErrorInternalServerError("Database query error")
-    })?;
+    .await??;
 
-    Ok(HttpResponse::Ok().json(serde_json::json!({
-        "page": page,
-        "limit": limit_val,
-        "following": results
-    })))
+    let page = Page::from_rows(rows, limit, |(_, ts, fid)| Cursor::new(*ts, *fid));
+
+    Ok(HttpResponse::Ok().json(page.map(|(info, _, _)| info.with_avatar_size(avatar_size))))
 }
 
 #[utoipa::path(
     get,
     path = "/api/user/auth/follow-req/{user_id}",
+    params(
+        ("user_id" = Uuid, Path, description = "The caller's own id"),
+        PageParams
+    ),
     responses(
-        (status = 200, description = "List of pending follow requests", body = serde_json::Value),
-        (status = 401, description = "Unauthorized", body = serde_json::Value),
-        (status = 500, description = "Internal server error", body = serde_json::Value)
+        (status = 200, description = "Pending follow requests to the current user, most recent first", body = PendingRequestPage),
+        (status = 400, description = "Invalid cursor", body = ErrorBody),
+        (status = 401, description = "Unauthorized", body = ErrorBody),
+        (status = 403, description = "Path id is not the caller's own", body = ErrorBody),
+        (status = 500, description = "Internal server error", body = ErrorBody)
     ),
     tag = "User",
     security(
@@ -994,77 +1001,66 @@ pub async fn following_list(pool: web::Data<DbPool>,req: HttpRequest,
     )
 )]
 
-pub async fn follow_requests(pool: web::Data<DbPool>,req: HttpRequest,) -> Result<HttpResponse, Error> {
- let extensions = req.extensions();
-    let user = match extensions.get::<User>() {
-    Some(u) => u,
-    None => {
-        return Ok(HttpResponse::Unauthorized().json(serde_json::json!({
-            "message": "Unauthorized"
-        })));
+pub async fn follow_requests(pool: web::Data<DbPool>, auth: AuthUser, path: web::Path<Uuid>,
+    query: web::Query<PageParams>,) -> Result<HttpResponse, ApiError> {
+    let target_id_val = auth.id;
+    if path.into_inner() != target_id_val {
+        return Err(ApiError::Forbidden("You can only list follow requests to yourself".to_string()));
     }
-      };
-
-    let uid = user.id;
 
+    let limit = query.limit();
+    let after = query.cursor()?;
 
-    let target_id_val = user.id;
     let pool = pool.clone();
 
-    let result: Vec<PendingRequest> = web::block(move || {
+    let rows = web::block(move || {
         use crate::schema::follows::dsl::*;
         use crate::schema::users::dsl::{users as u_table, id as u_id, name as u_name, profile_pic as u_pic};
 
-        let mut conn = pool.get().expect("DB connection failed");
+        let mut conn = pool.get()?;
 
-        let rows = follows
+        let mut q = follows
             .inner_join(u_table.on(user_id.eq(u_id)))
             .filter(target_id.eq(target_id_val))
             .filter(status.eq("pending"))
-            .order(created_at.asc())
-            .select((id, user_id, u_name, u_pic))
-            .load::<(Uuid, Uuid, String, Option<String>)>(&mut conn)?;
-
-        let mapped: Vec<PendingRequest> = rows
-            .into_iter()
-            .map(|(fid, requester_id, username, prof_pic)| PendingRequest {
-                id: fid,
-                requester_id,
-                username,
-                profile_pic: prof_pic,
-            })
-            .collect();
-
-        Ok::<Vec<PendingRequest>, diesel::result::Error>(mapped)
+            .order((created_at.desc(), id.desc()))
+            .select((id, user_id, u_name, u_pic, created_at))
+            .limit(limit + 1)
+            .into_boxed();
+
+        if let Some(c) = after {
+            q = q.filter(pagination::keyset_before(created_at, id, &c));
+        }
+
+        q.load::<(Uuid, Uuid, String, Option<String>, NaiveDateTime)>(&mut conn)
+            .map_err(ApiError::from)
     })
-    .await
-    .map_err(|e| {
-        log::error!("Pending requests error: {:?}", e);
-        actix_web::error::ErrorInternalServerError("Database error")
-    })?
-    .map_err(|e| {
-        log::error!("Database query error: {:?}", e);
-        actix_web::error::ErrorInternalServerError("Database query failed")
-    })?;
+    .await??;
 
-    Ok(HttpResponse::Ok().json(serde_json::json!({
-        "pendingRequests": result
+    let page = Page::from_rows(rows, limit, |(fid, _, _, _, ts)| Cursor::new(*ts, *fid));
+
+    Ok(HttpResponse::Ok().json(page.map(|(fid, requester_id, username, prof_pic, _)| PendingRequest {
+        id: fid,
+        requester_id,
+        username,
+        profile_pic: prof_pic,
     })))
 }
 
 #[utoipa::path(
-    put,
+    post,
     path = "/api/user/auth/handle-follow-req/{request_id}",
     request_body(content = HandleFollowRequest, description = "Approve or reject a follow request"),
     responses(
         (status = 200, description = "Follow request handled successfully", body = serde_json::Value),
-        (status = 400, description = "Invalid input or action", body = serde_json::Value),
-        (status = 401, description = "Unauthorized", body = serde_json::Value),
-        (status = 404, description = "Follow request not found or already processed", body = serde_json::Value),
-        (status = 500, description = "Internal server error", body = serde_json::Value)
+        (status = 400, description = "Invalid action", body = ErrorBody),
+        (status = 401, description = "Unauthorized", body = ErrorBody),
+        (status = 403, description = "The request is addressed to someone else", body = ErrorBody),
+        (status = 404, description = "No pending request with this id", body = ErrorBody),
+        (status = 500, description = "Internal server error", body = ErrorBody)
     ),
     params(
-        ("id" = Uuid, Path, description = "UUID of the follow request to handle")
+        ("request_id" = Uuid, Path, description = "UUID of the follow request to handle")
     ),
     tag = "User",
     security(
@@ -1072,95 +1068,56 @@ pub async fn follow_requests(pool: web::Data<DbPool>,req: HttpRequest,) -> Resul
     )
 )]
 
-pub async fn handle_follow_request(pool: web::Data<DbPool>,req: HttpRequest,
-    path: web::Path<Uuid>, 
-    body: web::Json<HandleFollowRequest>,) -> Result<HttpResponse, Error> {
+pub async fn handle_follow_request(pool: web::Data<DbPool>, auth: AuthUser,
+    path: web::Path<Uuid>,
+    body: web::Json<HandleFollowRequest>,) -> Result<HttpResponse, ApiError> {
     use crate::schema::follows::dsl::*;
-    
+
     let request_id = path.into_inner();
     let action = body.action.to_lowercase();
 
     // Validate action
     if !["approve", "reject"].contains(&action.as_str()) {
-        return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-            "success": false,
-            "message": "Invalid action. Use 'approve' or 'reject'"
-        })));
+        return Err(ApiError::BadRequest("Invalid action. Use 'approve' or 'reject'".to_string()));
     }
 
-    // Get owner_id from request body (the user who owns the account)
-    let owner_id = match &body.ownerId {
-        Some(id_str) => {
-            match Uuid::parse_str(id_str) {
-                Ok(uuid) => uuid,
-                Err(_) => {
-                    return Ok(HttpResponse::BadRequest().json(serde_json::json!({
-                        "success": false,
-                        "message": "Invalid owner ID format"
-                    })));
-                }
-            }
-        },
-        None => {
-            // Try to get from extensions as fallback
-            let extensions = req.extensions();
-            match extensions.get::<FollowerInfo>() {
-                Some(u) => u.user_id,
-                None => {
-                    return Ok(HttpResponse::Unauthorized().json(serde_json::json!({
-                        "success": false,
-                        "message": "Unauthorized - no owner ID provided"
-                    })));
-                }
-            }
-        }
-    };
+    // Only requests addressed to the caller can be handled.
+    let owner_id = auth.id;
 
     println!("   Owner ID (target of request): {}", owner_id);
 
     let new_status = if action == "approve" { "accepted" } else { "rejected" };
-    
+
     let pool_clone = pool.clone();
-    let request_id_clone = request_id.clone();
 
     // First, fetch the follow request to see who sent it
     let follow_request = web::block(move || {
-        let mut conn = pool_clone.get().expect("DB connection failed");
-        
+        let mut conn = pool_clone.get()?;
+
         follows
-            .filter(id.eq(request_id_clone))
-            .filter(target_id.eq(owner_id))
+            .filter(id.eq(request_id))
             .filter(status.eq("pending"))
             .first::<Follow>(&mut conn)
             .optional()
+            .map_err(ApiError::from)
     })
-    .await
-    .map_err(|e| {
-        log::error!("Failed to fetch follow request: {:?}", e);
-        actix_web::error::ErrorInternalServerError("Database error")
-    })?
-    .map_err(|e| {
-        log::error!("Database query error: {:?}", e);
-        actix_web::error::ErrorInternalServerError("Database query failed")
+    .await??;
+
+    let follow_req = follow_request.ok_or_else(|| {
+        println!("❌ Follow request not found or not pending");
+        ApiError::NotFound("Follow request not found or already processed".to_string())
     })?;
 
-    let follow_req = match follow_request {
-        Some(req) => req,
-        None => {
-            println!("❌ Follow request not found or not pending");
-            return Ok(HttpResponse::NotFound().json(serde_json::json!({
-                "success": false,
-                "message": "Follow request not found or already processed"
-            })));
-        }
-    };
+    if follow_req.target_id != owner_id {
+        return Err(ApiError::Forbidden("This follow request is not addressed to you".to_string()));
+    }
 
     println!("   Requester ID (who sent request): {}", follow_req.user_id);
 
     // Now update the status
     let pool_clone = pool.clone();
     let update_result: usize = web::block(move || {
-        let mut conn = pool_clone.get().expect("DB connection failed");
+        let mut conn = pool_clone.get()?;
 
         diesel::update(
             follows
@@ -1170,37 +1127,77 @@ pub async fn handle_follow_request(pool: web::Data<DbPool>,req: HttpRequest,
         )
         .set(status.eq(new_status))
         .execute(&mut conn)
+        .map_err(ApiError::from)
     })
-    .await
-    .map_err(|e| {
-        log::error!("Handle follow request error: {:?}", e);
-        actix_web::error::ErrorInternalServerError("Database error")
-    })?
-    .map_err(|e| {
-        log::error!("Database update error: {:?}", e);
-        actix_web::error::ErrorInternalServerError("Database update failed")
-    })?;
+    .await??;
 
     println!("   Update result: {} rows affected", update_result);
 
-    if update_result > 0 {
-        let msg = if action == "approve" { 
-            "Follow request approved successfully" 
-        } else { 
-            "Follow request rejected" 
-        };
-        
-        println!("✅ Request {} successfully", action);
-        
-        Ok(HttpResponse::Ok().json(serde_json::json!({ 
-            "success": true,
-            "message": msg 
-        })))
-    } else {
+    if update_result == 0 {
         println!("❌ No rows updated");
-        Ok(HttpResponse::NotFound().json(serde_json::json!({
-            "success": false,
-            "message": "Follow request not found or already processed"
-        })))
+        return Err(ApiError::NotFound("Follow request not found or already processed".to_string()));
     }
-}
\ No newline at end of file
+
+    let msg = if action == "approve" {
+        "Follow request approved successfully"
+    } else {
+        "Follow request rejected"
+    };
+
+    println!("✅ Request {} successfully", action);
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": msg
+    })))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/api/user/auth/account",
+    responses(
+        (status = 200, description = "Account deleted; files are removed in the background", body = serde_json::Value),
+        (status = 401, description = "Unauthorized", body = ErrorBody),
+        (status = 500, description = "Internal server error", body = ErrorBody)
+    ),
+    tag = "User",
+    security(
+        ("bearerAuth" = [])
+    )
+)]
+
+pub async fn delete_account(pool: web::Data<DbPool>, auth: AuthUser) -> Result<HttpResponse, ApiError> {
+    use crate::schema::post_media::dsl as m;
+    use crate::schema::user_posts::dsl as p;
+
+    let user = auth.user;
+    let pool = pool.clone();
+    web::block(move || {
+        let mut conn = pool.get()?;
+
+        conn.transaction::<_, diesel::result::Error, _>(|conn| {
+            let media: Vec<String> = m::post_media
+                .inner_join(p::user_posts)
+                .filter(p::user_id.eq(user.id))
+                .select(m::storage_key)
+                .load(conn)?;
+
+            // Posts, follows and reset tokens go with the user via ON DELETE CASCADE.
+            diesel::delete(users.filter(id.eq(user.id))).execute(conn)?;
+
+            jobs::enqueue(conn, &Job::AccountCleanup {
+                user_id: user.id,
+                profile_pic: user.profile_pic.clone(),
+                media,
+            })?;
+            Ok(())
+        })
+        .map_err(ApiError::from)
+    })
+    .await??;
+
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true,
+        "message": "Account deleted"
+    })))
+}
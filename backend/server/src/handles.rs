@@ -0,0 +1,86 @@
+//! `@handles`, the names mentions link to.
+//!
+//! Display names may contain spaces and needn't be unique, so `@` can't
+//! point at them. Every user also has a handle: up to [`MAX_HANDLE_LEN`] of
+//! `a-z`, `0-9`, `_` and `.`, not starting or ending with `.`. Handles are
+//! unique and stored lowercased, so `@Ann` and `@ann` are the same mention.
+
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use crate::errors::ApiError;
+use crate::schema::users;
+
+pub const MAX_HANDLE_LEN: usize = 30;
+
+/// Derived handles are cut to this length, leaving room for a suffix when
+/// the stem is taken.
+const STEM_LEN: usize = 24;
+
+pub fn is_valid(handle: &str) -> bool {
+    (1..=MAX_HANDLE_LEN).contains(&handle.len())
+        && handle.bytes().all(|b| b.is_ascii_lowercase() || b.is_ascii_digit() || b == b'_' || b == b'.')
+        && !handle.starts_with('.')
+        && !handle.ends_with('.')
+}
+
+/// `handle` as stored: trimmed, without a leading `@`, lowercased.
+pub fn normalize(handle: &str) -> Result<String, ApiError> {
+    let handle = handle.trim().trim_start_matches('@').to_lowercase();
+    if !is_valid(&handle) {
+        return Err(ApiError::BadRequest(format!(
+            "handle must be 1-{} characters of a-z, 0-9, '_' and '.', not starting or ending with '.'",
+            MAX_HANDLE_LEN
+        )));
+    }
+    Ok(handle)
+}
+
+pub fn is_taken(conn: &mut PgConnection, handle: &str) -> QueryResult<bool> {
+    diesel::select(diesel::dsl::exists(users::table.filter(users::handle.eq(handle)))).get_result(conn)
+}
+
+/// A free handle made from `name`, for users who didn't pick one. Matches
+/// the backfill in the `add_user_handles` migration.
+pub fn derive(conn: &mut PgConnection, name: &str) -> QueryResult<String> {
+    let stem: String = name
+        .to_lowercase()
+        .chars()
+        .filter(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || *c == '_' || *c == '.')
+        .take(STEM_LEN)
+        .collect();
+    let stem = match stem.trim_matches('.') {
+        "" => "user",
+        stem => stem,
+    };
+
+    let mut candidate = stem.to_string();
+    for _ in 0..5 {
+        if !is_taken(conn, &candidate)? {
+            break;
+        }
+        candidate = format!("{}_{}", stem, &Uuid::new_v4().simple().to_string()[..5]);
+    }
+    Ok(candidate)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn validates_handles() {
+        for handle in ["ann", "ann.lee", "a_b", "_x_", "a..b", "2024", &"a".repeat(MAX_HANDLE_LEN)] {
+            assert!(is_valid(handle), "{:?} should be valid", handle);
+        }
+        for handle in ["", ".ann", "ann.", "Ann", "ann lee", "ann-lee", "zoë", "@ann", &"a".repeat(MAX_HANDLE_LEN + 1)] {
+            assert!(!is_valid(handle), "{:?} should be invalid", handle);
+        }
+    }
+
+    #[test]
+    fn normalizes_before_validating() {
+        assert_eq!(normalize(" @Ann.Lee ").unwrap(), "ann.lee");
+        assert!(matches!(normalize("ann lee"), Err(ApiError::BadRequest(_))));
+    }
+}
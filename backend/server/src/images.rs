@@ -0,0 +1,182 @@
+//! Decoding and re-encoding uploaded pictures.
+//!
+//! Pictures are never stored as sent: the EXIF block of the original, with
+//! camera details and GPS coordinates, would reach every viewer. They are
+//! decoded, turned upright and encoded again, which leaves only the pixels.
+//! Animated PNG and WebP keep their first frame.
+
+use std::fs::File;
+use std::io::{BufReader, Cursor};
+use std::path::Path;
+
+use image::codecs::jpeg::JpegEncoder;
+use image::metadata::Orientation;
+use image::{DynamicImage, ImageDecoder, ImageError, ImageFormat, ImageReader, Limits, Rgb, RgbImage};
+
+use crate::probe::{self, invalid, ProbeError};
+use crate::upload::Format;
+
+/// Larger pictures are refused rather than decoded. 8192² RGBA is 256 MiB.
+pub const MAX_DIMENSION: u32 = 8192;
+const MAX_DECODE_BYTES: u64 = 300 * 1024 * 1024;
+
+/// Quality of re-encoded post pictures; high enough that a second lossy
+/// pass isn't visible.
+const JPEG_QUALITY: u8 = 90;
+
+fn decode_error(e: ImageError) -> ProbeError {
+    match e {
+        ImageError::IoError(e) => e.into(),
+        ImageError::Limits(_) => invalid(format!("larger than {}x{} pixels", MAX_DIMENSION, MAX_DIMENSION)),
+        e => invalid(e.to_string()),
+    }
+}
+
+fn encode_error(e: ImageError) -> ProbeError {
+    ProbeError::Io(std::io::Error::other(e))
+}
+
+/// Decodes the picture at `path`, already sniffed as `format`, and applies
+/// its EXIF orientation. Blocking; call from `web::block` or a worker thread.
+pub fn decode(path: &Path, format: Format) -> Result<DynamicImage, ProbeError> {
+    let mut reader = ImageReader::with_format(BufReader::new(File::open(path)?), probe::image_format(format)?);
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODE_BYTES);
+    reader.limits(limits);
+
+    let mut decoder = reader.into_decoder().map_err(decode_error)?;
+    // An unreadable EXIF block is ignored; the pixels are still fine.
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    let mut image = DynamicImage::from_decoder(decoder).map_err(decode_error)?;
+    image.apply_orientation(orientation);
+
+    if image.width() == 0 || image.height() == 0 {
+        return Err(invalid("image has no dimensions"));
+    }
+    Ok(image)
+}
+
+/// Flattens transparency onto white; JPEG has no alpha channel.
+pub fn flatten(image: DynamicImage) -> RgbImage {
+    if !image.color().has_alpha() {
+        return image.into_rgb8();
+    }
+    let rgba = image.into_rgba8();
+    RgbImage::from_fn(rgba.width(), rgba.height(), |x, y| {
+        let [r, g, b, a] = rgba.get_pixel(x, y).0;
+        let over_white = |c: u8| ((c as u32 * a as u32 + 255 * (255 - a as u32)) / 255) as u8;
+        Rgb([over_white(r), over_white(g), over_white(b)])
+    })
+}
+
+pub fn encode_jpeg(image: &RgbImage, quality: u8) -> Result<Vec<u8>, ProbeError> {
+    let mut bytes = Cursor::new(Vec::new());
+    JpegEncoder::new_with_quality(&mut bytes, quality).encode_image(image).map_err(encode_error)?;
+    Ok(bytes.into_inner())
+}
+
+/// Re-encodes the picture at `path` without its metadata. JPEG stays JPEG
+/// and PNG stays PNG. WebP becomes JPEG, or PNG when it has transparency:
+/// the `image` crate only writes lossless WebP, which comes out several
+/// times larger for photos. Returns the new format and content.
+pub fn strip(path: &Path, format: Format) -> Result<(Format, Vec<u8>), ProbeError> {
+    let image = decode(path, format)?;
+    let lossless = format == Format::Png || (format == Format::Webp && image.color().has_alpha());
+
+    if lossless {
+        let mut bytes = Cursor::new(Vec::new());
+        image.write_to(&mut bytes, ImageFormat::Png).map_err(encode_error)?;
+        Ok((Format::Png, bytes.into_inner()))
+    } else {
+        Ok((Format::Jpeg, encode_jpeg(&flatten(image), JPEG_QUALITY)?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use image::{Rgba, RgbaImage};
+
+    use super::*;
+
+    /// APP1 segment holding an EXIF block with orientation 6 (rotate 90°)
+    /// and a camera make.
+    fn exif_segment() -> Vec<u8> {
+        let mut tiff = b"II*\0\x08\0\0\0".to_vec();
+        tiff.extend_from_slice(&2u16.to_le_bytes());
+        // Orientation, SHORT, 1 value: 6.
+        tiff.extend_from_slice(&[0x12, 0x01, 3, 0, 1, 0, 0, 0, 6, 0, 0, 0]);
+        // Make, ASCII, 4 values inline: "Cam\0".
+        tiff.extend_from_slice(&[0x0F, 0x01, 2, 0, 4, 0, 0, 0]);
+        tiff.extend_from_slice(b"Cam\0");
+        tiff.extend_from_slice(&0u32.to_le_bytes());
+
+        let body = [b"Exif\0\0".as_slice(), &tiff].concat();
+        [[0xFF, 0xE1].as_slice(), &((body.len() + 2) as u16).to_be_bytes(), &body].concat()
+    }
+
+    /// Removed when dropped.
+    struct TempFile(std::path::PathBuf);
+
+    impl Drop for TempFile {
+        fn drop(&mut self) {
+            let _ = std::fs::remove_file(&self.0);
+        }
+    }
+
+    fn write(bytes: &[u8]) -> TempFile {
+        let path = std::env::temp_dir().join(format!("images-test-{}", uuid::Uuid::new_v4()));
+        std::fs::write(&path, bytes).unwrap();
+        TempFile(path)
+    }
+
+    fn encoded(image: DynamicImage, format: ImageFormat) -> Vec<u8> {
+        let mut bytes = Cursor::new(Vec::new());
+        image.write_to(&mut bytes, format).unwrap();
+        bytes.into_inner()
+    }
+
+    #[test]
+    fn jpeg_loses_exif_and_is_turned_upright() {
+        let jpeg = encode_jpeg(&RgbImage::from_pixel(40, 20, Rgb([200, 10, 10])), 90).unwrap();
+        let with_exif = [&jpeg[..2], &exif_segment(), &jpeg[2..]].concat();
+        let input = write(&with_exif);
+        assert_eq!(decode(&input.0, Format::Jpeg).unwrap().width(), 20);
+
+        let (format, bytes) = strip(&input.0, Format::Jpeg).unwrap();
+        assert_eq!(format, Format::Jpeg);
+        assert!(!bytes.windows(4).any(|w| w == b"Exif"));
+        assert!(!bytes.windows(3).any(|w| w == b"Cam"));
+
+        let output = write(&bytes);
+        let image = decode(&output.0, Format::Jpeg).unwrap();
+        assert_eq!((image.width(), image.height()), (20, 40));
+    }
+
+    #[test]
+    fn png_stays_png_with_its_transparency() {
+        let image = DynamicImage::ImageRgba8(RgbaImage::from_pixel(8, 6, Rgba([0, 0, 255, 128])));
+        let input = write(&encoded(image, ImageFormat::Png));
+
+        let (format, bytes) = strip(&input.0, Format::Png).unwrap();
+        assert_eq!(format, Format::Png);
+        let output = write(&bytes);
+        assert_eq!(decode(&output.0, Format::Png).unwrap().to_rgba8().get_pixel(0, 0), &Rgba([0, 0, 255, 128]));
+    }
+
+    #[test]
+    fn webp_becomes_jpeg_or_png() {
+        let opaque = write(&encoded(DynamicImage::ImageRgb8(RgbImage::new(8, 8)), ImageFormat::WebP));
+        assert_eq!(strip(&opaque.0, Format::Webp).unwrap().0, Format::Jpeg);
+
+        let transparent = write(&encoded(DynamicImage::ImageRgba8(RgbaImage::new(8, 8)), ImageFormat::WebP));
+        assert_eq!(strip(&transparent.0, Format::Webp).unwrap().0, Format::Png);
+    }
+
+    #[test]
+    fn undecodable_pictures_are_invalid() {
+        let input = write(b"\x89PNG\r\n\x1a\nnot really");
+        assert!(matches!(strip(&input.0, Format::Png), Err(ProbeError::Invalid(_))));
+    }
+}
@@ -0,0 +1,296 @@
+use std::io::Read as _;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::time::Duration;
+
+use diesel::prelude::*;
+use futures_util::TryStreamExt as _;
+use sha2::{Digest, Sha256};
+use tokio::io::AsyncWriteExt;
+use tokio::process::Command;
+use uuid::Uuid;
+
+use super::{Job, JobContext};
+use crate::avatar;
+use crate::mailer::Email;
+use crate::models::media::ProcessedMedia;
+use crate::probe::{self, ProbeError};
+use crate::schema::{post_media, users};
+use crate::storage::{self, StoreError};
+use crate::upload;
+
+pub async fn run(ctx: &JobContext, job: &Job) -> Result<(), String> {
+    match job {
+        Job::SendEmail { to, template } => {
+            let email = Email::from_template(to.clone(), template);
+            ctx.mailer.send(&email).map_err(|e| e.to_string())
+        }
+        Job::GenerateThumbnail { video, .. } => generate_thumbnail(ctx, video).await,
+        Job::AccountCleanup { profile_pic, media, .. } => {
+            if let Some(pic) = profile_pic {
+                for key in storage::avatar_keys(pic) {
+                    remove_object(ctx, &key).await?;
+                }
+            }
+            remove_media(ctx, media).await
+        }
+        Job::RemovePostFiles { media, .. } => remove_media(ctx, media).await,
+        Job::ProbeVideo { video, .. } => probe_video(ctx, video).await,
+        Job::ProcessAvatar { user_id, profile_pic } => process_avatar(ctx, *user_id, profile_pic).await,
+    }
+}
+
+async fn remove_media(ctx: &JobContext, media: &[String]) -> Result<(), String> {
+    for key in media.iter().flat_map(|key| storage::media_keys(key)) {
+        remove_object(ctx, &key).await?;
+    }
+    Ok(())
+}
+
+/// Deleting something that is already gone counts as success so retries stay idempotent.
+async fn remove_object(ctx: &JobContext, key: &str) -> Result<(), String> {
+    ctx.store.delete(key).await.map_err(|e| format!("failed to delete {}: {}", key, e))
+}
+
+/// Temp files of one job run, removed when it ends either way.
+struct Scratch(Vec<PathBuf>);
+
+impl Scratch {
+    fn file(&mut self, extension: &str) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("job-{}.{}", Uuid::new_v4(), extension));
+        self.0.push(path.clone());
+        path
+    }
+}
+
+impl Drop for Scratch {
+    fn drop(&mut self) {
+        for path in &self.0 {
+            let _ = std::fs::remove_file(path);
+        }
+    }
+}
+
+/// Grabs a frame one second in with ffmpeg and scales it to 480px wide.
+async fn generate_thumbnail(ctx: &JobContext, video: &str) -> Result<(), String> {
+    let mut scratch = Scratch(Vec::new());
+    let extension = Path::new(video).extension().and_then(|e| e.to_str()).unwrap_or("mp4");
+    let input = scratch.file(extension);
+    let output = scratch.file("jpg");
+
+    download(ctx, video, &input).await.map_err(|e| format!("failed to fetch {}: {}", video, e))?;
+
+    // Dropping the child on timeout kills it, so a stuck ffmpeg doesn't
+    // outlive the job.
+    let ffmpeg = &ctx.config.jobs.ffmpeg_path;
+    let child = Command::new(ffmpeg)
+        .args(["-y", "-loglevel", "error", "-ss", "1", "-i"])
+        .arg(&input)
+        .args(["-frames:v", "1", "-vf", "scale=480:-2"])
+        .arg(&output)
+        .stdin(Stdio::null())
+        .kill_on_drop(true)
+        .output();
+    let limit = Duration::from_secs(ctx.config.jobs.ffmpeg_timeout_secs);
+    let result = tokio::time::timeout(limit, child)
+        .await
+        .map_err(|_| format!("{} timed out after {}s", ffmpeg, limit.as_secs()))?
+        .map_err(|e| format!("failed to run {}: {}", ffmpeg, e))?;
+
+    if !result.status.success() {
+        return Err(format!("ffmpeg exited with {}: {}", result.status, String::from_utf8_lossy(&result.stderr).trim()));
+    }
+
+    ctx.store
+        .put_file(&storage::thumbnail_key(video), &output, "image/jpeg")
+        .await
+        .map_err(|e| format!("failed to store thumbnail of {}: {}", video, e))
+}
+
+/// Reads a stored video whose `post_media` row is still pending and fills
+/// in its metadata. Files that are missing or don't parse are marked failed;
+/// retrying won't fix them.
+async fn probe_video(ctx: &JobContext, video: &str) -> Result<(), String> {
+    let db_error = |e: diesel::result::Error| e.to_string();
+    let record = |processed: ProcessedMedia| -> Result<(), String> {
+        let conn = &mut ctx.pool.get().map_err(|e| e.to_string())?;
+        diesel::update(
+            post_media::table
+                .filter(post_media::storage_key.eq(video))
+                .filter(post_media::status.eq("pending")),
+        )
+        .set(&processed)
+        .execute(conn)
+        .map(|_| ())
+        .map_err(db_error)
+    };
+
+    // The post may have been edited or deleted since the job was queued.
+    let pending: bool = {
+        let conn = &mut ctx.pool.get().map_err(|e| e.to_string())?;
+        diesel::select(diesel::dsl::exists(
+            post_media::table
+                .filter(post_media::storage_key.eq(video))
+                .filter(post_media::status.eq("pending")),
+        ))
+        .get_result(conn)
+        .map_err(db_error)?
+    };
+    if !pending {
+        return Ok(());
+    }
+
+    match ctx.store.stat(video).await {
+        Err(StoreError::NotFound) => {
+            println!("⚠️ Marking {} as failed: file is missing", video);
+            return record(ProcessedMedia::failed(None, None));
+        }
+        Err(e) => return Err(format!("failed to fetch {}: {}", video, e)),
+        Ok(_) => {}
+    }
+
+    let mut scratch = Scratch(Vec::new());
+    let input = scratch.file("video");
+    download(ctx, video, &input).await.map_err(|e| format!("failed to fetch {}: {}", video, e))?;
+
+    let (size, checksum, probed) = blocking(move || {
+        let (format, size, checksum) = inspect(&input)?;
+        let probed = match format {
+            Some(format) => probe::probe_video(&input, format),
+            None => Err(ProbeError::Invalid("not a supported video format".to_string())),
+        };
+        Ok((size, checksum, probed))
+    })
+    .await
+    .map_err(|e| format!("failed to read {}: {}", video, e))?;
+
+    match probed {
+        Ok(info) => record(ProcessedMedia::ready(size, checksum, &info)),
+        Err(ProbeError::Invalid(reason)) => {
+            println!("⚠️ Marking {} as failed: {}", video, reason);
+            record(ProcessedMedia::failed(Some(size), Some(checksum)))
+        }
+        Err(ProbeError::Io(e)) => Err(format!("failed to read {}: {}", video, e)),
+    }
+}
+
+/// Renders the variants of a profile picture stored as uploaded and points
+/// the user at them. Pictures that are missing or don't decode are left as
+/// they are.
+async fn process_avatar(ctx: &JobContext, user_id: Uuid, original: &str) -> Result<(), String> {
+    let db_error = |e: diesel::result::Error| e.to_string();
+    let current = || -> Result<bool, String> {
+        let conn = &mut ctx.pool.get().map_err(|e| e.to_string())?;
+        diesel::select(diesel::dsl::exists(
+            users::table.filter(users::id.eq(user_id)).filter(users::profile_pic.eq(original)),
+        ))
+        .get_result(conn)
+        .map_err(db_error)
+    };
+
+    // The user may have been deleted since the job was queued.
+    if !current()? {
+        return Ok(());
+    }
+
+    let mut scratch = Scratch(Vec::new());
+    let input = scratch.file("image");
+    match ctx.store.stat(original).await {
+        Err(StoreError::NotFound) => {
+            println!("⚠️ Skipping profile picture {}: file is missing", original);
+            return Ok(());
+        }
+        Err(e) => return Err(format!("failed to fetch {}: {}", original, e)),
+        Ok(_) => {}
+    }
+    download(ctx, original, &input).await.map_err(|e| format!("failed to fetch {}: {}", original, e))?;
+
+    let render_input = input.clone();
+    let rendered = blocking(move || {
+        let (format, _, _) = inspect(&render_input)?;
+        Ok(match format.filter(|f| f.kind() == upload::MediaKind::Image) {
+            Some(format) => avatar::render(&render_input, format),
+            None => Err(ProbeError::Invalid("not a supported image format".to_string())),
+        })
+    })
+    .await
+    .map_err(|e| format!("failed to read {}: {}", original, e))?;
+    let variants = match rendered {
+        Ok(variants) => variants,
+        Err(ProbeError::Invalid(reason)) => {
+            println!("⚠️ Skipping profile picture {}: {}", original, reason);
+            return Ok(());
+        }
+        Err(ProbeError::Io(e)) => return Err(format!("failed to read {}: {}", original, e)),
+    };
+
+    let id = Uuid::new_v4().to_string();
+    for (size, bytes) in &variants {
+        let output = scratch.file("jpg");
+        tokio::fs::write(&output, bytes).await.map_err(|e| e.to_string())?;
+        let key = storage::avatar_key(&id, *size);
+        ctx.store
+            .put_file(&key, &output, "image/jpeg")
+            .await
+            .map_err(|e| format!("failed to store {}: {}", key, e))?;
+    }
+
+    // Only swap in the variants if the picture is still the one processed.
+    let processed = storage::avatar_key(&id, avatar::DEFAULT_SIZE);
+    let updated = {
+        let conn = &mut ctx.pool.get().map_err(|e| e.to_string())?;
+        diesel::update(users::table.filter(users::id.eq(user_id)).filter(users::profile_pic.eq(original)))
+            .set(users::profile_pic.eq(&processed))
+            .execute(conn)
+            .map_err(db_error)?
+    };
+
+    let unused = if updated == 0 { storage::avatar_keys(&processed) } else { vec![original.to_string()] };
+    for key in unused {
+        remove_object(ctx, &key).await?;
+    }
+    Ok(())
+}
+
+/// Runs file reads and decoding off the async worker threads.
+async fn blocking<T, F>(f: F) -> std::io::Result<T>
+where
+    F: FnOnce() -> std::io::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(f).await.map_err(std::io::Error::other)?
+}
+
+/// Sniffed format, size and hex SHA-256 of a local file.
+fn inspect(path: &Path) -> std::io::Result<(Option<upload::Format>, u64, String)> {
+    let mut file = std::fs::File::open(path)?;
+    let mut hasher = Sha256::new();
+    let mut head = Vec::with_capacity(upload::SNIFF_LEN);
+    let mut size = 0;
+    let mut buf = vec![0u8; 64 * 1024];
+
+    loop {
+        let n = file.read(&mut buf)?;
+        if n == 0 {
+            break;
+        }
+        if head.len() < upload::SNIFF_LEN {
+            let take = n.min(upload::SNIFF_LEN - head.len());
+            head.extend_from_slice(&buf[..take]);
+        }
+        hasher.update(&buf[..n]);
+        size += n as u64;
+    }
+
+    Ok((upload::sniff(&head), size, hex::encode(hasher.finalize())))
+}
+
+async fn download(ctx: &JobContext, key: &str, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+    let mut object = ctx.store.get(key, None).await?;
+    let mut file = tokio::fs::File::create(path).await?;
+    while let Some(chunk) = object.body.try_next().await? {
+        file.write_all(&chunk).await?;
+    }
+    file.flush().await?;
+    Ok(())
+}
@@ -0,0 +1,115 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::mailer::EmailTemplate;
+use crate::models::job::NewJob;
+use crate::schema::jobs;
+
+pub mod handlers;
+pub mod worker;
+
+pub use worker::JobContext;
+
+pub const DEFAULT_MAX_ATTEMPTS: i32 = 5;
+
+/// Typed job payloads. Stored as JSON in `jobs.payload`, with the variant
+/// name duplicated into `jobs.kind` for querying.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum Job {
+    SendEmail {
+        to: String,
+        #[serde(flatten)]
+        template: EmailTemplate,
+    },
+    GenerateThumbnail {
+        post_id: Uuid,
+        video: String,
+    },
+    AccountCleanup {
+        user_id: Uuid,
+        profile_pic: Option<String>,
+        /// Post attachment keys. Named `videos` in jobs queued before image
+        /// posts.
+        #[serde(alias = "videos")]
+        media: Vec<String>,
+    },
+    /// Files dropped from a post that was edited or deleted.
+    RemovePostFiles {
+        post_id: Uuid,
+        #[serde(alias = "videos")]
+        media: Vec<String>,
+    },
+    /// Fills in a `post_media` row still pending, e.g. one carried over from
+    /// before uploads were probed.
+    ProbeVideo {
+        post_id: Uuid,
+        video: String,
+    },
+    /// Replaces a profile picture stored as uploaded with its processed
+    /// variants.
+    ProcessAvatar {
+        user_id: Uuid,
+        profile_pic: String,
+    },
+}
+
+impl Job {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            Job::SendEmail { .. } => "send_email",
+            Job::GenerateThumbnail { .. } => "generate_thumbnail",
+            Job::AccountCleanup { .. } => "account_cleanup",
+            Job::RemovePostFiles { .. } => "remove_post_files",
+            Job::ProbeVideo { .. } => "probe_video",
+            Job::ProcessAvatar { .. } => "process_avatar",
+        }
+    }
+}
+
+pub fn enqueue(conn: &mut PgConnection, job: &Job) -> QueryResult<i64> {
+    enqueue_at(conn, job, Utc::now().naive_utc(), DEFAULT_MAX_ATTEMPTS)
+}
+
+pub fn enqueue_at(
+    conn: &mut PgConnection,
+    job: &Job,
+    run_at: NaiveDateTime,
+    max_attempts: i32,
+) -> QueryResult<i64> {
+    let payload = serde_json::to_value(job)
+        .map_err(|e| diesel::result::Error::SerializationError(Box::new(e)))?;
+
+    diesel::insert_into(jobs::table)
+        .values(&NewJob {
+            kind: job.kind().to_string(),
+            payload,
+            max_attempts,
+            run_at,
+        })
+        .returning(jobs::id)
+        .get_result(conn)
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn payloads_queued_with_videos_still_parse() {
+        let post_id = Uuid::new_v4();
+        let old = json!({ "kind": "remove_post_files", "post_id": post_id, "videos": ["userpost/a.mp4"] });
+        let Job::RemovePostFiles { media, .. } = serde_json::from_value(old).unwrap() else { panic!("wrong variant") };
+        assert_eq!(media, ["userpost/a.mp4"]);
+
+        let old = json!({ "kind": "account_cleanup", "user_id": post_id, "profile_pic": null, "videos": [] });
+        assert!(matches!(serde_json::from_value(old).unwrap(), Job::AccountCleanup { .. }));
+
+        let new = serde_json::to_value(Job::RemovePostFiles { post_id, media: vec!["userpost/b.png".to_string()] }).unwrap();
+        assert_eq!(new["media"], json!(["userpost/b.png"]));
+    }
+}
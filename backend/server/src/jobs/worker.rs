@@ -0,0 +1,164 @@
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration as StdDuration;
+
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+
+use super::{handlers, Job};
+use crate::config::{Config, JobsConfig};
+use crate::db::DbPool;
+use crate::mailer::Mailer;
+use crate::models::job::JobRow;
+use crate::schema::jobs::dsl::*;
+use crate::storage::MediaStore;
+
+/// Everything a job handler may need. Cheap to clone.
+#[derive(Clone)]
+pub struct JobContext {
+    pub pool: DbPool,
+    pub mailer: Arc<dyn Mailer>,
+    pub store: Arc<dyn MediaStore>,
+    pub config: Arc<Config>,
+}
+
+/// Starts `count` worker threads polling the `jobs` table.
+pub fn spawn(ctx: JobContext, count: usize) -> Vec<JoinHandle<()>> {
+    (0..count)
+        .map(|n| {
+            let ctx = ctx.clone();
+            thread::Builder::new()
+                .name(format!("job-worker-{}", n))
+                .spawn(move || run_loop(ctx))
+                .expect("Failed to spawn job worker")
+        })
+        .collect()
+}
+
+fn run_loop(ctx: JobContext) {
+    let settings = ctx.config.jobs.clone();
+    let idle = StdDuration::from_millis(settings.poll_interval_ms);
+
+    // Storage calls are async; each worker drives them on its own runtime.
+    let runtime = tokio::runtime::Builder::new_current_thread()
+        .enable_all()
+        .build()
+        .expect("Failed to start job worker runtime");
+
+    loop {
+        let mut conn = match ctx.pool.get() {
+            Ok(c) => c,
+            Err(e) => {
+                eprintln!("❌ Job worker could not get DB connection: {}", e);
+                thread::sleep(idle);
+                continue;
+            }
+        };
+
+        let job = match claim_next(&mut conn, &settings) {
+            Ok(Some(job)) => job,
+            Ok(None) => {
+                drop(conn);
+                thread::sleep(idle);
+                continue;
+            }
+            Err(e) => {
+                eprintln!("❌ Failed to claim job: {}", e);
+                drop(conn);
+                thread::sleep(idle);
+                continue;
+            }
+        };
+        // Don't hold a pooled connection while the job runs; handlers take their own.
+        drop(conn);
+
+        let outcome = match serde_json::from_value::<Job>(job.payload.clone()) {
+            Ok(task) => runtime.block_on(handlers::run(&ctx, &task)),
+            Err(e) => Err(format!("invalid payload: {}", e)),
+        };
+
+        let recorded = ctx.pool.get().map_err(|e| e.to_string()).and_then(|mut conn| {
+            match &outcome {
+                Ok(()) => mark_done(&mut conn, job.id),
+                Err(err) => mark_failed(&mut conn, &job, err, &settings),
+            }
+            .map_err(|e| e.to_string())
+        });
+
+        match (&outcome, recorded) {
+            (_, Err(e)) => eprintln!("❌ Failed to record result of job {}: {}", job.id, e),
+            (Err(err), Ok(())) => eprintln!("❌ Job {} ({}) attempt {} failed: {}", job.id, job.kind, job.attempts, err),
+            (Ok(()), Ok(())) => {}
+        }
+    }
+}
+
+/// Locks the oldest runnable job. Jobs stuck in `running` longer than the
+/// lease (worker crashed mid-run) are picked up again.
+pub fn claim_next(conn: &mut PgConnection, settings: &JobsConfig) -> QueryResult<Option<JobRow>> {
+    conn.transaction(|conn| {
+        let now = Utc::now().naive_utc();
+        let stale_before = now - Duration::seconds(settings.lease_secs);
+
+        let next = jobs
+            .filter(
+                status.eq("pending").and(run_at.le(now))
+                    .or(status.eq("running").and(locked_at.lt(stale_before))),
+            )
+            .order(run_at.asc())
+            .for_update()
+            .skip_locked()
+            .select(JobRow::as_select())
+            .first::<JobRow>(conn)
+            .optional()?;
+
+        match next {
+            Some(job) => diesel::update(jobs.find(job.id))
+                .set((
+                    status.eq("running"),
+                    locked_at.eq(Some(now)),
+                    attempts.eq(attempts + 1),
+                ))
+                .returning(JobRow::as_returning())
+                .get_result(conn)
+                .map(Some),
+            None => Ok(None),
+        }
+    })
+}
+
+pub fn mark_done(conn: &mut PgConnection, job_id: i64) -> QueryResult<()> {
+    diesel::update(jobs.find(job_id))
+        .set((status.eq("done"), locked_at.eq(None::<chrono::NaiveDateTime>), last_error.eq(None::<String>)))
+        .execute(conn)
+        .map(|_| ())
+}
+
+/// Reschedules with exponential backoff, or dead-letters the job once it has
+/// used up its attempts.
+pub fn mark_failed(conn: &mut PgConnection, job: &JobRow, err: &str, settings: &JobsConfig) -> QueryResult<()> {
+    let target = jobs.find(job.id);
+
+    if job.attempts >= job.max_attempts {
+        diesel::update(target)
+            .set((status.eq("dead"), locked_at.eq(None::<chrono::NaiveDateTime>), last_error.eq(err)))
+            .execute(conn)?;
+    } else {
+        let delay = backoff(job.attempts, settings);
+        diesel::update(target)
+            .set((
+                status.eq("pending"),
+                locked_at.eq(None::<chrono::NaiveDateTime>),
+                last_error.eq(err),
+                run_at.eq(Utc::now().naive_utc() + delay),
+            ))
+            .execute(conn)?;
+    }
+    Ok(())
+}
+
+fn backoff(attempt: i32, settings: &JobsConfig) -> Duration {
+    let exp = attempt.clamp(1, 16) as u32 - 1;
+    let secs = settings.backoff_base_secs.saturating_mul(1i64 << exp);
+    Duration::seconds(secs.min(settings.backoff_max_secs))
+}
@@ -0,0 +1,51 @@
+pub mod avatar;
+pub mod config;
+pub mod db;
+pub mod entities;
+pub mod errors;
+pub mod handles;
+pub mod images;
+pub mod jobs;
+pub mod mailer;
+pub mod models;
+pub mod pagination;
+pub mod policy;
+pub mod probe;
+pub mod schema;
+pub mod storage;
+pub mod tokens;
+pub mod upload;
+pub mod handlers;
+pub mod routes;
+pub mod middleware;
+pub mod api_docs;
+
+use actix_web::web;
+
+use config::Config;
+use db::DbPool;
+
+/// What the HTTP app shares between workers. `main` builds it from the
+/// config; integration tests build one around a test database.
+#[derive(Clone)]
+pub struct AppState {
+    pub pool: DbPool,
+    pub config: web::Data<Config>,
+    pub mailer: web::Data<dyn mailer::Mailer>,
+    pub store: web::Data<dyn storage::MediaStore>,
+}
+
+impl AppState {
+    /// Registers the shared state, the extractor error handlers and every
+    /// route. `main` adds logging, CORS and the Swagger UI around it.
+    pub fn configure(&self, cfg: &mut web::ServiceConfig) {
+        cfg.app_data(web::Data::new(self.pool.clone()))
+            .app_data(self.config.clone())
+            .app_data(self.mailer.clone())
+            .app_data(self.store.clone())
+            .app_data(web::JsonConfig::default().error_handler(errors::json_error_handler))
+            .app_data(web::QueryConfig::default().error_handler(errors::query_error_handler))
+            .app_data(web::PathConfig::default().error_handler(errors::path_error_handler));
+        routes::init(cfg, self.pool.clone(), self.config.clone());
+    }
+}
@@ -0,0 +1,99 @@
+use std::fmt;
+use std::sync::Arc;
+
+use lettre::message::{Mailbox, Message, MultiPart};
+
+use crate::config::{Config, MailBackend};
+
+pub mod memory;
+pub mod smtp;
+pub mod spool;
+pub mod templates;
+
+pub use memory::MemoryMailer;
+pub use smtp::SmtpMailer;
+pub use spool::SpoolMailer;
+pub use templates::EmailTemplate;
+
+#[derive(Debug, Clone)]
+pub struct Email {
+    pub to: String,
+    pub subject: String,
+    pub html_body: String,
+    pub text_body: String,
+}
+
+impl Email {
+    pub fn from_template(to: impl Into<String>, template: &EmailTemplate) -> Self {
+        let rendered = template.render();
+        Self {
+            to: to.into(),
+            subject: rendered.subject,
+            html_body: rendered.html,
+            text_body: rendered.text,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub enum MailError {
+    Address(String),
+    Build(String),
+    Transport(String),
+    Io(std::io::Error),
+}
+
+impl fmt::Display for MailError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MailError::Address(a) => write!(f, "invalid email address: {}", a),
+            MailError::Build(e) => write!(f, "failed to build email: {}", e),
+            MailError::Transport(e) => write!(f, "failed to send email: {}", e),
+            MailError::Io(e) => write!(f, "failed to write email: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for MailError {}
+
+impl From<std::io::Error> for MailError {
+    fn from(e: std::io::Error) -> Self {
+        MailError::Io(e)
+    }
+}
+
+/// Outgoing email transport. Implementations are blocking, so call `send`
+/// from `web::block` when inside a request handler.
+pub trait Mailer: Send + Sync {
+    fn send(&self, email: &Email) -> Result<(), MailError>;
+}
+
+pub fn parse_mailbox(address: &str) -> Result<Mailbox, MailError> {
+    address
+        .parse::<Mailbox>()
+        .map_err(|_| MailError::Address(address.to_string()))
+}
+
+/// Renders an `Email` into a multipart/alternative message from `from`, so
+/// text-only clients get the plain part.
+pub fn build_message(from: &Mailbox, email: &Email) -> Result<Message, MailError> {
+    Message::builder()
+        .from(from.clone())
+        .to(parse_mailbox(&email.to)?)
+        .subject(email.subject.clone())
+        .multipart(MultiPart::alternative_plain_html(
+            email.text_body.clone(),
+            email.html_body.clone(),
+        ))
+        .map_err(|e| MailError::Build(e.to_string()))
+}
+
+pub fn from_config(config: &Config) -> Result<Arc<dyn Mailer>, MailError> {
+    let from = parse_mailbox(config.smtp.sender())?;
+
+    let mailer: Arc<dyn Mailer> = match config.mail.backend {
+        MailBackend::Smtp => Arc::new(SmtpMailer::new(&config.smtp, from)?),
+        MailBackend::Spool => Arc::new(SpoolMailer::new(&config.mail.spool_dir, from)?),
+    };
+    Ok(mailer)
+}
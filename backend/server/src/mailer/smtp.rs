@@ -0,0 +1,33 @@
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{SmtpTransport, Transport};
+
+use super::{build_message, Email, MailError, Mailer};
+use crate::config::SmtpConfig;
+
+pub struct SmtpMailer {
+    transport: SmtpTransport,
+    from: Mailbox,
+}
+
+impl SmtpMailer {
+    pub fn new(config: &SmtpConfig, from: Mailbox) -> Result<Self, MailError> {
+        let creds = Credentials::new(config.username.clone(), config.password.clone());
+        let transport = SmtpTransport::relay(&config.host)
+            .map_err(|e| MailError::Transport(e.to_string()))?
+            .credentials(creds)
+            .build();
+
+        Ok(Self { transport, from })
+    }
+}
+
+impl Mailer for SmtpMailer {
+    fn send(&self, email: &Email) -> Result<(), MailError> {
+        let message = build_message(&self.from, email)?;
+        self.transport
+            .send(&message)
+            .map(|_| ())
+            .map_err(|e| MailError::Transport(e.to_string()))
+    }
+}
@@ -0,0 +1,31 @@
+use std::fs;
+use std::path::PathBuf;
+
+use chrono::Utc;
+use lettre::message::Mailbox;
+use uuid::Uuid;
+
+use super::{build_message, Email, MailError, Mailer};
+
+/// Writes every message as an `.eml` file instead of sending it. Handy for
+/// local development: open the files in any mail client to check rendering.
+pub struct SpoolMailer {
+    dir: PathBuf,
+    from: Mailbox,
+}
+
+impl SpoolMailer {
+    pub fn new(dir: &str, from: Mailbox) -> Result<Self, MailError> {
+        fs::create_dir_all(dir)?;
+        Ok(Self { dir: PathBuf::from(dir), from })
+    }
+}
+
+impl Mailer for SpoolMailer {
+    fn send(&self, email: &Email) -> Result<(), MailError> {
+        let message = build_message(&self.from, email)?;
+        let filename = format!("{}_{}.eml", Utc::now().format("%Y%m%dT%H%M%S"), Uuid::new_v4());
+        fs::write(self.dir.join(filename), message.formatted())?;
+        Ok(())
+    }
+}
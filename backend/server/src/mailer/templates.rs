@@ -0,0 +1,250 @@
+use std::collections::HashMap;
+
+use serde::{Deserialize, Serialize};
+
+/// A transactional email plus the variables it needs. Each variant maps to a
+/// named template in `TEMPLATES`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "template", rename_all = "snake_case")]
+pub enum EmailTemplate {
+    PasswordReset { name: String, reset_link: String, expires_in_minutes: i64 },
+    Welcome { name: String, app_link: String },
+    VerifyEmail { name: String, verify_link: String },
+    NewFollower { name: String, follower_name: String, profile_link: String },
+}
+
+#[derive(Debug, Clone)]
+pub struct RenderedEmail {
+    pub subject: String,
+    pub html: String,
+    pub text: String,
+}
+
+struct Template {
+    name: &'static str,
+    subject: &'static str,
+    text: &'static str,
+    html: &'static str,
+}
+
+const TEMPLATES: &[Template] = &[
+    Template {
+        name: "password_reset",
+        subject: "Password Reset Request",
+        text: "Hello, {{name}}\n\n\
+               Use the link below to reset your password:\n{{reset_link}}\n\n\
+               This link expires in {{expires_in_minutes}} minutes. \
+               If you did not ask for a reset you can ignore this email.",
+        html: "<p>Hello, {{name}}</p>\
+               <p>Click below to reset your password:</p>\
+               <p><a href=\"{{reset_link}}\">Reset Password</a></p>\
+               <p>This link expires in {{expires_in_minutes}} minutes. \
+               If you did not ask for a reset you can ignore this email.</p>",
+    },
+    Template {
+        name: "welcome",
+        subject: "Welcome aboard, {{name}}!",
+        text: "Hi {{name}},\n\n\
+               Thanks for signing up. Start sharing videos and following friends at:\n{{app_link}}",
+        html: "<p>Hi {{name}},</p>\
+               <p>Thanks for signing up. Start sharing videos and following friends.</p>\
+               <p><a href=\"{{app_link}}\">Open the app</a></p>",
+    },
+    Template {
+        name: "verify_email",
+        subject: "Verify your email address",
+        text: "Hi {{name}},\n\n\
+               Please confirm your email address by opening this link:\n{{verify_link}}",
+        html: "<p>Hi {{name}},</p>\
+               <p>Please confirm your email address.</p>\
+               <p><a href=\"{{verify_link}}\">Verify email</a></p>",
+    },
+    Template {
+        name: "new_follower",
+        subject: "{{follower_name}} started following you",
+        text: "Hi {{name}},\n\n\
+               {{follower_name}} started following you.\n\
+               See their profile: {{profile_link}}",
+        html: "<p>Hi {{name}},</p>\
+               <p><strong>{{follower_name}}</strong> started following you.</p>\
+               <p><a href=\"{{profile_link}}\">See their profile</a></p>",
+    },
+];
+
+impl EmailTemplate {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EmailTemplate::PasswordReset { .. } => "password_reset",
+            EmailTemplate::Welcome { .. } => "welcome",
+            EmailTemplate::VerifyEmail { .. } => "verify_email",
+            EmailTemplate::NewFollower { .. } => "new_follower",
+        }
+    }
+
+    fn vars(&self) -> HashMap<&'static str, String> {
+        let mut vars = HashMap::new();
+        match self {
+            EmailTemplate::PasswordReset { name, reset_link, expires_in_minutes } => {
+                vars.insert("name", name.clone());
+                vars.insert("reset_link", reset_link.clone());
+                vars.insert("expires_in_minutes", expires_in_minutes.to_string());
+            }
+            EmailTemplate::Welcome { name, app_link } => {
+                vars.insert("name", name.clone());
+                vars.insert("app_link", app_link.clone());
+            }
+            EmailTemplate::VerifyEmail { name, verify_link } => {
+                vars.insert("name", name.clone());
+                vars.insert("verify_link", verify_link.clone());
+            }
+            EmailTemplate::NewFollower { name, follower_name, profile_link } => {
+                vars.insert("name", name.clone());
+                vars.insert("follower_name", follower_name.clone());
+                vars.insert("profile_link", profile_link.clone());
+            }
+        }
+        vars
+    }
+
+    pub fn render(&self) -> RenderedEmail {
+        let template = TEMPLATES
+            .iter()
+            .find(|t| t.name == self.name())
+            .expect("every EmailTemplate variant has a template");
+        let vars = self.vars();
+
+        RenderedEmail {
+            subject: substitute(template.subject, &vars, false),
+            text: substitute(template.text, &vars, false),
+            html: layout(&substitute(template.html, &vars, true)),
+        }
+    }
+}
+
+/// Replaces `{{var}}` placeholders. Unknown placeholders render as empty.
+fn substitute(source: &str, vars: &HashMap<&'static str, String>, escape: bool) -> String {
+    let mut out = String::with_capacity(source.len());
+    let mut rest = source;
+
+    while let Some(start) = rest.find("{{") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        match after.find("}}") {
+            Some(end) => {
+                let key = after[..end].trim();
+                if let Some(value) = vars.get(key) {
+                    if escape {
+                        out.push_str(&escape_html(value));
+                    } else {
+                        out.push_str(value);
+                    }
+                }
+                rest = &after[end + 2..];
+            }
+            None => {
+                out.push_str(&rest[start..]);
+                rest = "";
+            }
+        }
+    }
+    out.push_str(rest);
+    out
+}
+
+fn escape_html(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    for c in value.chars() {
+        match c {
+            '&' => out.push_str("&amp;"),
+            '<' => out.push_str("&lt;"),
+            '>' => out.push_str("&gt;"),
+            '"' => out.push_str("&quot;"),
+            '\'' => out.push_str("&#39;"),
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+fn layout(body: &str) -> String {
+    format!(
+        "<!DOCTYPE html><html><body style=\"font-family: Arial, sans-serif; color: #222;\">{}</body></html>",
+        body
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A user-chosen name that is also markup.
+    const NAME: &str = "<b>Ann & \"Bo\"</b>";
+    const ESCAPED: &str = "&lt;b&gt;Ann &amp; &quot;Bo&quot;&lt;/b&gt;";
+    const LINK: &str = "https://app.example/x?a=1";
+
+    /// Every template, with the values that must appear in both parts.
+    fn cases() -> Vec<(EmailTemplate, &'static str, Vec<&'static str>)> {
+        vec![
+            (
+                EmailTemplate::PasswordReset { name: NAME.into(), reset_link: LINK.into(), expires_in_minutes: 30 },
+                "Password Reset Request",
+                vec![LINK, "30 minutes"],
+            ),
+            (
+                EmailTemplate::Welcome { name: NAME.into(), app_link: LINK.into() },
+                "Welcome aboard, <b>Ann & \"Bo\"</b>!",
+                vec![LINK],
+            ),
+            (
+                EmailTemplate::VerifyEmail { name: NAME.into(), verify_link: LINK.into() },
+                "Verify your email address",
+                vec![LINK],
+            ),
+            (
+                EmailTemplate::NewFollower { name: "Cy".into(), follower_name: NAME.into(), profile_link: LINK.into() },
+                "<b>Ann & \"Bo\"</b> started following you",
+                vec![LINK, "Cy"],
+            ),
+        ]
+    }
+
+    #[test]
+    fn every_template_renders_its_variables() {
+        for (template, subject, values) in cases() {
+            let email = template.render();
+            let name = template.name();
+            assert_eq!(email.subject, subject, "{}", name);
+
+            for part in [&email.text, &email.html] {
+                assert!(!part.contains("{{"), "{}: unfilled placeholder in {}", name, part);
+                for value in &values {
+                    assert!(part.contains(value), "{}: {} missing from {}", name, value, part);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn names_are_escaped_in_html_only() {
+        for (template, _, _) in cases() {
+            let email = template.render();
+            let name = template.name();
+
+            assert!(email.html.contains(ESCAPED), "{}: {}", name, email.html);
+            assert!(!email.html.contains(NAME), "{}: {}", name, email.html);
+            assert!(email.html.starts_with("<!DOCTYPE html>"), "{}", name);
+
+            assert!(email.text.contains(NAME), "{}: {}", name, email.text);
+            assert!(!email.text.contains("&amp;"), "{}: {}", name, email.text);
+        }
+    }
+
+    #[test]
+    fn text_part_has_no_markup() {
+        for (template, _, _) in cases() {
+            // Apart from the name itself, which is the user's own text.
+            let text = template.render().text.replace(NAME, "");
+            assert!(!text.contains('<') && !text.contains('>'), "{}: {}", template.name(), text);
+        }
+    }
+}
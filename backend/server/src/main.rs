@@ -1,48 +1,91 @@
-pub mod db;
-pub mod models;
-pub mod schema;
-pub mod handlers;
-pub mod routes;
-pub mod middleware;
-pub mod api_docs;
-
 use actix_web::{App, HttpServer, middleware::Logger, web};
-use actix_files::Files;
 use actix_cors::Cors;
-use db::{DbPool, connection};   
+use server::config::Config;
+use server::db::{DbPool, connection};
+use server::{jobs, mailer, storage, AppState};
 use utoipa_swagger_ui::SwaggerUi;
-use crate::api_docs::ApiDoc;
-use utoipa::OpenApi; 
+use server::api_docs::ApiDoc;
+use utoipa::OpenApi;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
-    let pool = connection();
+    let config = match Config::load() {
+        Ok(c) => c,
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            std::process::exit(1);
+        }
+    };
+
+    let pool: DbPool = connection(&config.database);
+    let mailer: web::Data<dyn mailer::Mailer> = match mailer::from_config(&config) {
+        Ok(m) => web::Data::from(m),
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            std::process::exit(1);
+        }
+    };
+    let store: web::Data<dyn storage::MediaStore> = match storage::from_config(&config) {
+        Ok(s) => web::Data::from(s),
+        Err(e) => {
+            eprintln!("❌ {}", e);
+            std::process::exit(1);
+        }
+    };
+    let bind_addr = config.bind_addr();
+    let config = web::Data::new(config);
+
+    let job_ctx = jobs::JobContext {
+        pool: pool.clone(),
+        mailer: mailer.clone().into_inner(),
+        store: store.clone().into_inner(),
+        config: config.clone().into_inner(),
+    };
+
+    // `server worker` runs only the job queue, so it can be scaled separately.
+    if std::env::args().nth(1).as_deref() == Some("worker") {
+        let count = config.jobs.workers.max(1);
+        println!("⚙️  Running {} job worker(s)", count);
+        for handle in jobs::worker::spawn(job_ctx, count) {
+            let _ = handle.join();
+        }
+        return Ok(());
+    }
+
+    if config.jobs.workers > 0 {
+        jobs::worker::spawn(job_ctx, config.jobs.workers);
+    }
+
+    let state = AppState { pool, config: config.clone(), mailer, store };
 
     println!("✅ Database connected successfully");
-    println!("🚀 Server running on http://127.0.0.1:8081");
+    println!("🚀 Server running on http://{}:{}", bind_addr.0, bind_addr.1);
 
     HttpServer::new(move || {
-        let cors = Cors::default()
-            .allowed_origin("http://localhost:5173")
-            .allowed_origin("http://127.0.0.1:5173")
-            .allowed_origin("http://127.0.0.1:8081")
-            .allowed_methods(vec!["GET", "POST", "PUT", "DELETE", "OPTIONS"])
+        let mut cors = Cors::default();
+        for origin in &config.server.cors_origins {
+            cors = cors.allowed_origin(origin);
+        }
+        let cors = cors
+            .allowed_methods(vec!["GET", "POST", "PUT", "PATCH", "DELETE", "OPTIONS"])
             .allowed_headers(vec!["Content-Type", "Authorization"])
             .supports_credentials()
             .max_age(3600);
 
         App::new()
-            .wrap(Logger::default())
+            // Like the default format, but with the path only: query
+            // strings can carry signed media links.
+            .wrap(
+                Logger::new(r#"%a "%{method}xi %U" %s %b "%{Referer}i" "%{User-Agent}i" %T"#)
+                    .custom_request_replace("method", |req| req.method().to_string()),
+            )
             .wrap(cors)
-            .app_data(web::Data::new(pool.clone()))
-            .configure(|cfg| routes::init(cfg, pool.clone(), "mysecretkey".to_string()))
-            .service(Files::new("/profile_pic", "./files/userprofile").show_files_listing())
-            .service(Files::new("/video", "./files/userpost").show_files_listing())
+            .configure(|cfg| state.configure(cfg))
             .service( SwaggerUi::new("/swagger-ui/{_:.*}")
             .url("/api-docs/openapi.json", ApiDoc::openapi())
 )
     })
-    .bind(("127.0.0.1", 8081))?
+    .bind(bind_addr)?
     .run()
     .await
 }
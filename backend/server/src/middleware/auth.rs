@@ -1,23 +1,31 @@
 use actix_service::{Service, Transform};
 use actix_web::{
     dev::{ServiceRequest, ServiceResponse},
-    error::ErrorUnauthorized,
-    Error, HttpMessage,
+    web, Error, HttpMessage,
 };
 use diesel::prelude::*;
 use futures_util::future::{ready, LocalBoxFuture, Ready, FutureExt};
 use jsonwebtoken::{decode, DecodingKey, Validation, errors::ErrorKind};
 use std::rc::Rc;
+use chrono::Utc;
+use uuid::Uuid;
 
 use crate::{
+    config::Config,
     db::DbPool,
+    errors::ApiError,
+    middleware::AuthUser,
     models::user::{User, Claims},
+    schema::{sessions, users},
     schema::users::dsl::*,
 };
 
 pub struct AuthMiddlewareFactory {
     pub pool: DbPool,
-    pub jwt_secret: String,
+    pub config: web::Data<Config>,
+    /// Let requests without an `Authorization` header through anonymously.
+    /// A header that is present but invalid is still rejected.
+    pub optional: bool,
 }
 
 impl<S, B> Transform<S, ServiceRequest> for AuthMiddlewareFactory
@@ -35,7 +43,8 @@ where
         ready(Ok(AuthMiddleware {
             service: Rc::new(service),
             pool: self.pool.clone(),
-            jwt_secret: self.jwt_secret.clone(),
+            config: self.config.clone(),
+            optional: self.optional,
         }))
     }
 }
@@ -43,7 +52,8 @@ where
 pub struct AuthMiddleware<S> {
     service: Rc<S>,
     pool: DbPool,
-    jwt_secret: String,
+    config: web::Data<Config>,
+    optional: bool,
 }
 
 impl<S, B> Service<ServiceRequest> for AuthMiddleware<S>
@@ -62,29 +72,39 @@ where
         self.service.poll_ready(ctx)
     }
 
-    fn call(&self, mut req: ServiceRequest) -> Self::Future {
+    fn call(&self, req: ServiceRequest) -> Self::Future {
         let srv = Rc::clone(&self.service);
         let pool = self.pool.clone();
-        let secret = self.jwt_secret.clone();
+        let config = self.config.clone();
+        let optional = self.optional;
 
         async move {
             // 🔐 Get Authorization header
-            let auth_header = req.headers().get("Authorization")
-                .ok_or_else(|| ErrorUnauthorized("Missing Authorization header"))?;
+            let token = match req.headers().get("Authorization") {
+                Some(auth_header) => {
+                    let auth_str = auth_header.to_str().unwrap_or("");
+                    if !auth_str.starts_with("Bearer ") {
+                        return Err(ApiError::Unauthorized("Invalid Authorization header format".to_string()).into());
+                    }
 
-            let auth_str = auth_header.to_str().unwrap_or("");
-            if !auth_str.starts_with("Bearer ") {
-                return Err(ErrorUnauthorized("Invalid Authorization header format"));
-            }
+                    // 🧾 Extract the token string
+                    Some(auth_str.trim_start_matches("Bearer").trim().to_string())
+                }
+                None => None,
+            };
 
-            // 🧾 Extract the token string
-            let token = auth_str.trim_start_matches("Bearer").trim();
+            let Some(token) = token else {
+                if optional {
+                    return srv.call(req).await;
+                }
+                return Err(ApiError::Unauthorized("Missing Authorization header".to_string()).into());
+            };
 
             
             // 🔍 Decode JWT token with detailed error logging
             let decoded = match decode::<Claims>(
-                token,
-                &DecodingKey::from_secret(secret.as_ref()),
+                &token,
+                &DecodingKey::from_secret(config.auth.jwt_secret.as_ref()),
                 &Validation::default(),
             ) {
                 Ok(data) => data,
@@ -95,21 +115,33 @@ where
                         ErrorKind::ExpiredSignature => println!("⏰ Token expired"),
                         _ => println!("❌ JWT decode error: {:?}", err),
                     }
-                    return Err(ErrorUnauthorized("Invalid or expired token"));
+                    return Err(ApiError::Unauthorized("Invalid or expired token".to_string()).into());
                 }
             };
 
             let claims = decoded.claims;
 
-            let mut conn = pool.get()
-                .map_err(|_| actix_web::error::ErrorInternalServerError("DB connection failed"))?;
+            let mut conn = pool.get().map_err(ApiError::from)?;
+
+            let (user_id, session_id) = match (Uuid::parse_str(&claims.id), Uuid::parse_str(&claims.sid)) {
+                (Ok(u), Ok(s)) => (u, s),
+                _ => return Err(ApiError::Unauthorized("Invalid or expired token".to_string()).into()),
+            };
 
             let user = users
-                .filter(email.eq(&claims.email))
+                .inner_join(sessions::table)
+                .filter(id.eq(user_id))
+                .filter(token_version.eq(claims.ver))
+                .filter(sessions::id.eq(session_id))
+                .filter(sessions::revoked_at.is_null())
+                .filter(sessions::expires_at.gt(Utc::now().naive_utc()))
+                .select(users::all_columns)
                 .first::<User>(&mut conn)
-                .map_err(|_| ErrorUnauthorized("User not found"))?;
+                .optional()
+                .map_err(ApiError::from)?
+                .ok_or_else(|| ApiError::Unauthorized("Session expired or revoked".to_string()))?;
 
-            req.extensions_mut().insert(user);   
+            req.extensions_mut().insert(AuthUser { user, session_id });
 
             let res = srv.call(req).await?;
             Ok(res)
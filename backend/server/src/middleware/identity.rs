@@ -0,0 +1,59 @@
+use actix_web::{dev::Payload, FromRequest, HttpMessage, HttpRequest};
+use futures_util::future::{ready, Ready};
+use std::ops::Deref;
+use uuid::Uuid;
+
+use crate::errors::ApiError;
+use crate::models::user::User;
+
+/// The caller authenticated by `AuthMiddleware`. Taking this as a handler
+/// argument is the auth check: the request is rejected with 401 when no
+/// middleware put an identity on it.
+#[derive(Clone)]
+pub struct AuthUser {
+    pub user: User,
+    /// Session the access token was issued for.
+    pub session_id: Uuid,
+}
+
+impl Deref for AuthUser {
+    type Target = User;
+
+    fn deref(&self) -> &User {
+        &self.user
+    }
+}
+
+impl FromRequest for AuthUser {
+    type Error = ApiError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(
+            req.extensions()
+                .get::<AuthUser>()
+                .cloned()
+                .ok_or_else(|| ApiError::Unauthorized("Unauthorized".to_string())),
+        )
+    }
+}
+
+/// Like `AuthUser`, but anonymous callers get `None` instead of a 401. Use it
+/// behind `AuthMiddlewareFactory { optional: true, .. }`.
+#[derive(Clone)]
+pub struct MaybeAuthUser(pub Option<AuthUser>);
+
+impl MaybeAuthUser {
+    pub fn id(&self) -> Option<Uuid> {
+        self.0.as_ref().map(|u| u.id)
+    }
+}
+
+impl FromRequest for MaybeAuthUser {
+    type Error = ApiError;
+    type Future = Ready<Result<Self, Self::Error>>;
+
+    fn from_request(req: &HttpRequest, _: &mut Payload) -> Self::Future {
+        ready(Ok(MaybeAuthUser(req.extensions().get::<AuthUser>().cloned())))
+    }
+}
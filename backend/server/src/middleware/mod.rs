@@ -1 +1,4 @@
 pub mod auth;
+pub mod identity;
+
+pub use identity::{AuthUser, MaybeAuthUser};
@@ -0,0 +1,59 @@
+use diesel::prelude::*;
+use serde::{Serialize, Deserialize};
+use uuid::Uuid;
+use chrono::NaiveDateTime;
+use utoipa::ToSchema;
+use validator::Validate;
+use crate::schema::post_comments;
+
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = post_comments)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct PostComment {
+    pub id: Uuid,
+    pub post_id: Uuid,
+    pub user_id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub body: String,
+    pub created_at: NaiveDateTime,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = post_comments)]
+pub struct NewPostComment {
+    pub post_id: Uuid,
+    pub user_id: Uuid,
+    pub parent_id: Option<Uuid>,
+    pub body: String,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct CreateCommentRequest {
+    #[validate(length(min = 1, max = 2200, message = "Comment must be 1-2200 characters"))]
+    pub body: String,
+    /// Top-level comment to reply to. Replies can't be replied to.
+    pub parent_id: Option<Uuid>,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct UpdateCommentRequest {
+    #[validate(length(min = 1, max = 2200, message = "Comment must be 1-2200 characters"))]
+    pub body: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct CommentResponse {
+    pub id: Uuid,
+    pub post_id: Uuid,
+    pub user_id: Uuid,
+    pub user_name: String,
+    pub profile_pic: Option<String>,
+    pub parent_id: Option<Uuid>,
+    pub body: String,
+    pub created_at: NaiveDateTime,
+    /// Set once the comment has been edited.
+    pub updated_at: Option<NaiveDateTime>,
+    /// Always 0 for replies.
+    pub reply_count: i64,
+}
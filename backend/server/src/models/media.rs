@@ -0,0 +1,181 @@
+use diesel::prelude::*;
+use serde::Serialize;
+use uuid::Uuid;
+use chrono::NaiveDateTime;
+use utoipa::ToSchema;
+use crate::config::AuthConfig;
+use crate::probe::VideoInfo;
+use crate::schema::post_media;
+use crate::storage;
+use crate::tokens;
+use crate::upload::SavedFile;
+
+/// One attachment of a post. `status` is `pending` until the file has been
+/// read, then `ready`, or `failed` if it couldn't be.
+#[derive(Queryable, Selectable, Debug)]
+#[diesel(table_name = post_media)]
+#[diesel(check_for_backend(diesel::pg::Pg))]
+pub struct PostMedia {
+    pub id: Uuid,
+    pub post_id: Uuid,
+    pub position: i32,
+    pub kind: String,
+    pub storage_key: String,
+    pub byte_size: Option<i64>,
+    pub checksum: Option<String>,
+    pub duration_ms: Option<i64>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub codec: Option<String>,
+    pub status: String,
+    pub created_at: NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = post_media)]
+pub struct NewPostMedia {
+    pub post_id: Uuid,
+    pub position: i32,
+    pub kind: String,
+    pub storage_key: String,
+    pub byte_size: Option<i64>,
+    pub checksum: Option<String>,
+    pub duration_ms: Option<i64>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub codec: Option<String>,
+    pub status: String,
+}
+
+impl NewPostMedia {
+    /// A file saved in this request, already checked and probed.
+    pub fn uploaded(post_id: Uuid, position: usize, file: &SavedFile) -> Self {
+        let video = file.video.as_ref();
+        let (width, height) = match (video, file.image) {
+            (Some(v), _) => (Some(v.width as i32), Some(v.height as i32)),
+            (None, Some(i)) => (Some(i.width as i32), Some(i.height as i32)),
+            (None, None) => (None, None),
+        };
+        NewPostMedia {
+            post_id,
+            position: position as i32,
+            kind: file.format.kind().as_str().to_string(),
+            storage_key: file.key.clone(),
+            byte_size: Some(file.size as i64),
+            checksum: Some(file.checksum.clone()),
+            duration_ms: video.and_then(|v| v.duration_ms),
+            width,
+            height,
+            codec: video.map(|v| v.codec.clone()),
+            status: "ready".to_string(),
+        }
+    }
+}
+
+/// Metadata read from a stored file after the fact.
+#[derive(AsChangeset)]
+#[diesel(table_name = post_media)]
+pub struct ProcessedMedia {
+    pub byte_size: Option<i64>,
+    pub checksum: Option<String>,
+    pub duration_ms: Option<i64>,
+    pub width: Option<i32>,
+    pub height: Option<i32>,
+    pub codec: Option<String>,
+    pub status: String,
+}
+
+impl ProcessedMedia {
+    pub fn ready(byte_size: u64, checksum: String, info: &VideoInfo) -> Self {
+        ProcessedMedia {
+            byte_size: Some(byte_size as i64),
+            checksum: Some(checksum),
+            duration_ms: info.duration_ms,
+            width: Some(info.width as i32),
+            height: Some(info.height as i32),
+            codec: Some(info.codec.clone()),
+            status: "ready".to_string(),
+        }
+    }
+
+    /// The file is missing or unreadable; whatever is known is kept.
+    pub fn failed(byte_size: Option<u64>, checksum: Option<String>) -> Self {
+        ProcessedMedia {
+            byte_size: byte_size.map(|size| size as i64),
+            checksum,
+            duration_ms: None,
+            width: None,
+            height: None,
+            codec: None,
+            status: "failed".to_string(),
+        }
+    }
+}
+
+/// One slide of a post's carousel, tagged by `kind`. Everything but `key`
+/// and `status` is missing until the file has been processed.
+#[derive(Serialize, ToSchema, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "lowercase")]
+pub enum MediaInfo {
+    Video {
+        /// Storage key; the file is served at `/media/{key}`.
+        key: String,
+        /// Signed path to fetch the file from without an `Authorization`
+        /// header, e.g. as a `<video>` source. Expires after a while; fetch
+        /// the post again for a fresh one.
+        url: String,
+        /// Signed path of the poster frame, which may not be generated yet.
+        thumbnail_url: String,
+        /// `pending`, `ready` or `failed`.
+        status: String,
+        byte_size: Option<i64>,
+        /// Hex SHA-256 of the file.
+        checksum: Option<String>,
+        /// Missing when the file doesn't record it.
+        duration_ms: Option<i64>,
+        /// Display size; together they give the aspect ratio.
+        width: Option<i32>,
+        height: Option<i32>,
+        /// `h264`, `hevc`, `vp8`, `vp9`, `av1`, ...
+        codec: Option<String>,
+    },
+    Image {
+        key: String,
+        url: String,
+        status: String,
+        byte_size: Option<i64>,
+        checksum: Option<String>,
+        /// Display size, after EXIF rotation.
+        width: Option<i32>,
+        height: Option<i32>,
+    },
+}
+
+impl MediaInfo {
+    pub fn new(m: PostMedia, auth: &AuthConfig) -> Self {
+        let url = tokens::signed_media_url(auth, &m.storage_key);
+        match m.kind.as_str() {
+            "image" => MediaInfo::Image {
+                url,
+                key: m.storage_key,
+                status: m.status,
+                byte_size: m.byte_size,
+                checksum: m.checksum,
+                width: m.width,
+                height: m.height,
+            },
+            _ => MediaInfo::Video {
+                url,
+                thumbnail_url: tokens::signed_media_url(auth, &storage::thumbnail_key(&m.storage_key)),
+                key: m.storage_key,
+                status: m.status,
+                byte_size: m.byte_size,
+                checksum: m.checksum,
+                duration_ms: m.duration_ms,
+                width: m.width,
+                height: m.height,
+                codec: m.codec,
+            },
+        }
+    }
+}
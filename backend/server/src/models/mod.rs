@@ -1,5 +1,8 @@
 pub mod user;
 pub use user::{User, Claims};
 pub mod post;
-
-
+pub mod comment;
+pub mod media;
+pub mod search;
+pub mod job;
+pub mod session;
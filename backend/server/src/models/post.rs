@@ -2,7 +2,11 @@ use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 use chrono::NaiveDateTime;
-use utoipa::{ToSchema, IntoParams};
+use utoipa::ToSchema;
+use validator::Validate;
+
+use crate::entities::Entity;
+use crate::models::media::MediaInfo;
 
 
 
@@ -12,7 +16,6 @@ pub struct UserPost {
     pub id: Uuid,
     pub user_id: Option<Uuid>,
     pub description: String,
-    pub videos: Vec<Option<String>>,  // ✅ Changed to match schema
     pub created_at: Option<NaiveDateTime>,
 }
 
@@ -22,18 +25,28 @@ pub struct NewUserPost {
     pub id: Uuid,
     pub user_id: Option<Uuid>,
     pub description: String,
-     #[schema(value_type = Option<String>, format = Binary)]
-    pub videos: Vec<Option<String>>,  // ✅ Changed to match schema
     pub created_at: Option<NaiveDateTime>,
 }
 
+/// Multipart form for creating a post. Parts of `media` and `videos` are
+/// shown in the order they are sent.
+#[derive(ToSchema)]
+pub struct NewPostForm {
+    pub description: String,
+    /// Videos and images; the kind is read from the content.
+    #[schema(value_type = Vec<String>, format = Binary)]
+    pub media: Vec<String>,
+    /// Videos only, for older clients.
+    #[schema(value_type = Vec<String>, format = Binary)]
+    pub videos: Vec<String>,
+}
+
 #[derive(Serialize, Queryable, Selectable, ToSchema)]
 #[diesel(table_name = crate::schema::user_posts)]
 pub struct UserPostWithUser {
     pub id: Uuid,
     pub user_id: Option<Uuid>,
     pub description: String,
-    pub videos: Vec<Option<String>>,
     pub created_at: Option<NaiveDateTime>,
 }
 
@@ -42,8 +55,27 @@ pub struct UserPostResponse {
     pub id: Uuid,
     pub user_id: Option<Uuid>,
     pub description: String,
-    pub videos: Vec<String>,  // Cleaned up without Option
     pub created_at: Option<NaiveDateTime>,
     pub user_name: Option<String>,
     pub profile_pic: Option<String>,
-}
\ No newline at end of file
+    pub like_count: i64,
+    /// Whether the caller has liked this post.
+    pub liked_by_me: bool,
+    /// Comments and replies together.
+    pub comment_count: i64,
+    /// Hashtags and mentions in `description`, in order.
+    pub entities: Vec<Entity>,
+    /// The carousel: videos and images in display order.
+    pub media: Vec<MediaInfo>,
+}
+
+/// Fields left out are unchanged.
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct UpdatePostRequest {
+    #[validate(length(min = 1, message = "Description cannot be empty"))]
+    pub description: Option<String>,
+    /// Keys of the post's media in their new order. Media left out are
+    /// deleted; new ones can't be added here.
+    #[validate(length(min = 1, message = "A post needs at least one attachment"))]
+    pub media: Option<Vec<String>>,
+}
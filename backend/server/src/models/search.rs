@@ -0,0 +1,59 @@
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::models::post::UserPostResponse;
+use crate::pagination::{DEFAULT_LIMIT, MAX_LIMIT};
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SearchType {
+    #[default]
+    Users,
+    Posts,
+    Hashtags,
+}
+
+#[derive(Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SearchParams {
+    /// Search text, 1-100 characters.
+    pub q: String,
+    /// What to search. Defaults to `users`.
+    #[serde(rename = "type", default)]
+    #[param(rename = "type", inline, required = false)]
+    pub kind: SearchType,
+    /// Defaults to 20, capped at 50.
+    pub limit: Option<i64>,
+}
+
+impl SearchParams {
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+}
+
+#[derive(Queryable, Serialize, ToSchema)]
+pub struct UserSearchItem {
+    pub id: Uuid,
+    pub name: String,
+    pub profile_pic: Option<String>,
+    pub account_type: String,
+}
+
+#[derive(Serialize, ToSchema)]
+pub struct HashtagSearchItem {
+    pub tag: String,
+    /// Posts with this tag that the caller can see.
+    pub post_count: i64,
+}
+
+/// Best matches first. `type` echoes the kind that was searched.
+#[derive(Serialize, ToSchema)]
+#[serde(tag = "type", content = "items", rename_all = "lowercase")]
+pub enum SearchResults {
+    Users(Vec<UserSearchItem>),
+    Posts(Vec<UserPostResponse>),
+    Hashtags(Vec<HashtagSearchItem>),
+}
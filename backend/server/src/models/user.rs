@@ -2,8 +2,10 @@ use diesel::prelude::*;
 use serde::{Serialize, Deserialize};
 use uuid::Uuid;
 use chrono::NaiveDateTime;
-use utoipa::{ToSchema, IntoParams};
+use utoipa::ToSchema;
+use validator::Validate;
 use crate::schema::{users, password_reset_tokens, follows};
+use crate::storage;
 
 #[derive(Queryable, Serialize, Clone, ToSchema )]
 #[diesel(table_name = users)]
@@ -17,6 +19,9 @@ pub struct User {
     pub account_type: String,
     pub profile_pic: Option<String>,
     pub created_at: NaiveDateTime,
+    #[serde(skip_serializing)]
+    pub token_version: i32,
+    pub handle: String,
 }
 
 
@@ -43,16 +48,23 @@ pub struct NewUser {
     
     #[schema(value_type = Option<String>, format = Binary)]
     pub profile_pic: Option<String>,
+
+    /// What `@mentions` link to. Derived from the name when not given.
+    #[schema(example = "john.doe")]
+    pub handle: String,
 }
 
-#[derive(Deserialize, ToSchema)]
+#[derive(Deserialize, ToSchema, Validate)]
 pub struct LoginRequest {
+    #[validate(email(message = "Invalid email address"))]
     pub email: String,
+    #[validate(length(min = 1, message = "Password is required"))]
     pub password: String,
 }
 
-#[derive(Deserialize, ToSchema)]
+#[derive(Deserialize, ToSchema, Validate)]
 pub struct ForgotPasswordRequest {
+    #[validate(email(message = "Invalid email address"))]
     pub email: String,
 }
 
@@ -62,6 +74,10 @@ pub struct Claims {
     pub id: String,
     pub name: String,
     pub exp: usize,
+    /// Id of the session this access token was issued for.
+    pub sid: String,
+    /// Must match `users.token_version`, otherwise the token was invalidated.
+    pub ver: i32,
 }
 
 #[derive(Queryable, Insertable, Serialize, Deserialize, Debug, ToSchema)]
@@ -73,9 +89,19 @@ pub struct PasswordResetToken {
     pub expires_at: NaiveDateTime,
 }
 
-#[derive(Deserialize, ToSchema)]
+#[derive(Deserialize, ToSchema, Validate)]
+pub struct ChangePasswordRequest {
+    #[validate(length(min = 1, message = "Current password is required"))]
+    pub current_password: String,
+    #[validate(length(min = 8, message = "Password must be at least 8 characters"))]
+    pub new_password: String,
+}
+
+#[derive(Deserialize, ToSchema, Validate)]
 pub struct ResetPasswordRequest {
+    #[validate(length(min = 1, message = "Token is required"))]
     pub token: String,
+    #[validate(length(min = 8, message = "Password must be at least 8 characters"))]
     pub new_password: String,
 }
 
@@ -96,11 +122,11 @@ pub struct Follow {
     pub user_id: Uuid,
     pub target_id: Uuid,
     pub status: String,
-    pub created_at: Option<chrono::NaiveDateTime>,
+    pub created_at: NaiveDateTime,
 }
 
 #[derive(Insertable, ToSchema)]
-#[table_name = "follows"]
+#[diesel(table_name = follows)]
 pub struct NewFollow {
     pub user_id: Uuid,
     pub target_id: Uuid,
@@ -118,40 +144,47 @@ pub struct UserProfile {
     pub account_type: String,
     pub phoneno: String,
     pub address: Option<String>,
+    pub handle: String,
 }
 
+/// Only the fields that are `Some` are written.
 #[derive(AsChangeset, ToSchema)]
 #[diesel(table_name = users)]
 pub struct UserUpdate {
-    pub name: String,
-    pub email: String,
-    pub address: String,
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub address: Option<String>,
     pub account_type: Option<String>,
     pub phoneno: Option<String>,
+    pub handle: Option<String>,
 }
 
-#[derive(Deserialize, ToSchema)]
+#[derive(Deserialize, ToSchema, Validate)]
 pub struct UserUpdateRequest {
+    #[validate(length(min = 1, max = 100, message = "Username must be 1-100 characters"))]
     pub username: Option<String>,
+    #[validate(email(message = "Invalid email address"))]
     pub email: Option<String>,
-    pub accountType: Option<String>,
-    pub phoneNo: Option<String>,
+    #[serde(rename = "accountType")]
+    pub account_type: Option<String>,
+    #[serde(rename = "phoneNo")]
+    #[validate(length(min = 1, max = 20, message = "Phone number must be 1-20 characters"))]
+    pub phone_no: Option<String>,
     pub address: Option<String>,
-    pub loggedInUserId: Option<Uuid>,
+    /// What `@mentions` link to; see `handles`.
+    pub handle: Option<String>,
 }
 
-#[derive(Deserialize, ToSchema, IntoParams)]
-pub struct PaginationParams {
-    pub page: Option<i64>,
-    pub limit: Option<i64>,
-}
-
- #[derive(Deserialize, ToSchema)]
+/// The follower is always the authenticated user. Older clients also sent
+/// `userId`; it may still be sent, but only as the caller's own id.
+#[derive(Deserialize, ToSchema)]
 pub struct FollowBody {
-    pub userId: String,
-    pub targetId: String,
+    #[serde(rename = "targetId")]
+    pub target_id: Uuid,
+    /// `follow` or `unfollow`.
     pub action: String,
-    pub isRequest: Option<bool>,
+    #[serde(rename = "userId")]
+    pub user_id: Option<Uuid>,
 }
 
 #[derive(Queryable, Serialize, ToSchema)]
@@ -161,6 +194,15 @@ pub struct FollowerInfo {
     pub profile_pic: Option<String>,
 }
 
+impl FollowerInfo {
+    /// Replaces the stored key in `profile_pic` with the URL of its `size`
+    /// variant.
+    pub fn with_avatar_size(mut self, size: u32) -> Self {
+        self.profile_pic = self.profile_pic.map(|key| storage::avatar_url(&key, size));
+        self
+    }
+}
+
 #[derive(Queryable, Serialize, Identifiable, ToSchema)]
 #[diesel(table_name = follows)]
 #[diesel(primary_key(id))]
@@ -183,8 +225,8 @@ pub struct PendingRequest {
 
 #[derive(Deserialize, ToSchema)]
 pub struct HandleFollowRequest {
+    /// `approve` or `reject`.
     pub action: String,
-    pub ownerId: Option<String>, // Add this
 }
 
 #[derive(Serialize, ToSchema)]
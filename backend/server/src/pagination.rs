@@ -0,0 +1,222 @@
+//! Keyset pagination shared by every list endpoint.
+//!
+//! Lists are ordered newest first by `(created_at, id)`. A page is fetched
+//! with `LIMIT limit + 1`; the extra row only tells us whether another page
+//! exists, and the last row that is returned becomes the `next_cursor`.
+
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::{DateTime, NaiveDateTime};
+use diesel::dsl;
+use diesel::expression::{AsExpression, TypedExpressionType};
+use diesel::prelude::*;
+use diesel::sql_types::{is_nullable, SqlType, Uuid as SqlUuid};
+use serde::{Deserialize, Serialize};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::errors::ApiError;
+use crate::models::comment::CommentResponse;
+use crate::models::post::UserPostResponse;
+use crate::models::user::{FollowerInfo, PendingRequest, UserListItem};
+
+pub const DEFAULT_LIMIT: i64 = 20;
+pub const MAX_LIMIT: i64 = 50;
+
+/// Position of the last item on a page. Clients only ever see it encoded.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Cursor {
+    pub created_at: NaiveDateTime,
+    pub id: Uuid,
+}
+
+impl Cursor {
+    pub fn new(created_at: NaiveDateTime, id: Uuid) -> Self {
+        Cursor { created_at, id }
+    }
+
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(format!("{}:{}", self.created_at.and_utc().timestamp_micros(), self.id))
+    }
+
+    pub fn decode(raw: &str) -> Result<Self, ApiError> {
+        let invalid = || ApiError::BadRequest("Invalid cursor".to_string());
+
+        let bytes = URL_SAFE_NO_PAD.decode(raw).map_err(|_| invalid())?;
+        let text = String::from_utf8(bytes).map_err(|_| invalid())?;
+        let (micros, id) = text.split_once(':').ok_or_else(invalid)?;
+
+        let created_at = micros
+            .parse::<i64>()
+            .ok()
+            .and_then(DateTime::from_timestamp_micros)
+            .ok_or_else(invalid)?
+            .naive_utc();
+        let id = Uuid::parse_str(id).map_err(|_| invalid())?;
+
+        Ok(Cursor { created_at, id })
+    }
+}
+
+#[derive(Deserialize, ToSchema, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct PageParams {
+    /// `next_cursor` from the previous page; omit for the first page.
+    pub cursor: Option<String>,
+    /// Defaults to 20, capped at 50.
+    pub limit: Option<i64>,
+}
+
+impl PageParams {
+    pub fn limit(&self) -> i64 {
+        self.limit.unwrap_or(DEFAULT_LIMIT).clamp(1, MAX_LIMIT)
+    }
+
+    pub fn cursor(&self) -> Result<Option<Cursor>, ApiError> {
+        self.cursor.as_deref().map(Cursor::decode).transpose()
+    }
+}
+
+#[derive(Serialize, ToSchema)]
+#[aliases(
+    UserPage = Page<UserListItem>,
+    FollowerPage = Page<FollowerInfo>,
+    PendingRequestPage = Page<PendingRequest>,
+    PostPage = Page<UserPostResponse>,
+    CommentPage = Page<CommentResponse>
+)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// `None` on the last page.
+    pub next_cursor: Option<String>,
+    /// Size of the whole list, for endpoints that report it.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub total: Option<i64>,
+}
+
+impl<T> Page<T> {
+    /// Builds a page from up to `limit + 1` rows, taking the cursor of the
+    /// last kept row from `cursor_of`.
+    pub fn from_rows(mut rows: Vec<T>, limit: i64, cursor_of: impl Fn(&T) -> Cursor) -> Self {
+        let has_more = rows.len() as i64 > limit;
+        rows.truncate(limit as usize);
+
+        let next_cursor = if has_more {
+            rows.last().map(|row| cursor_of(row).encode())
+        } else {
+            None
+        };
+
+        Page { items: rows, next_cursor, total: None }
+    }
+
+    pub fn with_total(self, total: i64) -> Self {
+        Page { total: Some(total), ..self }
+    }
+
+    pub fn map<U>(self, f: impl FnMut(T) -> U) -> Page<U> {
+        Page {
+            items: self.items.into_iter().map(f).collect(),
+            next_cursor: self.next_cursor,
+            total: self.total,
+        }
+    }
+}
+
+pub type KeysetBefore<T, I> =
+    dsl::Or<dsl::Lt<T, NaiveDateTime>, dsl::And<dsl::Eq<T, NaiveDateTime>, dsl::Lt<I, Uuid>>>;
+
+/// `WHERE` clause selecting rows strictly after `cursor` in
+/// `ORDER BY created_at DESC, id DESC` order. Works for `Timestamp` and
+/// `Timestamptz` columns; nullable ones need `.assume_not_null()`.
+pub fn keyset_before<T, I, ST>(created_at: T, id: I, cursor: &Cursor) -> KeysetBefore<T, I>
+where
+    T: Expression<SqlType = ST> + ExpressionMethods + Copy,
+    I: Expression<SqlType = SqlUuid> + ExpressionMethods,
+    ST: SqlType<IsNull = is_nullable::NotNull> + TypedExpressionType,
+    NaiveDateTime: AsExpression<ST>,
+{
+    created_at
+        .lt(cursor.created_at)
+        .or(created_at.eq(cursor.created_at).and(id.lt(cursor.id)))
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    fn cursor() -> Cursor {
+        let created_at = NaiveDate::from_ymd_opt(2026, 10, 18).unwrap().and_hms_micro_opt(13, 5, 9, 123_456).unwrap();
+        Cursor::new(created_at, Uuid::parse_str("6f1c1e1a-2b3c-4d5e-8f90-a1b2c3d4e5f6").unwrap())
+    }
+
+    fn raw(text: &str) -> String {
+        URL_SAFE_NO_PAD.encode(text)
+    }
+
+    fn assert_bad_request(raw: &str) {
+        match Cursor::decode(raw) {
+            Err(ApiError::BadRequest(message)) => assert_eq!(message, "Invalid cursor"),
+            other => panic!("decoding {:?}: expected BadRequest, got {:?}", raw, other.map(|_| ())),
+        }
+    }
+
+    #[test]
+    fn round_trips() {
+        let cursor = cursor();
+        let encoded = cursor.encode();
+        assert!(encoded.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'_'));
+        assert_eq!(Cursor::decode(&encoded).unwrap(), cursor);
+
+        // Before the epoch too.
+        let old = Cursor::new(DateTime::from_timestamp_micros(-1_500_000).unwrap().naive_utc(), Uuid::nil());
+        assert_eq!(Cursor::decode(&old.encode()).unwrap(), old);
+    }
+
+    #[test]
+    fn bad_base64_is_rejected() {
+        // The last two use the standard alphabet and padding, not URL-safe.
+        for raw in ["!!!", "a", "abc$def", "YW Jj", "ab+/", "YWJj=="] {
+            assert_bad_request(raw);
+        }
+    }
+
+    #[test]
+    fn malformed_contents_are_rejected() {
+        let id = cursor().id;
+        let cases = [
+            String::new(),
+            "1792332122000000".to_string(),
+            format!("{}", id),
+            format!(":{}", id),
+            format!("yesterday:{}", id),
+            format!("1.5:{}", id),
+            format!("{}:{}", i64::MAX, id),
+            format!("99999999999999999999:{}", id),
+        ];
+        for text in &cases {
+            assert_bad_request(&raw(text));
+        }
+        assert_bad_request(&URL_SAFE_NO_PAD.encode([0xFF, 0xFE, b':', b'1']));
+    }
+
+    #[test]
+    fn non_uuid_ids_are_rejected() {
+        for id in ["", "42", "not-a-uuid", "6f1c1e1a-2b3c-4d5e-8f90-a1b2c3d4e5f", "6f1c1e1a-2b3c-4d5e-8f90-a1b2c3d4e5f6:extra"] {
+            assert_bad_request(&raw(&format!("1792332122000000:{}", id)));
+        }
+    }
+
+    #[test]
+    fn page_params_reject_bad_cursors_and_clamp_limits() {
+        let params = PageParams { cursor: Some("!!!".to_string()), limit: Some(1000) };
+        assert!(matches!(params.cursor(), Err(ApiError::BadRequest(_))));
+        assert_eq!(params.limit(), MAX_LIMIT);
+
+        let params = PageParams { cursor: None, limit: Some(0) };
+        assert_eq!(params.cursor().unwrap(), None);
+        assert_eq!(params.limit(), 1);
+        assert_eq!(PageParams { cursor: None, limit: None }.limit(), DEFAULT_LIMIT);
+    }
+}
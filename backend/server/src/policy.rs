@@ -0,0 +1,143 @@
+//! Visibility rules for user content. Every read path that exposes another
+//! user's profile details, follower lists, posts or media asks this module.
+//!
+//! - The owner sees everything.
+//! - If either user has blocked the other, the account does not exist as far
+//!   as the viewer is concerned.
+//! - Public accounts are visible to everyone, including anonymous callers.
+//! - Private accounts are visible to accepted followers only.
+
+use diesel::dsl::{exists, sql};
+use diesel::expression::BoxableExpression;
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::sql_types::{Bool, Nullable, Uuid as SqlUuid};
+use uuid::Uuid;
+
+use crate::errors::ApiError;
+use crate::schema::{follows, users};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// Profile details, posts and follower lists.
+    Full,
+    /// Private account the viewer doesn't follow: name, avatar and counts only.
+    Limited,
+}
+
+/// True when either user has blocked the other.
+pub fn is_blocked_between(conn: &mut PgConnection, a: Uuid, b: Uuid) -> QueryResult<bool> {
+    use crate::schema::blocks::dsl::*;
+
+    diesel::select(exists(
+        blocks.filter(
+            blocker_id.eq(a).and(blocked_id.eq(b))
+                .or(blocker_id.eq(b).and(blocked_id.eq(a))),
+        ),
+    ))
+    .get_result(conn)
+}
+
+/// Users `viewer` has blocked or been blocked by.
+pub fn blocked_with(conn: &mut PgConnection, viewer: Uuid) -> QueryResult<Vec<Uuid>> {
+    use crate::schema::blocks::dsl::*;
+
+    let rows = blocks
+        .filter(blocker_id.eq(viewer).or(blocked_id.eq(viewer)))
+        .select((blocker_id, blocked_id))
+        .load::<(Uuid, Uuid)>(conn)?;
+
+    Ok(rows.into_iter().map(|(a, b)| if a == viewer { b } else { a }).collect())
+}
+
+/// What `viewer` may see of `owner`. Unknown and blocked accounts are both
+/// reported as 404 so a block can't be probed for.
+pub fn access(conn: &mut PgConnection, viewer: Option<Uuid>, owner: Uuid) -> Result<Access, ApiError> {
+    let not_found = || ApiError::NotFound("User not found".to_string());
+
+    let account_type = users::table
+        .filter(users::id.eq(owner))
+        .select(users::account_type)
+        .first::<String>(conn)
+        .optional()?
+        .ok_or_else(not_found)?;
+
+    let Some(viewer) = viewer else {
+        return Ok(if account_type == "public" { Access::Full } else { Access::Limited });
+    };
+
+    if viewer == owner {
+        return Ok(Access::Full);
+    }
+
+    if is_blocked_between(conn, viewer, owner)? {
+        return Err(not_found());
+    }
+
+    if account_type == "public" {
+        return Ok(Access::Full);
+    }
+
+    let follows_owner: bool = diesel::select(exists(
+        follows::table
+            .filter(follows::user_id.eq(viewer))
+            .filter(follows::target_id.eq(owner))
+            .filter(follows::status.eq("accepted")),
+    ))
+    .get_result(conn)?;
+
+    Ok(if follows_owner { Access::Full } else { Access::Limited })
+}
+
+/// Like [`access`], but a private account the viewer doesn't follow is a 403.
+pub fn require_full_access(conn: &mut PgConnection, viewer: Option<Uuid>, owner: Uuid) -> Result<(), ApiError> {
+    match access(conn, viewer, owner)? {
+        Access::Full => Ok(()),
+        Access::Limited => Err(ApiError::Forbidden("This account is private".to_string())),
+    }
+}
+
+/// Checks that `viewer` may see the post and returns its author.
+pub fn require_post_access(conn: &mut PgConnection, viewer: Option<Uuid>, post_id: Uuid) -> Result<Uuid, ApiError> {
+    use crate::schema::user_posts;
+
+    let owner = user_posts::table
+        .filter(user_posts::id.eq(post_id))
+        .select(user_posts::user_id)
+        .first::<Option<Uuid>>(conn)
+        .optional()?
+        .flatten()
+        .ok_or_else(|| ApiError::NotFound("Post not found".to_string()))?;
+
+    require_full_access(conn, viewer, owner)?;
+    Ok(owner)
+}
+
+/// SQL filter over `user_posts` keeping only posts whose author `viewer` has
+/// full access to. The same rules as [`access`], evaluated in the database so
+/// listings can paginate over it.
+pub fn visible_posts<QS>(viewer: Option<Uuid>) -> Box<dyn BoxableExpression<QS, Pg, SqlType = Bool>> {
+    // Written as SQL because the queries using it join `users` already, and
+    // Diesel won't let the same table appear again in a subquery.
+    Box::new(
+        sql::<Bool>(
+            "user_posts.user_id IN (\
+                SELECT u.id FROM users u \
+                WHERE (u.account_type = 'public' OR u.id = ",
+        )
+        .bind::<Nullable<SqlUuid>, _>(viewer)
+        .sql(
+            " OR EXISTS (SELECT 1 FROM follows f \
+                WHERE f.target_id = u.id AND f.status = 'accepted' AND f.user_id = ",
+        )
+        .bind::<Nullable<SqlUuid>, _>(viewer)
+        .sql(
+            ")) AND NOT EXISTS (SELECT 1 FROM blocks b \
+                WHERE (b.blocker_id = u.id AND b.blocked_id = ",
+        )
+        .bind::<Nullable<SqlUuid>, _>(viewer)
+        .sql(") OR (b.blocked_id = u.id AND b.blocker_id = ")
+        .bind::<Nullable<SqlUuid>, _>(viewer)
+        .sql(")))"),
+    )
+}
@@ -0,0 +1,33 @@
+//! JPEG, PNG and WebP images.
+//!
+//! Only the header is decoded: enough for the size and, for JPEG and WebP,
+//! the EXIF orientation, which cameras use to record portrait shots.
+
+use std::io::{BufRead, Seek};
+
+use image::metadata::Orientation;
+use image::{ImageDecoder, ImageFormat, ImageReader};
+
+use super::{invalid, ImageInfo, ProbeError};
+
+pub fn probe<R: BufRead + Seek>(reader: R, format: ImageFormat) -> Result<ImageInfo, ProbeError> {
+    let mut decoder = ImageReader::with_format(reader, format)
+        .into_decoder()
+        .map_err(|e| invalid(e.to_string()))?;
+
+    let (mut width, mut height) = decoder.dimensions();
+    if width == 0 || height == 0 {
+        return Err(invalid("image has no dimensions"));
+    }
+
+    // An unreadable EXIF block is ignored; the pixels are still fine.
+    let orientation = decoder.orientation().unwrap_or(Orientation::NoTransforms);
+    if matches!(
+        orientation,
+        Orientation::Rotate90 | Orientation::Rotate270 | Orientation::Rotate90FlipH | Orientation::Rotate270FlipH
+    ) {
+        (width, height) = (height, width);
+    }
+
+    Ok(ImageInfo { width, height })
+}
@@ -0,0 +1,142 @@
+//! Media metadata read straight from the file, without ffmpeg.
+//!
+//! MP4 and QuickTime files (ISO base media boxes) are handled by [`mp4`],
+//! WebM files (Matroska EBML elements) by [`webm`], and images by [`image`].
+//! Only the headers are read; sample data is skipped with seeks, so probing
+//! a large upload costs a few small reads. A file that doesn't parse, or a
+//! video without a video track, is not something we can show and is
+//! rejected.
+
+use std::fmt;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+
+use crate::errors::ApiError;
+use crate::upload::{Format, MediaKind};
+
+pub mod image;
+pub mod mp4;
+pub mod webm;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct VideoInfo {
+    /// `None` when the container doesn't record it, e.g. live WebM.
+    pub duration_ms: Option<i64>,
+    /// Display size, after any rotation the container asks for.
+    pub width: u32,
+    pub height: u32,
+    /// Short codec name such as `h264`, `hevc`, `vp9` or `av1`. Other codecs
+    /// keep their container code, lowercased.
+    pub codec: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ImageInfo {
+    /// Display size, after any EXIF rotation.
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug)]
+pub enum ProbeError {
+    Io(std::io::Error),
+    /// Not a well-formed file, or no usable video track.
+    Invalid(String),
+}
+
+impl fmt::Display for ProbeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ProbeError::Io(e) => write!(f, "failed to read media: {}", e),
+            ProbeError::Invalid(reason) => write!(f, "{}", reason),
+        }
+    }
+}
+
+impl std::error::Error for ProbeError {}
+
+impl ProbeError {
+    /// The error to answer with when the upload in form field `field`, a
+    /// `kind` file, can't be read.
+    pub fn for_field(self, field: &str, kind: MediaKind) -> ApiError {
+        match self {
+            ProbeError::Invalid(reason) => {
+                ApiError::UnsupportedMedia(format!("'{}' is not a valid {}: {}", field, kind.as_str(), reason))
+            }
+            e => e.into(),
+        }
+    }
+}
+
+impl From<std::io::Error> for ProbeError {
+    fn from(e: std::io::Error) -> Self {
+        // Running out of bytes mid-header means the file is cut short.
+        if e.kind() == std::io::ErrorKind::UnexpectedEof {
+            ProbeError::Invalid("file is truncated".to_string())
+        } else {
+            ProbeError::Io(e)
+        }
+    }
+}
+
+impl From<ProbeError> for ApiError {
+    fn from(e: ProbeError) -> Self {
+        match e {
+            ProbeError::Invalid(reason) => ApiError::UnsupportedMedia(format!("Not a valid media file: {}", reason)),
+            ProbeError::Io(e) => ApiError::Internal(e.to_string()),
+        }
+    }
+}
+
+pub(crate) fn invalid(reason: impl Into<String>) -> ProbeError {
+    ProbeError::Invalid(reason.into())
+}
+
+/// Reads the metadata of the video at `path`, already sniffed as `format`.
+/// Blocking; call from `web::block` or a worker thread.
+pub fn probe_video(path: &Path, format: Format) -> Result<VideoInfo, ProbeError> {
+    let file = File::open(path)?;
+    let len = file.metadata()?.len();
+    let mut reader = BufReader::new(file);
+
+    match format {
+        Format::Mp4 | Format::Mov => mp4::probe(&mut reader, len),
+        Format::Webm => webm::probe(&mut reader, len),
+        _ => Err(invalid("not a video format")),
+    }
+}
+
+/// Reads the size of the image at `path`, already sniffed as `format`.
+/// Blocking, like [`probe_video`].
+pub fn probe_image(path: &Path, format: Format) -> Result<ImageInfo, ProbeError> {
+    let reader = BufReader::new(File::open(path)?);
+    image::probe(reader, image_format(format)?)
+}
+
+pub(crate) fn image_format(format: Format) -> Result<::image::ImageFormat, ProbeError> {
+    match format {
+        Format::Jpeg => Ok(::image::ImageFormat::Jpeg),
+        Format::Png => Ok(::image::ImageFormat::Png),
+        Format::Webp => Ok(::image::ImageFormat::WebP),
+        _ => Err(invalid("not an image format")),
+    }
+}
+
+/// Maps container codec codes to the names clients see.
+fn codec_name(code: &str) -> String {
+    match code {
+        "avc1" | "avc3" | "V_MPEG4/ISO/AVC" => "h264",
+        "hvc1" | "hev1" | "V_MPEGH/ISO/HEVC" => "hevc",
+        "av01" | "V_AV1" => "av1",
+        "vp08" | "V_VP8" => "vp8",
+        "vp09" | "V_VP9" => "vp9",
+        "mp4v" => "mpeg4",
+        other => return other.trim().to_ascii_lowercase(),
+    }
+    .to_string()
+}
+
+fn millis(duration: f64) -> Option<i64> {
+    (duration.is_finite() && duration > 0.0).then(|| duration.round() as i64)
+}
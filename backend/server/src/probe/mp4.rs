@@ -0,0 +1,351 @@
+//! ISO base media files: MP4, M4V and QuickTime MOV.
+//!
+//! Everything needed lives in the `moov` box, which encoders put either
+//! before or after the sample data (`mdat`). Top-level boxes are walked with
+//! seeks and only `moov` is read into memory.
+
+use std::io::{Read, Seek, SeekFrom};
+
+use super::{codec_name, invalid, millis, ProbeError, VideoInfo};
+
+/// Larger `moov` boxes are refused rather than buffered. Hours of video
+/// need a few MiB.
+const MAX_MOOV_BYTES: u64 = 64 * 1024 * 1024;
+
+type FourCc = [u8; 4];
+
+fn be_u16(data: &[u8], at: usize) -> Option<u16> {
+    data.get(at..at + 2).map(|b| u16::from_be_bytes([b[0], b[1]]))
+}
+
+fn be_u32(data: &[u8], at: usize) -> Option<u32> {
+    data.get(at..at + 4).map(|b| u32::from_be_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn be_u64(data: &[u8], at: usize) -> Option<u64> {
+    data.get(at..at + 8).map(|b| u64::from_be_bytes(b.try_into().expect("8 bytes")))
+}
+
+/// Splits `data` into its child boxes.
+fn boxes(mut data: &[u8]) -> Result<Vec<(FourCc, &[u8])>, ProbeError> {
+    let mut out = Vec::new();
+
+    while !data.is_empty() {
+        let size = be_u32(data, 0).ok_or_else(|| invalid("truncated box header"))? as u64;
+        let kind: FourCc = data.get(4..8).ok_or_else(|| invalid("truncated box header"))?.try_into().expect("4 bytes");
+
+        let (header, size) = match size {
+            0 => (8, data.len() as u64),
+            1 => (16, be_u64(data, 8).ok_or_else(|| invalid("truncated box header"))?),
+            n => (8, n),
+        };
+        if size < header || size > data.len() as u64 {
+            return Err(invalid(format!("box '{}' overruns its parent", String::from_utf8_lossy(&kind))));
+        }
+
+        out.push((kind, &data[header as usize..size as usize]));
+        data = &data[size as usize..];
+    }
+
+    Ok(out)
+}
+
+fn child<'a>(data: &'a [u8], kind: &FourCc) -> Result<Option<&'a [u8]>, ProbeError> {
+    Ok(boxes(data)?.into_iter().find(|(k, _)| k == kind).map(|(_, body)| body))
+}
+
+/// Walks nested boxes, e.g. `mdia/minf/stbl/stsd`.
+fn path<'a>(data: &'a [u8], kinds: &[&FourCc]) -> Result<Option<&'a [u8]>, ProbeError> {
+    let mut current = data;
+    for kind in kinds {
+        match child(current, kind)? {
+            Some(body) => current = body,
+            None => return Ok(None),
+        }
+    }
+    Ok(Some(current))
+}
+
+/// Timescale and duration from an `mvhd` or `mdhd` body. The duration is
+/// `None` when unset (0 or all ones, as fragmented files write it).
+fn header_duration(body: &[u8]) -> Option<(u32, Option<u64>)> {
+    let (timescale, duration) = match body.first()? {
+        1 => (be_u32(body, 20)?, be_u64(body, 24)?),
+        _ => (be_u32(body, 12)?, be_u32(body, 16)? as u64),
+    };
+    let duration = (duration != 0 && duration != u32::MAX as u64 && duration != u64::MAX).then_some(duration);
+    Some((timescale, duration))
+}
+
+fn to_millis(duration: u64, timescale: u32) -> Option<i64> {
+    if timescale == 0 {
+        return None;
+    }
+    millis(duration as f64 * 1000.0 / timescale as f64)
+}
+
+struct Track {
+    codec: String,
+    width: u32,
+    height: u32,
+    duration_ms: Option<i64>,
+}
+
+/// The track, if it is video.
+fn video_track(trak: &[u8]) -> Result<Option<Track>, ProbeError> {
+    let Some(mdia) = child(trak, b"mdia")? else {
+        return Ok(None);
+    };
+    let handler = child(mdia, b"hdlr")?.and_then(|hdlr| hdlr.get(8..12));
+    if handler != Some(b"vide") {
+        return Ok(None);
+    }
+
+    // First sample description: its type is the codec, followed by the
+    // coded size.
+    let stsd = path(mdia, &[b"minf", b"stbl", b"stsd"])?.ok_or_else(|| invalid("video track has no sample description"))?;
+    let entry = stsd.get(8..).ok_or_else(|| invalid("truncated sample description"))?;
+    let code = entry.get(4..8).ok_or_else(|| invalid("truncated sample description"))?;
+    let codec = codec_name(&String::from_utf8_lossy(code));
+    let coded = (be_u16(entry, 32).unwrap_or(0) as u32, be_u16(entry, 34).unwrap_or(0) as u32);
+
+    let duration_ms = child(mdia, b"mdhd")?
+        .and_then(header_duration)
+        .and_then(|(timescale, duration)| to_millis(duration?, timescale));
+
+    // The track header carries the display size (16.16 fixed point) and the
+    // transform matrix; phones record portrait video as rotated landscape.
+    let (mut width, mut height) = coded;
+    if let Some(tkhd) = child(trak, b"tkhd")? {
+        let base = if tkhd.first() == Some(&1) { 36 } else { 24 };
+        let display = (be_u32(tkhd, base + 52).unwrap_or(0) >> 16, be_u32(tkhd, base + 56).unwrap_or(0) >> 16);
+        if display.0 > 0 && display.1 > 0 {
+            (width, height) = display;
+        }
+        let (a, b) = (be_u32(tkhd, base + 16).unwrap_or(0), be_u32(tkhd, base + 20).unwrap_or(0));
+        if a == 0 && b != 0 {
+            (width, height) = (height, width);
+        }
+    }
+
+    Ok(Some(Track { codec, width, height, duration_ms }))
+}
+
+fn parse_moov(moov: &[u8]) -> Result<VideoInfo, ProbeError> {
+    let mvhd = child(moov, b"mvhd")?.and_then(header_duration);
+
+    let mut track = None;
+    for (kind, body) in boxes(moov)? {
+        if &kind == b"trak"
+            && let Some(found) = video_track(body)?
+        {
+            track = Some(found);
+            break;
+        }
+    }
+    let track = track.ok_or_else(|| invalid("no video track"))?;
+    if track.width == 0 || track.height == 0 {
+        return Err(invalid("video track has no dimensions"));
+    }
+
+    // Fragmented files leave the movie duration unset and record it in
+    // `mvex/mehd` instead, in the movie timescale.
+    let fragmented = path(moov, &[b"mvex", b"mehd"])?.and_then(|mehd| match mehd.first()? {
+        1 => be_u64(mehd, 4),
+        _ => be_u32(mehd, 4).map(u64::from),
+    });
+    let duration_ms = mvhd
+        .and_then(|(timescale, duration)| to_millis(duration.or(fragmented)?, timescale))
+        .or(track.duration_ms);
+
+    Ok(VideoInfo { duration_ms, width: track.width, height: track.height, codec: track.codec })
+}
+
+pub fn probe<R: Read + Seek>(reader: &mut R, len: u64) -> Result<VideoInfo, ProbeError> {
+    let mut pos = 0;
+
+    while pos < len {
+        reader.seek(SeekFrom::Start(pos))?;
+        let mut header = [0u8; 8];
+        reader.read_exact(&mut header)?;
+
+        let kind: FourCc = header[4..8].try_into().expect("4 bytes");
+        let (header_len, size) = match u32::from_be_bytes(header[0..4].try_into().expect("4 bytes")) {
+            0 => (8, len - pos),
+            1 => {
+                let mut large = [0u8; 8];
+                reader.read_exact(&mut large)?;
+                (16, u64::from_be_bytes(large))
+            }
+            n => (8, n as u64),
+        };
+        if size < header_len || size > len - pos {
+            return Err(invalid(format!("box '{}' overruns the file", String::from_utf8_lossy(&kind))));
+        }
+
+        if &kind == b"moov" {
+            let body_len = size - header_len;
+            if body_len > MAX_MOOV_BYTES {
+                return Err(invalid("movie header is too large"));
+            }
+            let mut moov = vec![0u8; body_len as usize];
+            reader.read_exact(&mut moov)?;
+            return parse_moov(&moov);
+        }
+
+        pos += size;
+    }
+
+    Err(invalid("no movie header ('moov' box)"))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    const IDENTITY: [u32; 9] = [0x10000, 0, 0, 0, 0x10000, 0, 0, 0, 0x4000_0000];
+    const ROTATE_90: [u32; 9] = [0, 0x10000, 0, 0xFFFF_0000, 0, 0, 0, 0, 0x4000_0000];
+
+    fn bx(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        let mut out = ((body.len() + 8) as u32).to_be_bytes().to_vec();
+        out.extend_from_slice(kind);
+        out.extend_from_slice(body);
+        out
+    }
+
+    fn full(kind: &[u8; 4], body: &[u8]) -> Vec<u8> {
+        bx(kind, &[&[0, 0, 0, 0], body].concat())
+    }
+
+    fn header(kind: &[u8; 4], timescale: u32, duration: u32) -> Vec<u8> {
+        let body = [[0u8; 8].as_slice(), &timescale.to_be_bytes(), &duration.to_be_bytes(), &[0; 4]].concat();
+        full(kind, &body)
+    }
+
+    fn tkhd(width: u32, height: u32, matrix: [u32; 9]) -> Vec<u8> {
+        let mut body = vec![0u8; 20 + 8 + 8];
+        matrix.iter().for_each(|v| body.extend_from_slice(&v.to_be_bytes()));
+        body.extend_from_slice(&(width << 16).to_be_bytes());
+        body.extend_from_slice(&(height << 16).to_be_bytes());
+        full(b"tkhd", &body)
+    }
+
+    fn trak(handler: &[u8; 4], codec: &[u8; 4], width: u16, height: u16, matrix: [u32; 9]) -> Vec<u8> {
+        let mut entry = vec![0u8; 24];
+        entry.extend_from_slice(&width.to_be_bytes());
+        entry.extend_from_slice(&height.to_be_bytes());
+        entry.extend_from_slice(&[0; 50]);
+        let stsd = full(b"stsd", &[1u32.to_be_bytes().as_slice(), &bx(codec, &entry)].concat());
+        let hdlr = full(b"hdlr", &[[0u8; 4].as_slice(), handler, &[0; 12]].concat());
+        let mdia = bx(b"mdia", &[header(b"mdhd", 1000, 7000), hdlr, bx(b"minf", &bx(b"stbl", &stsd))].concat());
+        bx(b"trak", &[tkhd(width as u32, height as u32, matrix), mdia].concat())
+    }
+
+    fn file(moov_body: &[u8]) -> Vec<u8> {
+        let ftyp = bx(b"ftyp", b"isom\0\0\x02\0isommp41");
+        [ftyp, bx(b"moov", moov_body), bx(b"mdat", &[7; 500])].concat()
+    }
+
+    fn probe_bytes(data: &[u8]) -> Result<VideoInfo, ProbeError> {
+        probe(&mut Cursor::new(data), data.len() as u64)
+    }
+
+    fn assert_invalid(result: Result<VideoInfo, ProbeError>) {
+        assert!(matches!(result, Err(ProbeError::Invalid(_))), "expected Invalid, got {:?}", result);
+    }
+
+    #[test]
+    fn reads_duration_size_and_codec() {
+        let moov = [
+            header(b"mvhd", 1000, 12_345),
+            trak(b"soun", b"mp4a", 0, 0, IDENTITY),
+            trak(b"vide", b"avc1", 1920, 1080, IDENTITY),
+        ]
+        .concat();
+
+        let info = probe_bytes(&file(&moov)).unwrap();
+        assert_eq!(info, VideoInfo { duration_ms: Some(12_345), width: 1920, height: 1080, codec: "h264".to_string() });
+    }
+
+    #[test]
+    fn finds_moov_after_mdat() {
+        let moov = bx(b"moov", &[header(b"mvhd", 600, 1500), trak(b"vide", b"vp09", 640, 480, IDENTITY)].concat());
+        let data = [bx(b"ftyp", b"isom\0\0\x02\0"), bx(b"mdat", &[0; 300]), moov].concat();
+
+        let info = probe_bytes(&data).unwrap();
+        assert_eq!((info.duration_ms, info.codec.as_str()), (Some(2500), "vp9"));
+    }
+
+    #[test]
+    fn rotation_matrix_swaps_width_and_height() {
+        let moov = [header(b"mvhd", 1000, 1000), trak(b"vide", b"hvc1", 1920, 1080, ROTATE_90)].concat();
+
+        let info = probe_bytes(&file(&moov)).unwrap();
+        assert_eq!((info.width, info.height, info.codec.as_str()), (1080, 1920, "hevc"));
+    }
+
+    #[test]
+    fn fragmented_duration_comes_from_mehd() {
+        let mvex = bx(b"mvex", &full(b"mehd", &4321u32.to_be_bytes()));
+        let moov = [header(b"mvhd", 1000, 0), trak(b"vide", b"av01", 640, 480, IDENTITY), mvex].concat();
+
+        let info = probe_bytes(&file(&moov)).unwrap();
+        assert_eq!((info.duration_ms, info.codec.as_str()), (Some(4321), "av1"));
+    }
+
+    #[test]
+    fn file_without_video_track_is_invalid() {
+        let moov = [header(b"mvhd", 1000, 1000), trak(b"soun", b"mp4a", 0, 0, IDENTITY)].concat();
+        assert_invalid(probe_bytes(&file(&moov)));
+    }
+
+    #[test]
+    fn file_without_moov_is_invalid() {
+        assert_invalid(probe_bytes(&[bx(b"ftyp", b"isom\0\0\x02\0"), bx(b"mdat", &[0; 100])].concat()));
+        assert_invalid(probe_bytes(&[]));
+    }
+
+    #[test]
+    fn truncated_box_is_invalid() {
+        let mut data = bx(b"ftyp", b"isom\0\0\x02\0");
+        data.extend_from_slice(&999_999u32.to_be_bytes());
+        data.extend_from_slice(b"mdat");
+        data.extend_from_slice(&[0; 100]);
+        assert_invalid(probe_bytes(&data));
+
+        // Cut inside a box header.
+        assert_invalid(probe_bytes(&[0, 0, 0]));
+    }
+
+    #[test]
+    fn oversized_lengths_are_invalid() {
+        // 64-bit size that runs past the end of the file.
+        let mut data = bx(b"ftyp", b"isom\0\0\x02\0");
+        data.extend_from_slice(&1u32.to_be_bytes());
+        data.extend_from_slice(b"mdat");
+        data.extend_from_slice(&u64::MAX.to_be_bytes());
+        assert_invalid(probe_bytes(&data));
+
+        // A child box claiming more than its parent holds.
+        let mut trak = trak(b"vide", b"avc1", 640, 480, IDENTITY);
+        trak[0..4].copy_from_slice(&u32::MAX.to_be_bytes());
+        assert_invalid(probe_bytes(&file(&[header(b"mvhd", 1000, 1000), trak].concat())));
+
+        // A moov too large to buffer is refused before allocating.
+        let huge = MAX_MOOV_BYTES + 16;
+        let mut data = (huge as u32).to_be_bytes().to_vec();
+        data.extend_from_slice(b"moov");
+        assert_invalid(probe(&mut Cursor::new(&data), huge));
+    }
+
+    #[test]
+    fn every_truncation_fails_cleanly() {
+        let moov = [header(b"mvhd", 1000, 5000), trak(b"vide", b"avc1", 320, 240, ROTATE_90)].concat();
+        let data = file(&moov);
+        for len in 0..data.len() {
+            let _ = probe_bytes(&data[..len]);
+        }
+    }
+}
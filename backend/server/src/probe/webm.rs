@@ -0,0 +1,371 @@
+//! WebM, a subset of Matroska.
+//!
+//! The file is a tree of EBML elements: an id and a size, both
+//! variable-length integers, then the body. `Info` (duration) and `Tracks`
+//! (codec, size) normally sit near the start of the `Segment`, before the
+//! first `Cluster` of frames, so the walk usually ends after a few kilobytes.
+
+use std::io::{Read, Seek, SeekFrom};
+
+use super::{codec_name, invalid, millis, ProbeError, VideoInfo};
+
+const EBML: u32 = 0x1A45_DFA3;
+const DOC_TYPE: u32 = 0x4282;
+const SEGMENT: u32 = 0x1853_8067;
+const CLUSTER: u32 = 0x1F43_B675;
+const INFO: u32 = 0x1549_A966;
+const TIMECODE_SCALE: u32 = 0x2A_D7B1;
+const DURATION: u32 = 0x4489;
+const TRACKS: u32 = 0x1654_AE6B;
+const TRACK_ENTRY: u32 = 0xAE;
+const TRACK_TYPE: u32 = 0x83;
+const CODEC_ID: u32 = 0x86;
+const VIDEO: u32 = 0xE0;
+const PIXEL_WIDTH: u32 = 0xB0;
+const PIXEL_HEIGHT: u32 = 0xBA;
+const DISPLAY_WIDTH: u32 = 0x54B0;
+const DISPLAY_HEIGHT: u32 = 0x54BA;
+const DISPLAY_UNIT: u32 = 0x54B2;
+
+const TRACK_TYPE_VIDEO: u64 = 1;
+
+/// Nanoseconds per timecode unit unless `Info` says otherwise.
+const DEFAULT_TIMECODE_SCALE: u64 = 1_000_000;
+
+/// Header elements larger than this are refused rather than buffered.
+const MAX_ELEMENT_BYTES: u64 = 16 * 1024 * 1024;
+
+/// Reads a variable-length integer: the count of leading zero bits in the
+/// first byte gives the length. Ids keep the length marker, sizes drop it.
+/// A size with all value bits set means "unknown".
+fn read_vint<R: Read>(reader: &mut R, max_len: u32, keep_marker: bool) -> Result<(u64, bool), ProbeError> {
+    let mut first = [0u8; 1];
+    reader.read_exact(&mut first)?;
+
+    let len = first[0].leading_zeros() + 1;
+    if len > max_len {
+        return Err(invalid("bad element header"));
+    }
+
+    let marker = 0x80u8 >> (len - 1);
+    let value_bits = first[0] & (marker - 1);
+    let mut value = if keep_marker { first[0] as u64 } else { value_bits as u64 };
+    let mut all_ones = value_bits == marker - 1;
+
+    for _ in 1..len {
+        let mut byte = [0u8; 1];
+        reader.read_exact(&mut byte)?;
+        value = (value << 8) | byte[0] as u64;
+        all_ones &= byte[0] == 0xFF;
+    }
+
+    Ok((value, !keep_marker && all_ones))
+}
+
+/// Id and size of the next element; the size is `None` when unknown.
+fn read_header<R: Read>(reader: &mut R) -> Result<(u32, Option<u64>), ProbeError> {
+    let (id, _) = read_vint(reader, 4, true)?;
+    let (size, unknown) = read_vint(reader, 8, false)?;
+    Ok((id as u32, (!unknown).then_some(size)))
+}
+
+/// Child elements of a buffered body.
+fn elements(data: &[u8]) -> Result<Vec<(u32, &[u8])>, ProbeError> {
+    let mut cursor = std::io::Cursor::new(data);
+    let mut out = Vec::new();
+
+    while (cursor.position() as usize) < data.len() {
+        let (id, size) = read_header(&mut cursor)?;
+        let start = cursor.position() as usize;
+        let size = size.ok_or_else(|| invalid("unknown size inside a header element"))? as usize;
+        let body = data.get(start..start.saturating_add(size)).ok_or_else(|| invalid("element overruns its parent"))?;
+        out.push((id, body));
+        cursor.set_position((start + size) as u64);
+    }
+
+    Ok(out)
+}
+
+fn uint(body: &[u8]) -> u64 {
+    body.iter().take(8).fold(0, |acc, b| (acc << 8) | *b as u64)
+}
+
+fn float(body: &[u8]) -> Option<f64> {
+    match body.len() {
+        4 => Some(f32::from_be_bytes(body.try_into().ok()?) as f64),
+        8 => Some(f64::from_be_bytes(body.try_into().ok()?)),
+        _ => None,
+    }
+}
+
+fn read_body<R: Read>(reader: &mut R, size: Option<u64>) -> Result<Vec<u8>, ProbeError> {
+    let size = size.ok_or_else(|| invalid("unknown size on a header element"))?;
+    if size > MAX_ELEMENT_BYTES {
+        return Err(invalid("header element is too large"));
+    }
+    let mut body = vec![0u8; size as usize];
+    reader.read_exact(&mut body)?;
+    Ok(body)
+}
+
+/// Duration in milliseconds from the `Info` element.
+fn parse_info(body: &[u8]) -> Result<Option<i64>, ProbeError> {
+    let mut scale = DEFAULT_TIMECODE_SCALE;
+    let mut duration = None;
+    for (id, value) in elements(body)? {
+        match id {
+            TIMECODE_SCALE => scale = uint(value),
+            DURATION => duration = float(value),
+            _ => {}
+        }
+    }
+    Ok(duration.and_then(|d| millis(d * scale as f64 / 1_000_000.0)))
+}
+
+/// Codec and display size of the first video track.
+fn parse_tracks(body: &[u8]) -> Result<Option<(String, u32, u32)>, ProbeError> {
+    for (id, entry) in elements(body)? {
+        if id != TRACK_ENTRY {
+            continue;
+        }
+
+        let mut track_type = 0;
+        let mut codec = None;
+        let (mut pixel, mut display, mut unit) = ((0, 0), (0, 0), 0);
+
+        for (id, value) in elements(entry)? {
+            match id {
+                TRACK_TYPE => track_type = uint(value),
+                CODEC_ID => codec = Some(String::from_utf8_lossy(value).trim_end_matches('\0').to_string()),
+                VIDEO => {
+                    for (id, value) in elements(value)? {
+                        match id {
+                            PIXEL_WIDTH => pixel.0 = uint(value),
+                            PIXEL_HEIGHT => pixel.1 = uint(value),
+                            DISPLAY_WIDTH => display.0 = uint(value),
+                            DISPLAY_HEIGHT => display.1 = uint(value),
+                            DISPLAY_UNIT => unit = uint(value),
+                            _ => {}
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        if track_type != TRACK_TYPE_VIDEO {
+            continue;
+        }
+        // Display size only counts when given in pixels (unit 0).
+        let (width, height) = if unit == 0 && display.0 > 0 && display.1 > 0 { display } else { pixel };
+        let codec = codec.ok_or_else(|| invalid("video track has no codec"))?;
+        return Ok(Some((codec_name(&codec), width.min(u32::MAX as u64) as u32, height.min(u32::MAX as u64) as u32)));
+    }
+    Ok(None)
+}
+
+pub fn probe<R: Read + Seek>(reader: &mut R, len: u64) -> Result<VideoInfo, ProbeError> {
+    let (id, size) = read_header(reader)?;
+    if id != EBML {
+        return Err(invalid("missing EBML header"));
+    }
+    let header = read_body(reader, size)?;
+    let doc_type = elements(&header)?
+        .into_iter()
+        .find(|(id, _)| *id == DOC_TYPE)
+        .map(|(_, value)| String::from_utf8_lossy(value).trim_end_matches('\0').to_string());
+    if !matches!(doc_type.as_deref(), Some("webm") | Some("matroska")) {
+        return Err(invalid("not a WebM document"));
+    }
+
+    let (id, size) = read_header(reader)?;
+    if id != SEGMENT {
+        return Err(invalid("missing segment"));
+    }
+    let segment_end = match size {
+        Some(size) => reader.stream_position()?.saturating_add(size).min(len),
+        None => len,
+    };
+
+    let mut duration_ms = None;
+    let mut track = None;
+
+    while reader.stream_position()? < segment_end {
+        let (id, size) = read_header(reader)?;
+        match id {
+            INFO => duration_ms = parse_info(&read_body(reader, size)?)?,
+            TRACKS => track = parse_tracks(&read_body(reader, size)?)?,
+            // Frames start here. Muxers write the headers we need before
+            // them, so only keep looking if `Tracks` hasn't shown up yet.
+            CLUSTER if track.is_some() => break,
+            _ => match size {
+                Some(size) => {
+                    if size > segment_end.saturating_sub(reader.stream_position()?) {
+                        return Err(invalid("element overruns the segment"));
+                    }
+                    reader.seek(SeekFrom::Current(size as i64))?;
+                }
+                None => break,
+            },
+        }
+        if track.is_some() && duration_ms.is_some() {
+            break;
+        }
+    }
+
+    let (codec, width, height) = track.ok_or_else(|| invalid("no video track"))?;
+    if width == 0 || height == 0 {
+        return Err(invalid("video track has no dimensions"));
+    }
+
+    Ok(VideoInfo { duration_ms, width, height, codec })
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use super::*;
+
+    const VOID: u32 = 0xEC;
+
+    fn id_bytes(id: u32) -> Vec<u8> {
+        let bytes = id.to_be_bytes();
+        let skip = bytes.iter().take_while(|b| **b == 0).count();
+        bytes[skip..].to_vec()
+    }
+
+    /// Sizes are always written as 8-byte vints so the builders stay simple.
+    fn el(id: u32, body: &[u8]) -> Vec<u8> {
+        let mut out = id_bytes(id);
+        out.push(0x01);
+        out.extend_from_slice(&(body.len() as u64).to_be_bytes()[1..]);
+        out.extend_from_slice(body);
+        out
+    }
+
+    fn uint_el(id: u32, value: u64) -> Vec<u8> {
+        el(id, &value.to_be_bytes())
+    }
+
+    fn header(doc_type: &str) -> Vec<u8> {
+        el(EBML, &el(DOC_TYPE, doc_type.as_bytes()))
+    }
+
+    fn track(kind: u64, codec: &str, video: &[u8]) -> Vec<u8> {
+        let mut body = [uint_el(TRACK_TYPE, kind), el(CODEC_ID, codec.as_bytes())].concat();
+        if !video.is_empty() {
+            body.extend(el(VIDEO, video));
+        }
+        el(TRACK_ENTRY, &body)
+    }
+
+    fn pixels(width: u64, height: u64) -> Vec<u8> {
+        [uint_el(PIXEL_WIDTH, width), uint_el(PIXEL_HEIGHT, height)].concat()
+    }
+
+    /// A segment of unknown size, as live muxers write it.
+    fn file(children: &[Vec<u8>]) -> Vec<u8> {
+        let mut data = header("webm");
+        data.extend(id_bytes(SEGMENT));
+        data.push(0xFF);
+        children.iter().for_each(|child| data.extend_from_slice(child));
+        data
+    }
+
+    fn basic() -> Vec<u8> {
+        file(&[
+            el(INFO, &el(DURATION, &5000f64.to_be_bytes())),
+            el(TRACKS, &[track(2, "A_OPUS", &[]), track(1, "V_VP9", &pixels(640, 360))].concat()),
+            el(CLUSTER, &[0; 64]),
+        ])
+    }
+
+    fn probe_bytes(data: &[u8]) -> Result<VideoInfo, ProbeError> {
+        probe(&mut Cursor::new(data), data.len() as u64)
+    }
+
+    fn assert_invalid(result: Result<VideoInfo, ProbeError>) {
+        assert!(matches!(result, Err(ProbeError::Invalid(_))), "expected Invalid, got {:?}", result);
+    }
+
+    #[test]
+    fn reads_duration_size_and_codec() {
+        let info = probe_bytes(&basic()).unwrap();
+        assert_eq!(info, VideoInfo { duration_ms: Some(5000), width: 640, height: 360, codec: "vp9".to_string() });
+    }
+
+    #[test]
+    fn display_size_and_timecode_scale_are_applied() {
+        let info = el(INFO, &[uint_el(TIMECODE_SCALE, 2_000_000), el(DURATION, &2500.5f32.to_be_bytes())].concat());
+        let video = [pixels(640, 360), uint_el(DISPLAY_WIDTH, 480), uint_el(DISPLAY_HEIGHT, 360)].concat();
+        let body = [el(VOID, &[0; 10]), info, el(TRACKS, &track(1, "V_AV1", &video))].concat();
+        let mut data = header("matroska");
+        data.extend(el(SEGMENT, &body));
+
+        let info = probe_bytes(&data).unwrap();
+        assert_eq!(info, VideoInfo { duration_ms: Some(5001), width: 480, height: 360, codec: "av1".to_string() });
+    }
+
+    #[test]
+    fn tracks_after_a_cluster_are_found() {
+        let data = file(&[el(CLUSTER, &[0; 64]), el(TRACKS, &track(1, "V_VP8", &pixels(320, 240)))]);
+
+        let info = probe_bytes(&data).unwrap();
+        assert_eq!((info.duration_ms, info.width, info.codec.as_str()), (None, 320, "vp8"));
+    }
+
+    #[test]
+    fn file_without_video_track_is_invalid() {
+        assert_invalid(probe_bytes(&file(&[el(TRACKS, &track(2, "A_OPUS", &[]))])));
+        assert_invalid(probe_bytes(&file(&[el(TRACKS, &track(1, "V_VP9", &pixels(0, 0)))])));
+    }
+
+    #[test]
+    fn other_documents_are_invalid() {
+        let mut data = header("mkv3d");
+        data.extend_from_slice(&basic()[header("webm").len()..]);
+        assert_invalid(probe_bytes(&data));
+        assert_invalid(probe_bytes(b"RIFF\0\0\0\0WEBPVP8 "));
+        assert_invalid(probe_bytes(&[]));
+    }
+
+    #[test]
+    fn truncated_element_is_invalid() {
+        let data = basic();
+        let tracks_end = data.len() - el(CLUSTER, &[0; 64]).len();
+        assert_invalid(probe_bytes(&data[..tracks_end - 3]));
+    }
+
+    #[test]
+    fn oversized_lengths_are_invalid() {
+        // A header element too large to buffer is refused before allocating.
+        let mut data = file(&[]);
+        data.extend(id_bytes(TRACKS));
+        data.push(0x01);
+        data.extend_from_slice(&(MAX_ELEMENT_BYTES + 1).to_be_bytes()[1..]);
+        assert_invalid(probe_bytes(&data));
+
+        // A skipped element claiming more than the file holds.
+        let mut data = file(&[]);
+        data.extend(id_bytes(CLUSTER));
+        data.extend_from_slice(&[0x01, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFF, 0xFE]);
+        assert_invalid(probe_bytes(&data));
+
+        // A child claiming more than its parent holds.
+        let mut video = id_bytes(VIDEO);
+        video.push(0x01);
+        video.extend_from_slice(&1000u64.to_be_bytes()[1..]);
+        video.extend(pixels(640, 360));
+        let entry = el(TRACK_ENTRY, &[uint_el(TRACK_TYPE, 1), el(CODEC_ID, b"V_VP9"), video].concat());
+        assert_invalid(probe_bytes(&file(&[el(TRACKS, &entry)])));
+    }
+
+    #[test]
+    fn every_truncation_fails_cleanly() {
+        let data = basic();
+        for len in 0..data.len() {
+            let _ = probe_bytes(&data[..len]);
+        }
+    }
+}
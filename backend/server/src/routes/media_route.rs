@@ -0,0 +1,18 @@
+use actix_web::web;
+use crate::handlers::media_handler;
+use crate::config::Config;
+use crate::db::DbPool;
+use crate::middleware::auth::AuthMiddlewareFactory;
+
+pub fn init(cfg: &mut web::ServiceConfig, pool: DbPool, config: web::Data<Config>) {
+    cfg.service(
+        web::scope("/media")
+            .wrap(AuthMiddlewareFactory {
+                pool,
+                config,
+                optional: true,
+            })
+            .route("/{key:.*}", web::get().to(media_handler::serve_media))
+            .route("/{key:.*}", web::head().to(media_handler::serve_media)),
+    );
+}
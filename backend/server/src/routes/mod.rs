@@ -1,13 +1,16 @@
 use actix_web::web;
+use crate::config::Config;
 use crate::db::DbPool;
 
+pub mod media_route;
 pub mod user_route;
 
-pub fn init(cfg: &mut web::ServiceConfig, pool: DbPool, jwt_secret: String) {
+pub fn init(cfg: &mut web::ServiceConfig, pool: DbPool, config: web::Data<Config>) {
     cfg.service(
         web::scope("/api")
-            .configure(|scope_cfg| user_route::init(scope_cfg, pool.clone(), jwt_secret.clone())),
+            .configure(|scope_cfg| user_route::init(scope_cfg, pool.clone(), config.clone())),
     );
+    media_route::init(cfg, pool, config);
 }
 
 
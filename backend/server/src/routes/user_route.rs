@@ -1,21 +1,27 @@
 use actix_web::web;
 use crate::handlers::user_handler;
 use crate::handlers::post_handler;
+use crate::handlers::comment_handler;
+use crate::handlers::search_handler;
+use crate::handlers::session_handler;
+use crate::config::Config;
 use crate::db::DbPool;
 use crate::middleware::auth::AuthMiddlewareFactory;
 
-pub fn init(cfg: &mut web::ServiceConfig, pool: DbPool, jwt_secret: String) {
+pub fn init(cfg: &mut web::ServiceConfig, pool: DbPool, config: web::Data<Config>) {
     cfg.service(
         web::scope("/user")
             .route("/register", web::post().to(user_handler::register_user))
             .route("/login", web::post().to(user_handler::login))
+            .route("/refresh", web::post().to(session_handler::refresh))
             .route("/forgot-password", web::post().to(user_handler::forgot_password))
             .route("/reset-password", web::post().to(user_handler::reset_password))
             .service(
                 web::scope("/auth")
                     .wrap(AuthMiddlewareFactory {
                         pool: pool.clone(),
-                        jwt_secret: jwt_secret.clone(),
+                        config: config.clone(),
+                        optional: false,
                     })
                     .route("/get-users", web::get().to(user_handler::get_users))
                     .route("/follow", web::post().to(user_handler::follow_button))
@@ -26,8 +32,30 @@ pub fn init(cfg: &mut web::ServiceConfig, pool: DbPool, jwt_secret: String) {
                     .route("/followings/{user_id}", web::get().to(user_handler::following_list))
                     .route("/follow-req/{user_id}", web::get().to(user_handler::follow_requests))
                     .route("/handle-follow-req/{request_id}", web::post().to(user_handler::handle_follow_request))
+                    .route("/block/{user_id}", web::post().to(user_handler::block_user))
+                    .route("/block/{user_id}", web::delete().to(user_handler::unblock_user))
                     .route("/posts", web::post().to(post_handler::create_user_post))
+                    .route("/posts/{post_id}", web::get().to(post_handler::get_post))
+                    .route("/posts/{post_id}", web::patch().to(post_handler::update_post))
+                    .route("/posts/{post_id}", web::delete().to(post_handler::delete_post))
+                    .route("/posts/{post_id}/like", web::post().to(post_handler::like_post))
+                    .route("/posts/{post_id}/like", web::delete().to(post_handler::unlike_post))
+                    .route("/posts/{post_id}/comments", web::get().to(comment_handler::list_comments))
+                    .route("/posts/{post_id}/comments", web::post().to(comment_handler::create_comment))
+                    .route("/comments/{comment_id}", web::patch().to(comment_handler::update_comment))
+                    .route("/comments/{comment_id}", web::delete().to(comment_handler::delete_comment))
+                    .route("/comments/{comment_id}/replies", web::get().to(comment_handler::list_replies))
                     .route("/getpost", web::get().to(post_handler::get_user_posts))
+                    .route("/users/{user_id}/posts", web::get().to(post_handler::list_user_posts))
+                    .route("/feed", web::get().to(post_handler::home_feed))
+                    .route("/hashtags/{tag}/posts", web::get().to(post_handler::hashtag_posts))
+                    .route("/search", web::get().to(search_handler::search))
+                    .route("/account", web::delete().to(user_handler::delete_account))
+                    .route("/sessions", web::get().to(session_handler::list_sessions))
+                    .route("/sessions/{session_id}", web::delete().to(session_handler::revoke_session))
+                    .route("/logout", web::post().to(session_handler::logout))
+                    .route("/change-password", web::post().to(user_handler::change_password))
+                    .route("/logout-all", web::post().to(session_handler::logout_all))
             ),
     );
 }
@@ -1,12 +1,44 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    blocks (blocker_id, blocked_id) {
+        blocker_id -> Uuid,
+        blocked_id -> Uuid,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     follows (id) {
         id -> Uuid,
         user_id -> Uuid,
         target_id -> Uuid,
         status -> Text,
-        created_at -> Nullable<Timestamptz>,
+        created_at -> Timestamptz,
+    }
+}
+
+diesel::table! {
+    hashtags (id) {
+        id -> Uuid,
+        tag -> Text,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    jobs (id) {
+        id -> Int8,
+        kind -> Text,
+        payload -> Jsonb,
+        status -> Text,
+        attempts -> Int4,
+        max_attempts -> Int4,
+        run_at -> Timestamp,
+        locked_at -> Nullable<Timestamp>,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamp,
+        updated_at -> Timestamp,
     }
 }
 
@@ -19,12 +51,78 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    post_comments (id) {
+        id -> Uuid,
+        post_id -> Uuid,
+        user_id -> Uuid,
+        parent_id -> Nullable<Uuid>,
+        body -> Text,
+        created_at -> Timestamp,
+        updated_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    post_hashtags (post_id, hashtag_id) {
+        post_id -> Uuid,
+        hashtag_id -> Uuid,
+    }
+}
+
+diesel::table! {
+    post_likes (post_id, user_id) {
+        post_id -> Uuid,
+        user_id -> Uuid,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    post_media (id) {
+        id -> Uuid,
+        post_id -> Uuid,
+        storage_key -> Text,
+        byte_size -> Nullable<Int8>,
+        duration_ms -> Nullable<Int8>,
+        width -> Nullable<Int4>,
+        height -> Nullable<Int4>,
+        codec -> Nullable<Text>,
+        created_at -> Timestamp,
+        position -> Int4,
+        kind -> Text,
+        checksum -> Nullable<Text>,
+        status -> Text,
+    }
+}
+
+diesel::table! {
+    post_mentions (post_id, user_id) {
+        post_id -> Uuid,
+        user_id -> Uuid,
+    }
+}
+
+diesel::table! {
+    sessions (id) {
+        id -> Uuid,
+        user_id -> Uuid,
+        refresh_token_hash -> Text,
+        previous_token_hash -> Nullable<Text>,
+        user_agent -> Nullable<Text>,
+        ip_address -> Nullable<Text>,
+        created_at -> Timestamp,
+        last_used_at -> Timestamp,
+        expires_at -> Timestamp,
+        revoked_at -> Nullable<Timestamp>,
+    }
+}
+
 diesel::table! {
     user_posts (id) {
         id -> Uuid,
         user_id -> Nullable<Uuid>,
         description -> Text,
-        videos -> Array<Nullable<Text>>,
         created_at -> Nullable<Timestamp>,
     }
 }
@@ -46,10 +144,37 @@ diesel::table! {
         #[max_length = 255]
         profile_pic -> Nullable<Varchar>,
         created_at -> Timestamp,
+        token_version -> Int4,
+        #[max_length = 30]
+        handle -> Varchar,
     }
 }
 
 diesel::joinable!(password_reset_tokens -> users (user_id));
+diesel::joinable!(post_comments -> user_posts (post_id));
+diesel::joinable!(post_comments -> users (user_id));
+diesel::joinable!(post_hashtags -> hashtags (hashtag_id));
+diesel::joinable!(post_hashtags -> user_posts (post_id));
+diesel::joinable!(post_likes -> user_posts (post_id));
+diesel::joinable!(post_likes -> users (user_id));
+diesel::joinable!(post_media -> user_posts (post_id));
+diesel::joinable!(post_mentions -> user_posts (post_id));
+diesel::joinable!(post_mentions -> users (user_id));
+diesel::joinable!(sessions -> users (user_id));
 diesel::joinable!(user_posts -> users (user_id));
 
-diesel::allow_tables_to_appear_in_same_query!(follows, password_reset_tokens, user_posts, users,);
+diesel::allow_tables_to_appear_in_same_query!(
+    blocks,
+    follows,
+    hashtags,
+    jobs,
+    password_reset_tokens,
+    post_comments,
+    post_hashtags,
+    post_likes,
+    post_media,
+    post_mentions,
+    sessions,
+    user_posts,
+    users,
+);
@@ -0,0 +1,96 @@
+use std::io::{ErrorKind, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::{Duration, UNIX_EPOCH};
+
+use async_trait::async_trait;
+use tokio::io::{AsyncReadExt, AsyncSeekExt};
+use tokio_util::io::ReaderStream;
+
+use super::{content_type_for, is_valid_key, ByteRange, MediaObject, MediaStore, ObjectMeta, StoreError};
+
+/// Keeps objects as files under `root`, one per key.
+pub struct LocalStore {
+    root: PathBuf,
+}
+
+impl LocalStore {
+    pub fn new(root: &str) -> Result<Self, StoreError> {
+        std::fs::create_dir_all(root)?;
+        Ok(Self { root: PathBuf::from(root) })
+    }
+
+    fn path(&self, key: &str) -> Result<PathBuf, StoreError> {
+        if !is_valid_key(key) {
+            return Err(StoreError::NotFound);
+        }
+        Ok(self.root.join(key))
+    }
+}
+
+fn object_meta(key: &str, metadata: &std::fs::Metadata) -> Result<ObjectMeta, StoreError> {
+    if !metadata.is_file() {
+        return Err(StoreError::NotFound);
+    }
+    let last_modified = metadata.modified().ok();
+    // Size and modification time, the way static file servers tag files.
+    let etag = last_modified
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|t| format!("{:x}-{:x}", metadata.len(), t.as_nanos()));
+
+    Ok(ObjectMeta {
+        size: metadata.len(),
+        content_type: content_type_for(key).to_string(),
+        etag,
+        last_modified,
+    })
+}
+
+#[async_trait]
+impl MediaStore for LocalStore {
+    async fn put_file(&self, key: &str, path: &Path, _content_type: &str) -> Result<(), StoreError> {
+        let target = self.path(key)?;
+        if let Some(parent) = target.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        tokio::fs::copy(path, &target).await?;
+        Ok(())
+    }
+
+    async fn stat(&self, key: &str) -> Result<ObjectMeta, StoreError> {
+        let metadata = tokio::fs::metadata(self.path(key)?).await?;
+        object_meta(key, &metadata)
+    }
+
+    async fn get(&self, key: &str, range: Option<ByteRange>) -> Result<MediaObject, StoreError> {
+        let mut file = tokio::fs::File::open(self.path(key)?).await?;
+        let meta = object_meta(key, &file.metadata().await?)?;
+
+        let bounds = match range {
+            Some(range) => Some(range.resolve(meta.size).ok_or(StoreError::RangeNotSatisfiable(meta.size))?),
+            None => None,
+        };
+        let (start, end) = bounds.unwrap_or((0, meta.size.saturating_sub(1)));
+        let len = if meta.size == 0 { 0 } else { end - start + 1 };
+
+        file.seek(SeekFrom::Start(start)).await?;
+
+        Ok(MediaObject {
+            body: Box::pin(ReaderStream::new(file.take(len))),
+            len,
+            range: bounds,
+            meta,
+        })
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StoreError> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Ok(()) => Ok(()),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(()),
+            Err(e) => Err(StoreError::Io(e)),
+        }
+    }
+
+    async fn presigned_url(&self, _key: &str, _ttl: Duration) -> Result<Option<String>, StoreError> {
+        Ok(None)
+    }
+}
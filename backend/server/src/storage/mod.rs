@@ -0,0 +1,370 @@
+//! Where uploaded media lives.
+//!
+//! Posts and users store storage keys such as `userpost/<id>_clip.mp4`, never
+//! paths or URLs. A [`MediaStore`] maps keys to bytes: [`LocalStore`] keeps
+//! them under a directory, [`S3Store`] in an S3-compatible bucket (AWS, MinIO,
+//! ...). Pick one with `storage.backend`.
+
+use std::fmt;
+use std::path::Path;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::time::{Duration, SystemTime};
+
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures_util::Stream;
+
+use crate::config::{Config, StorageBackend};
+use crate::errors::ApiError;
+
+pub mod local;
+pub mod s3;
+
+pub use local::LocalStore;
+pub use s3::S3Store;
+
+/// Key prefixes. They match the directories the server wrote to before
+/// storage keys existed, so a local store rooted at `./files` finds old
+/// uploads where they already are.
+pub const POST_PREFIX: &str = "userpost/";
+pub const PROFILE_PREFIX: &str = "userprofile/";
+const THUMBNAIL_PREFIX: &str = "userpost/thumbnails/";
+const AVATAR_PREFIX: &str = "userprofile/avatars/";
+
+pub fn post_key(filename: &str) -> String {
+    format!("{}{}", POST_PREFIX, filename)
+}
+
+pub fn profile_key(filename: &str) -> String {
+    format!("{}{}", PROFILE_PREFIX, filename)
+}
+
+/// Key of the generated poster frame of a video.
+pub fn thumbnail_key(video_key: &str) -> String {
+    let name = video_key.strip_prefix(POST_PREFIX).unwrap_or(video_key);
+    format!("{}{}.jpg", THUMBNAIL_PREFIX, name)
+}
+
+/// Every object a stored post attachment occupies: videos have a thumbnail,
+/// pictures don't.
+pub fn media_keys(key: &str) -> Vec<String> {
+    if content_type_for(key).starts_with("video/") {
+        vec![key.to_string(), thumbnail_key(key)]
+    } else {
+        vec![key.to_string()]
+    }
+}
+
+/// The video a thumbnail key belongs to.
+pub fn video_of_thumbnail(key: &str) -> Option<String> {
+    key.strip_prefix(THUMBNAIL_PREFIX)
+        .and_then(|rest| rest.strip_suffix(".jpg"))
+        .map(post_key)
+}
+
+/// Key of one size of a processed profile picture.
+pub fn avatar_key(id: &str, size: u32) -> String {
+    format!("{}{}/{}.jpg", AVATAR_PREFIX, id, size)
+}
+
+/// The same profile picture at `size`. Pictures stored before they were
+/// processed exist in one size only and come back unchanged.
+pub fn avatar_variant(key: &str, size: u32) -> String {
+    key.strip_prefix(AVATAR_PREFIX)
+        .and_then(|rest| rest.split_once('/'))
+        .map(|(id, _)| avatar_key(id, size))
+        .unwrap_or_else(|| key.to_string())
+}
+
+/// Where clients fetch the `size` variant of a profile picture. Profile
+/// pictures are public, so the URL isn't signed.
+pub fn avatar_url(key: &str, size: u32) -> String {
+    format!("/media/{}", avatar_variant(key, size))
+}
+
+/// Every object a stored profile picture occupies.
+pub fn avatar_keys(key: &str) -> Vec<String> {
+    if key.starts_with(AVATAR_PREFIX) {
+        crate::avatar::SIZES.iter().map(|size| avatar_variant(key, *size)).collect()
+    } else {
+        vec![key.to_string()]
+    }
+}
+
+/// Keys come from URLs, so reject anything that could step outside the store.
+pub fn is_valid_key(key: &str) -> bool {
+    !key.is_empty()
+        && !key.contains('\\')
+        && key.split('/').all(|segment| !segment.is_empty() && segment != "." && segment != "..")
+}
+
+/// Guesses the MIME type from the key's extension.
+pub fn content_type_for(key: &str) -> &'static str {
+    match Path::new(key).extension().and_then(|e| e.to_str()).map(str::to_ascii_lowercase).as_deref() {
+        Some("mp4") | Some("m4v") => "video/mp4",
+        Some("webm") => "video/webm",
+        Some("mov") => "video/quicktime",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("png") => "image/png",
+        Some("webp") => "image/webp",
+        _ => "application/octet-stream",
+    }
+}
+
+/// A single `Range: bytes=...` request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ByteRange {
+    /// `start-` or `start-end`, end inclusive.
+    From { start: u64, end: Option<u64> },
+    /// `-n`: the last `n` bytes.
+    Suffix(u64),
+}
+
+impl ByteRange {
+    /// Parses a `Range` header. Multiple ranges and other units aren't
+    /// supported; like invalid headers they yield `None` and the whole
+    /// object is served.
+    pub fn parse(header: &str) -> Option<Self> {
+        let spec = header.trim().strip_prefix("bytes=")?;
+        if spec.contains(',') {
+            return None;
+        }
+        let (start, end) = spec.split_once('-')?;
+        let (start, end) = (start.trim(), end.trim());
+
+        if start.is_empty() {
+            return end.parse().ok().filter(|n| *n > 0).map(ByteRange::Suffix);
+        }
+
+        let start = start.parse().ok()?;
+        let end = if end.is_empty() { None } else { Some(end.parse().ok()?) };
+        if end.is_some_and(|end| end < start) {
+            return None;
+        }
+        Some(ByteRange::From { start, end })
+    }
+
+    /// Inclusive bounds within an object of `size` bytes, or `None` when
+    /// the range can't be satisfied.
+    pub fn resolve(self, size: u64) -> Option<(u64, u64)> {
+        if size == 0 {
+            return None;
+        }
+        match self {
+            ByteRange::From { start, end } if start < size => Some((start, end.unwrap_or(size - 1).min(size - 1))),
+            ByteRange::From { .. } => None,
+            ByteRange::Suffix(n) => Some((size.saturating_sub(n), size - 1)),
+        }
+    }
+
+    pub fn header_value(self) -> String {
+        match self {
+            ByteRange::From { start, end: Some(end) } => format!("bytes={}-{}", start, end),
+            ByteRange::From { start, end: None } => format!("bytes={}-", start),
+            ByteRange::Suffix(n) => format!("bytes=-{}", n),
+        }
+    }
+}
+
+pub type ByteStream = Pin<Box<dyn Stream<Item = std::io::Result<Bytes>> + Send>>;
+
+/// What the store knows about an object without reading it.
+#[derive(Debug, Clone)]
+pub struct ObjectMeta {
+    pub size: u64,
+    pub content_type: String,
+    /// Opaque entity tag, without quotes. Changes whenever the content does.
+    pub etag: Option<String>,
+    pub last_modified: Option<SystemTime>,
+}
+
+/// An object, or the requested part of it, ready to stream.
+pub struct MediaObject {
+    pub body: ByteStream,
+    /// Bytes in `body`.
+    pub len: u64,
+    /// Inclusive bounds of `body` when a range was requested.
+    pub range: Option<(u64, u64)>,
+    pub meta: ObjectMeta,
+}
+
+#[derive(Debug)]
+pub enum StoreError {
+    NotFound,
+    /// Carries the object size for the `Content-Range` of the 416 response.
+    RangeNotSatisfiable(u64),
+    Io(std::io::Error),
+    Backend(String),
+}
+
+impl fmt::Display for StoreError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StoreError::NotFound => write!(f, "object not found"),
+            StoreError::RangeNotSatisfiable(size) => write!(f, "range not satisfiable for {} bytes", size),
+            StoreError::Io(e) => write!(f, "storage I/O error: {}", e),
+            StoreError::Backend(e) => write!(f, "storage backend error: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for StoreError {}
+
+impl From<std::io::Error> for StoreError {
+    fn from(e: std::io::Error) -> Self {
+        if e.kind() == std::io::ErrorKind::NotFound {
+            StoreError::NotFound
+        } else {
+            StoreError::Io(e)
+        }
+    }
+}
+
+impl From<StoreError> for ApiError {
+    fn from(e: StoreError) -> Self {
+        match e {
+            StoreError::NotFound => ApiError::NotFound("File not found".to_string()),
+            other => ApiError::Internal(other.to_string()),
+        }
+    }
+}
+
+#[async_trait]
+pub trait MediaStore: Send + Sync {
+    /// Stores the file at `path` under `key`, replacing any existing object.
+    /// `path` is left in place; callers clean up their own temp files.
+    async fn put_file(&self, key: &str, path: &Path, content_type: &str) -> Result<(), StoreError>;
+
+    async fn stat(&self, key: &str) -> Result<ObjectMeta, StoreError>;
+
+    /// Streams the object, or only `range` of it.
+    async fn get(&self, key: &str, range: Option<ByteRange>) -> Result<MediaObject, StoreError>;
+
+    /// Deleting a missing key succeeds, so job retries stay idempotent.
+    async fn delete(&self, key: &str) -> Result<(), StoreError>;
+
+    /// Time-limited URL clients can fetch the object from directly, or `None`
+    /// when the backend can't hand out URLs and the server must stream it.
+    async fn presigned_url(&self, key: &str, ttl: Duration) -> Result<Option<String>, StoreError>;
+}
+
+pub fn from_config(config: &Config) -> Result<Arc<dyn MediaStore>, StoreError> {
+    let store: Arc<dyn MediaStore> = match config.storage.backend {
+        StorageBackend::Local => Arc::new(LocalStore::new(&config.storage.local_root)?),
+        StorageBackend::S3 => Arc::new(S3Store::new(&config.storage.s3)?),
+    };
+    Ok(store)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn valid_keys() {
+        for key in [
+            "userpost/clip.mp4",
+            "userpost/thumbnails/clip.mp4.jpg",
+            "userprofile/avatars/0b9e/256.jpg",
+            "a",
+            "a b/c+d%20.mp4",
+            "..hidden/x..y",
+            "ünïcode/ファイル.png",
+        ] {
+            assert!(is_valid_key(key), "{:?} should be valid", key);
+        }
+    }
+
+    #[test]
+    fn keys_that_could_leave_the_store_are_invalid() {
+        for key in [
+            "",
+            "/",
+            "/etc/passwd",
+            "userpost/",
+            "userpost//clip.mp4",
+            "../secret",
+            "userpost/../../secret",
+            "userpost/./clip.mp4",
+            "userpost/..",
+            ".",
+            "userpost\\..\\secret",
+            "C:\\clip.mp4",
+        ] {
+            assert!(!is_valid_key(key), "{:?} should be invalid", key);
+        }
+    }
+
+    #[test]
+    fn parses_single_byte_ranges() {
+        let cases = [
+            ("bytes=0-", ByteRange::From { start: 0, end: None }),
+            ("bytes=0-0", ByteRange::From { start: 0, end: Some(0) }),
+            ("bytes=100-199", ByteRange::From { start: 100, end: Some(199) }),
+            (" bytes= 5 - 9 ", ByteRange::From { start: 5, end: Some(9) }),
+            ("bytes=-500", ByteRange::Suffix(500)),
+        ];
+        for (header, range) in cases {
+            assert_eq!(ByteRange::parse(header), Some(range), "parsing {:?}", header);
+            assert_eq!(ByteRange::parse(&range.header_value()), Some(range));
+        }
+    }
+
+    #[test]
+    fn unsupported_ranges_are_ignored() {
+        for header in [
+            "",
+            "bytes=",
+            "bytes=-",
+            "bytes=-0",
+            "bytes=5",
+            "bytes=9-5",
+            "bytes=a-b",
+            "bytes=1-2,4-5",
+            "bytes=-1-2",
+            "items=0-10",
+            "Bytes=0-10",
+            "bytes=18446744073709551616-",
+        ] {
+            assert_eq!(ByteRange::parse(header), None, "parsing {:?}", header);
+        }
+    }
+
+    #[test]
+    fn resolves_ranges_against_the_object_size() {
+        let from = |start, end| ByteRange::From { start, end };
+        let cases = [
+            (from(0, None), 1000, Some((0, 999))),
+            (from(10, Some(19)), 1000, Some((10, 19))),
+            // The end is clamped to the last byte.
+            (from(990, Some(5000)), 1000, Some((990, 999))),
+            (from(999, None), 1000, Some((999, 999))),
+            (from(1000, None), 1000, None),
+            (from(0, Some(0)), 0, None),
+            (ByteRange::Suffix(100), 1000, Some((900, 999))),
+            // A suffix longer than the object is the whole object.
+            (ByteRange::Suffix(5000), 1000, Some((0, 999))),
+            (ByteRange::Suffix(1), 0, None),
+        ];
+        for (range, size, expected) in cases {
+            assert_eq!(range.resolve(size), expected, "resolving {:?} against {} bytes", range, size);
+        }
+    }
+
+    #[test]
+    fn derived_keys() {
+        let video = post_key("id_clip.mp4");
+        assert_eq!(thumbnail_key(&video), "userpost/thumbnails/id_clip.mp4.jpg");
+        assert_eq!(video_of_thumbnail(&thumbnail_key(&video)), Some(video.clone()));
+        assert_eq!(media_keys(&video), [video.clone(), thumbnail_key(&video)]);
+        assert_eq!(media_keys("userpost/id_pic.jpg"), ["userpost/id_pic.jpg"]);
+
+        let avatar = avatar_key("abc", 256);
+        assert_eq!(avatar_variant(&avatar, 64), "userprofile/avatars/abc/64.jpg");
+        assert_eq!(avatar_variant("userprofile/old.png", 64), "userprofile/old.png");
+        assert_eq!(avatar_url(&avatar, 512), "/media/userprofile/avatars/abc/512.jpg");
+        assert_eq!(avatar_url("userprofile/old.png", 64), "/media/userprofile/old.png");
+        assert_eq!(avatar_keys(&avatar).len(), crate::avatar::SIZES.len());
+    }
+}
@@ -0,0 +1,307 @@
+//! S3-compatible object storage, signed with AWS Signature Version 4.
+//!
+//! Only the calls the server needs are implemented, so there's no SDK
+//! to pull in. Payloads are sent as `UNSIGNED-PAYLOAD`, which every
+//! S3-compatible service accepts over both HTTP and HTTPS.
+
+use std::path::Path;
+use std::time::{Duration, SystemTime};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt as _;
+use hmac::{Hmac, Mac};
+use reqwest::header::{self, HeaderMap};
+use reqwest::{Body, Client, Method, StatusCode};
+use sha2::{Digest, Sha256};
+use tokio_util::io::ReaderStream;
+
+use super::{content_type_for, is_valid_key, ByteRange, MediaObject, MediaStore, ObjectMeta, StoreError};
+use crate::config::S3Config;
+
+const ALGORITHM: &str = "AWS4-HMAC-SHA256";
+const UNSIGNED_PAYLOAD: &str = "UNSIGNED-PAYLOAD";
+
+/// Longest lifetime SigV4 allows for a presigned URL.
+const MAX_PRESIGN_SECS: u64 = 7 * 24 * 60 * 60;
+
+pub struct S3Store {
+    client: Client,
+    /// `scheme://host[:port]` of the service, without a trailing slash.
+    endpoint: String,
+    host: String,
+    bucket: String,
+    region: String,
+    access_key: String,
+    secret_key: String,
+    path_style: bool,
+}
+
+impl S3Store {
+    pub fn new(config: &S3Config) -> Result<Self, StoreError> {
+        let endpoint = config.endpoint.trim_end_matches('/').to_string();
+        let authority = endpoint
+            .split_once("://")
+            .map(|(_, rest)| rest)
+            .filter(|rest| !rest.is_empty() && !rest.contains('/'))
+            .ok_or_else(|| StoreError::Backend(format!("invalid S3 endpoint {:?}", config.endpoint)))?;
+
+        let host = if config.path_style {
+            authority.to_string()
+        } else {
+            format!("{}.{}", config.bucket, authority)
+        };
+
+        // The same client is used from the HTTP workers and from job threads,
+        // each on its own runtime; pooled connections would be bound to
+        // whichever runtime opened them.
+        let client = Client::builder()
+            .pool_max_idle_per_host(0)
+            .build()
+            .map_err(|e| StoreError::Backend(e.to_string()))?;
+
+        Ok(Self {
+            client,
+            endpoint,
+            host,
+            bucket: config.bucket.clone(),
+            region: config.region.clone(),
+            access_key: config.access_key.clone(),
+            secret_key: config.secret_key.clone(),
+            path_style: config.path_style,
+        })
+    }
+
+    /// Absolute path of `key` in request URLs, percent-encoded.
+    fn object_path(&self, key: &str) -> Result<String, StoreError> {
+        if !is_valid_key(key) {
+            return Err(StoreError::NotFound);
+        }
+        let key = uri_encode(key, false);
+        Ok(if self.path_style {
+            format!("/{}/{}", uri_encode(&self.bucket, true), key)
+        } else {
+            format!("/{}", key)
+        })
+    }
+
+    fn url(&self, path: &str, query: &str) -> String {
+        let scheme = self.endpoint.split("://").next().unwrap_or("https");
+        if query.is_empty() {
+            format!("{}://{}{}", scheme, self.host, path)
+        } else {
+            format!("{}://{}{}?{}", scheme, self.host, path, query)
+        }
+    }
+
+    fn scope(&self, now: &DateTime<Utc>) -> String {
+        format!("{}/{}/s3/aws4_request", now.format("%Y%m%d"), self.region)
+    }
+
+    fn signature(&self, now: &DateTime<Utc>, canonical_request: &str) -> String {
+        let string_to_sign = format!(
+            "{}\n{}\n{}\n{}",
+            ALGORITHM,
+            now.format("%Y%m%dT%H%M%SZ"),
+            self.scope(now),
+            hex::encode(Sha256::digest(canonical_request.as_bytes())),
+        );
+
+        let date = hmac(format!("AWS4{}", self.secret_key).as_bytes(), now.format("%Y%m%d").to_string().as_bytes());
+        let region = hmac(&date, self.region.as_bytes());
+        let service = hmac(&region, b"s3");
+        let signing_key = hmac(&service, b"aws4_request");
+        hex::encode(hmac(&signing_key, string_to_sign.as_bytes()))
+    }
+
+    /// Builds a request carrying an `Authorization` header.
+    fn signed(&self, method: Method, key: &str) -> Result<reqwest::RequestBuilder, StoreError> {
+        let path = self.object_path(key)?;
+        let now = Utc::now();
+        let amz_date = now.format("%Y%m%dT%H%M%SZ").to_string();
+        let signed_headers = "host;x-amz-content-sha256;x-amz-date";
+
+        let canonical_request = format!(
+            "{}\n{}\n\nhost:{}\nx-amz-content-sha256:{}\nx-amz-date:{}\n\n{}\n{}",
+            method, path, self.host, UNSIGNED_PAYLOAD, amz_date, signed_headers, UNSIGNED_PAYLOAD,
+        );
+        let authorization = format!(
+            "{} Credential={}/{}, SignedHeaders={}, Signature={}",
+            ALGORITHM,
+            self.access_key,
+            self.scope(&now),
+            signed_headers,
+            self.signature(&now, &canonical_request),
+        );
+
+        Ok(self
+            .client
+            .request(method, self.url(&path, ""))
+            .header("x-amz-content-sha256", UNSIGNED_PAYLOAD)
+            .header("x-amz-date", amz_date)
+            .header(header::AUTHORIZATION, authorization))
+    }
+}
+
+fn hmac(key: &[u8], data: &[u8]) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC accepts any key length");
+    mac.update(data);
+    mac.finalize().into_bytes().to_vec()
+}
+
+/// Percent-encodes everything but unreserved characters, as SigV4 requires.
+/// `/` is kept in object keys and encoded elsewhere.
+fn uri_encode(value: &str, encode_slash: bool) -> String {
+    let mut out = String::with_capacity(value.len());
+    for byte in value.bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => out.push(byte as char),
+            b'/' if !encode_slash => out.push('/'),
+            _ => out.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+    out
+}
+
+fn backend_error(e: reqwest::Error) -> StoreError {
+    StoreError::Backend(e.to_string())
+}
+
+async fn status_error(response: reqwest::Response) -> StoreError {
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    StoreError::Backend(format!("S3 responded {}: {}", status, body.trim()))
+}
+
+fn header_str(headers: &HeaderMap, name: header::HeaderName) -> Option<&str> {
+    headers.get(name).and_then(|v| v.to_str().ok())
+}
+
+fn object_meta(key: &str, headers: &HeaderMap, size: u64) -> ObjectMeta {
+    // Objects uploaded by other tools may carry a generic type.
+    let content_type = header_str(headers, header::CONTENT_TYPE)
+        .filter(|t| *t != "binary/octet-stream" && *t != "application/octet-stream")
+        .unwrap_or(content_type_for(key))
+        .to_string();
+
+    ObjectMeta {
+        size,
+        content_type,
+        etag: header_str(headers, header::ETAG).map(|t| t.trim_start_matches("W/").trim_matches('"').to_string()),
+        last_modified: header_str(headers, header::LAST_MODIFIED)
+            .and_then(|v| DateTime::parse_from_rfc2822(v).ok())
+            .map(SystemTime::from),
+    }
+}
+
+/// `bytes start-end/total` from a `Content-Range` header.
+fn parse_content_range(value: &str) -> Option<(u64, u64, u64)> {
+    let (range, total) = value.strip_prefix("bytes ")?.split_once('/')?;
+    let (start, end) = range.split_once('-')?;
+    Some((start.parse().ok()?, end.parse().ok()?, total.parse().ok()?))
+}
+
+#[async_trait]
+impl MediaStore for S3Store {
+    async fn put_file(&self, key: &str, path: &Path, content_type: &str) -> Result<(), StoreError> {
+        let file = tokio::fs::File::open(path).await?;
+        let len = file.metadata().await?.len();
+
+        let response = self
+            .signed(Method::PUT, key)?
+            .header(header::CONTENT_TYPE, content_type)
+            .header(header::CONTENT_LENGTH, len)
+            .body(Body::wrap_stream(ReaderStream::new(file)))
+            .send()
+            .await
+            .map_err(backend_error)?;
+
+        if !response.status().is_success() {
+            return Err(status_error(response).await);
+        }
+        Ok(())
+    }
+
+    async fn stat(&self, key: &str) -> Result<ObjectMeta, StoreError> {
+        let response = self.signed(Method::HEAD, key)?.send().await.map_err(backend_error)?;
+
+        match response.status() {
+            StatusCode::OK => {
+                let size = header_str(response.headers(), header::CONTENT_LENGTH)
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or_default();
+                Ok(object_meta(key, response.headers(), size))
+            }
+            StatusCode::NOT_FOUND => Err(StoreError::NotFound),
+            _ => Err(status_error(response).await),
+        }
+    }
+
+    async fn get(&self, key: &str, range: Option<ByteRange>) -> Result<MediaObject, StoreError> {
+        let mut request = self.signed(Method::GET, key)?;
+        if let Some(range) = range {
+            request = request.header(header::RANGE, range.header_value());
+        }
+        let response = request.send().await.map_err(backend_error)?;
+        let headers = response.headers();
+
+        let (len, size, bounds) = match response.status() {
+            StatusCode::OK => {
+                let len = response.content_length().unwrap_or_default();
+                (len, len, None)
+            }
+            StatusCode::PARTIAL_CONTENT => {
+                let (start, end, total) = header_str(headers, header::CONTENT_RANGE)
+                    .and_then(parse_content_range)
+                    .ok_or_else(|| StoreError::Backend("partial response without Content-Range".to_string()))?;
+                (end - start + 1, total, Some((start, end)))
+            }
+            StatusCode::NOT_FOUND => return Err(StoreError::NotFound),
+            StatusCode::RANGE_NOT_SATISFIABLE => {
+                let total = header_str(headers, header::CONTENT_RANGE)
+                    .and_then(|v| v.strip_prefix("bytes */"))
+                    .and_then(|v| v.parse().ok())
+                    .unwrap_or_default();
+                return Err(StoreError::RangeNotSatisfiable(total));
+            }
+            _ => return Err(status_error(response).await),
+        };
+        let meta = object_meta(key, headers, size);
+
+        Ok(MediaObject {
+            body: Box::pin(response.bytes_stream().map_err(std::io::Error::other)),
+            len,
+            range: bounds,
+            meta,
+        })
+    }
+
+    async fn delete(&self, key: &str) -> Result<(), StoreError> {
+        let response = self.signed(Method::DELETE, key)?.send().await.map_err(backend_error)?;
+
+        // S3 answers 204 whether or not the key existed; some stand-ins 404.
+        match response.status() {
+            status if status.is_success() || status == StatusCode::NOT_FOUND => Ok(()),
+            _ => Err(status_error(response).await),
+        }
+    }
+
+    async fn presigned_url(&self, key: &str, ttl: Duration) -> Result<Option<String>, StoreError> {
+        let path = self.object_path(key)?;
+        let now = Utc::now();
+        let credential = format!("{}/{}", self.access_key, self.scope(&now));
+
+        // Parameters in the canonical order: sorted by name.
+        let query = format!(
+            "X-Amz-Algorithm={}&X-Amz-Credential={}&X-Amz-Date={}&X-Amz-Expires={}&X-Amz-SignedHeaders=host",
+            ALGORITHM,
+            uri_encode(&credential, true),
+            now.format("%Y%m%dT%H%M%SZ"),
+            ttl.as_secs().clamp(1, MAX_PRESIGN_SECS),
+        );
+        let canonical_request = format!("GET\n{}\n{}\nhost:{}\n\nhost\n{}", path, query, self.host, UNSIGNED_PAYLOAD);
+        let signature = self.signature(&now, &canonical_request);
+
+        Ok(Some(self.url(&path, &format!("{}&X-Amz-Signature={}", query, signature))))
+    }
+}
@@ -0,0 +1,220 @@
+use actix_web::HttpRequest;
+use base64::{engine::general_purpose::URL_SAFE_NO_PAD, Engine as _};
+use chrono::{Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use diesel::PgConnection;
+use hmac::{Hmac, Mac};
+use jsonwebtoken::{encode, EncodingKey, Header};
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::config::AuthConfig;
+use crate::errors::ApiError;
+use crate::models::session::{NewSession, Session, TokenPair};
+use crate::models::user::{Claims, User};
+use crate::schema::sessions;
+
+const MAX_USER_AGENT_LEN: usize = 512;
+
+/// Device metadata recorded with each session.
+pub struct ClientMeta {
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+impl ClientMeta {
+    pub fn from_request(req: &HttpRequest) -> Self {
+        let user_agent = req
+            .headers()
+            .get("User-Agent")
+            .and_then(|v| v.to_str().ok())
+            .map(|ua| ua.chars().take(MAX_USER_AGENT_LEN).collect());
+        let ip_address = req.connection_info().realip_remote_addr().map(|ip| ip.to_string());
+
+        ClientMeta { user_agent, ip_address }
+    }
+}
+
+/// 256 random bits, URL-safe. Only its hash is ever stored.
+pub fn generate_refresh_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    URL_SAFE_NO_PAD.encode(bytes)
+}
+
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+pub fn encode_access_token(config: &AuthConfig, user: &User, session_id: Uuid) -> Result<(String, i64), ApiError> {
+    let ttl = Duration::minutes(config.access_token_ttl_minutes);
+    let claims = Claims {
+        id: user.id.to_string(),
+        name: user.name.clone(),
+        email: user.email.clone(),
+        exp: (Utc::now() + ttl).timestamp() as usize,
+        sid: session_id.to_string(),
+        ver: user.token_version,
+    };
+
+    let token = encode(&Header::default(), &claims, &EncodingKey::from_secret(config.jwt_secret.as_ref()))?;
+    Ok((token, ttl.num_seconds()))
+}
+
+fn media_mac(config: &AuthConfig, key: &str, expires: i64) -> Hmac<Sha256> {
+    // Keyed separately from JWTs so one can never pass for the other.
+    let mut mac = Hmac::<Sha256>::new_from_slice(format!("media:{}", config.jwt_secret).as_bytes())
+        .expect("HMAC accepts keys of any length");
+    mac.update(key.as_bytes());
+    mac.update(b"\n");
+    mac.update(expires.to_string().as_bytes());
+    mac
+}
+
+/// `/media/{key}` with a signature that grants read access to that one
+/// object for `media_url_ttl_minutes`. Only hand these to viewers already
+/// allowed to see the object.
+pub fn signed_media_url(config: &AuthConfig, key: &str) -> String {
+    let expires = (Utc::now() + Duration::minutes(config.media_url_ttl_minutes)).timestamp();
+    let signature = hex::encode(media_mac(config, key, expires).finalize().into_bytes());
+    format!("/media/{}?expires={}&signature={}", key, expires, signature)
+}
+
+/// Whether `signature` was issued for `key` by [`signed_media_url`] and
+/// hasn't expired.
+pub fn verify_media_signature(config: &AuthConfig, key: &str, expires: i64, signature: &str) -> bool {
+    if expires < Utc::now().timestamp() {
+        return false;
+    }
+    let Ok(signature) = hex::decode(signature) else {
+        return false;
+    };
+    media_mac(config, key, expires).verify_slice(&signature).is_ok()
+}
+
+fn refresh_expiry(config: &AuthConfig) -> NaiveDateTime {
+    Utc::now().naive_utc() + Duration::days(config.refresh_token_ttl_days)
+}
+
+/// Creates a session for a freshly authenticated user and returns its first token pair.
+pub fn start_session(
+    conn: &mut PgConnection,
+    config: &AuthConfig,
+    user: &User,
+    meta: ClientMeta,
+) -> Result<TokenPair, ApiError> {
+    let refresh_token = generate_refresh_token();
+
+    let session: Session = diesel::insert_into(sessions::table)
+        .values(&NewSession {
+            user_id: user.id,
+            refresh_token_hash: hash_token(&refresh_token),
+            user_agent: meta.user_agent,
+            ip_address: meta.ip_address,
+            expires_at: refresh_expiry(config),
+        })
+        .returning(Session::as_returning())
+        .get_result(conn)?;
+
+    let (token, expires_in) = encode_access_token(config, user, session.id)?;
+
+    Ok(TokenPair { token, refresh_token, expires_in, session_id: session.id })
+}
+
+/// Exchanges a refresh token for a new pair. The presented token is retired;
+/// if a retired token shows up again the whole session is revoked, since one
+/// of the two holders must be an attacker.
+pub fn rotate_session(
+    conn: &mut PgConnection,
+    config: &AuthConfig,
+    refresh_token: &str,
+    meta: ClientMeta,
+) -> Result<TokenPair, ApiError> {
+    use crate::schema::sessions::dsl as s;
+    use crate::schema::users::dsl as u;
+
+    let presented = hash_token(refresh_token);
+    let now = Utc::now().naive_utc();
+
+    let rotated = conn.transaction::<_, ApiError, _>(|conn| {
+        let Some(session) = s::sessions
+            .filter(s::refresh_token_hash.eq(&presented))
+            .for_update()
+            .select(Session::as_select())
+            .first(conn)
+            .optional()?
+        else {
+            return Ok(None);
+        };
+
+        if session.revoked_at.is_some() || session.expires_at <= now {
+            return Err(ApiError::Unauthorized("Session expired or revoked".to_string()));
+        }
+
+        let user = u::users.filter(u::id.eq(session.user_id)).first::<User>(conn)?;
+        let next_token = generate_refresh_token();
+
+        diesel::update(s::sessions.filter(s::id.eq(session.id)))
+            .set((
+                s::refresh_token_hash.eq(hash_token(&next_token)),
+                s::previous_token_hash.eq(Some(&presented)),
+                s::last_used_at.eq(now),
+                s::expires_at.eq(refresh_expiry(config)),
+                s::user_agent.eq(meta.user_agent.or(session.user_agent)),
+                s::ip_address.eq(meta.ip_address.or(session.ip_address)),
+            ))
+            .execute(conn)?;
+
+        let (token, expires_in) = encode_access_token(config, &user, session.id)?;
+        Ok(Some(TokenPair { token, refresh_token: next_token, expires_in, session_id: session.id }))
+    })?;
+
+    if let Some(pair) = rotated {
+        return Ok(pair);
+    }
+
+    let reused = diesel::update(
+        s::sessions
+            .filter(s::previous_token_hash.eq(&presented))
+            .filter(s::revoked_at.is_null()),
+    )
+    .set(s::revoked_at.eq(now))
+    .execute(conn)?;
+
+    if reused > 0 {
+        eprintln!("⚠️  Refresh token reuse detected; session revoked");
+    }
+
+    Err(ApiError::Unauthorized("Invalid refresh token".to_string()))
+}
+
+pub fn revoke_session(conn: &mut PgConnection, owner: Uuid, session_id: Uuid) -> Result<usize, ApiError> {
+    use crate::schema::sessions::dsl as s;
+
+    Ok(diesel::update(
+        s::sessions
+            .filter(s::id.eq(session_id))
+            .filter(s::user_id.eq(owner))
+            .filter(s::revoked_at.is_null()),
+    )
+    .set(s::revoked_at.eq(Utc::now().naive_utc()))
+    .execute(conn)?)
+}
+
+/// Logs the user out everywhere: revokes every session and bumps
+/// `token_version` so access tokens already handed out stop working too.
+pub fn revoke_all_sessions(conn: &mut PgConnection, owner: Uuid) -> Result<usize, ApiError> {
+    use crate::schema::sessions::dsl as s;
+    use crate::schema::users::dsl as u;
+
+    conn.transaction::<_, ApiError, _>(|conn| {
+        diesel::update(u::users.filter(u::id.eq(owner)))
+            .set(u::token_version.eq(u::token_version + 1))
+            .execute(conn)?;
+
+        Ok(diesel::update(s::sessions.filter(s::user_id.eq(owner)).filter(s::revoked_at.is_null()))
+            .set(s::revoked_at.eq(Utc::now().naive_utc()))
+            .execute(conn)?)
+    })
+}
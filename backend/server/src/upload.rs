@@ -0,0 +1,459 @@
+//! Multipart file uploads.
+//!
+//! Files are streamed to a temp file, then put into the [`MediaStore`] under a
+//! fresh key. The type is decided by the first bytes of the content, never by
+//! the client's filename or `Content-Type`, and the stored extension follows
+//! from it. Videos must also parse as a container with a video track, and
+//! images must have a readable header. Images are stored re-encoded without
+//! their metadata (see [`crate::images`]), so the stored format and name may
+//! differ from the upload's.
+//!
+//! Every object stored through an [`Uploads`] batch is deleted again when the
+//! batch is dropped without [`Uploads::keep`], so a request that fails
+//! halfway, whether on validation or in the database, leaves nothing behind.
+
+use actix_multipart::Field;
+use actix_web::web;
+use futures_util::TryStreamExt as _;
+use sha2::{Digest, Sha256};
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tokio::io::AsyncWriteExt;
+use uuid::Uuid;
+
+use crate::errors::ApiError;
+use crate::images;
+use crate::probe::{self, ImageInfo, ProbeError, VideoInfo};
+use crate::storage::MediaStore;
+
+/// Cap for plain text form fields.
+pub const MAX_TEXT_FIELD_BYTES: usize = 64 * 1024;
+
+/// Bytes needed to tell the supported formats apart.
+pub const SNIFF_LEN: usize = 12;
+
+/// Longest part of the client's filename kept in the stored name.
+const MAX_STEM_LEN: usize = 64;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MediaKind {
+    Video,
+    Image,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Format {
+    Mp4,
+    Webm,
+    Mov,
+    Jpeg,
+    Png,
+    Webp,
+}
+
+impl Format {
+    pub fn kind(self) -> MediaKind {
+        match self {
+            Format::Mp4 | Format::Webm | Format::Mov => MediaKind::Video,
+            Format::Jpeg | Format::Png | Format::Webp => MediaKind::Image,
+        }
+    }
+
+    /// Extension files of this format are stored with.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Mp4 => "mp4",
+            Format::Webm => "webm",
+            Format::Mov => "mov",
+            Format::Jpeg => "jpg",
+            Format::Png => "png",
+            Format::Webp => "webp",
+        }
+    }
+
+    pub fn mime(self) -> &'static str {
+        match self {
+            Format::Mp4 => "video/mp4",
+            Format::Webm => "video/webm",
+            Format::Mov => "video/quicktime",
+            Format::Jpeg => "image/jpeg",
+            Format::Png => "image/png",
+            Format::Webp => "image/webp",
+        }
+    }
+}
+
+impl MediaKind {
+    /// Name stored in `post_media.kind`.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            MediaKind::Video => "video",
+            MediaKind::Image => "image",
+        }
+    }
+
+    /// Extensions accepted on the client's filename.
+    pub fn extensions(self) -> &'static [&'static str] {
+        match self {
+            MediaKind::Video => &["mp4", "m4v", "webm", "mov"],
+            MediaKind::Image => &["jpg", "jpeg", "png", "webp"],
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            MediaKind::Video => "MP4, WebM or MOV video",
+            MediaKind::Image => "JPEG, PNG or WebP image",
+        }
+    }
+}
+
+const KINDS: [MediaKind; 2] = [MediaKind::Video, MediaKind::Image];
+
+/// Identifies a supported format from the start of a file.
+pub fn sniff(head: &[u8]) -> Option<Format> {
+    let at = |offset: usize, magic: &[u8]| head.get(offset..offset + magic.len()) == Some(magic);
+
+    if at(0, &[0xFF, 0xD8, 0xFF]) {
+        Some(Format::Jpeg)
+    } else if at(0, b"\x89PNG\r\n\x1a\n") {
+        Some(Format::Png)
+    } else if at(0, b"RIFF") && at(8, b"WEBP") {
+        Some(Format::Webp)
+    } else if at(0, &[0x1A, 0x45, 0xDF, 0xA3]) {
+        Some(Format::Webm)
+    } else if at(4, b"ftyp") {
+        // QuickTime files declare the `qt  ` brand; everything else ISO-based
+        // (isom, mp42, M4V, ...) plays as MP4.
+        Some(if at(8, b"qt  ") { Format::Mov } else { Format::Mp4 })
+    } else if [b"moov", b"mdat", b"wide", b"free"].iter().any(|atom| at(4, *atom)) {
+        // Older QuickTime files start straight with an atom.
+        Some(Format::Mov)
+    } else {
+        None
+    }
+}
+
+/// What a file field accepts.
+#[derive(Debug, Clone, Copy)]
+pub struct FileRules {
+    /// Per-file caps in bytes; `None` when the field doesn't take that kind.
+    pub max_video_bytes: Option<u64>,
+    pub max_image_bytes: Option<u64>,
+    /// How many files the whole batch may hold, across fields.
+    pub max_files: usize,
+}
+
+impl FileRules {
+    fn max_bytes(&self, kind: MediaKind) -> Option<u64> {
+        match kind {
+            MediaKind::Video => self.max_video_bytes,
+            MediaKind::Image => self.max_image_bytes,
+        }
+    }
+
+    fn accepts(&self, kind: MediaKind) -> bool {
+        self.max_bytes(kind).is_some()
+    }
+
+    fn label(&self) -> String {
+        KINDS.iter().filter(|k| self.accepts(**k)).map(|k| k.label()).collect::<Vec<_>>().join(" or ")
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SavedFile {
+    /// Storage key the file is put under by [`Uploads::save`].
+    pub key: String,
+    /// Local copy, available until the batch is dropped.
+    pub path: PathBuf,
+    pub format: Format,
+    /// Size and hex SHA-256 of the content, as stored.
+    pub size: u64,
+    pub checksum: String,
+    /// Container metadata, for videos.
+    pub video: Option<VideoInfo>,
+    /// Header metadata, for images.
+    pub image: Option<ImageInfo>,
+}
+
+/// Stored name: a fresh id, the sanitized client name, and the extension of
+/// the sniffed format.
+fn stored_name(original: Option<&str>, format: Format) -> String {
+    let stem = original
+        .map(|name| Path::new(name).file_stem().and_then(|s| s.to_str()).unwrap_or_default())
+        .map(sanitize_filename::sanitize)
+        .unwrap_or_default()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join("_");
+    let stem: String = stem.chars().take(MAX_STEM_LEN).collect();
+
+    if stem.is_empty() {
+        format!("{}.{}", Uuid::new_v4(), format.extension())
+    } else {
+        format!("{}_{}.{}", Uuid::new_v4(), stem, format.extension())
+    }
+}
+
+/// Reads a text field, rejecting anything over [`MAX_TEXT_FIELD_BYTES`].
+pub async fn read_text(field: &mut Field) -> Result<String, ApiError> {
+    let name = field.name().to_string();
+    let mut data = Vec::new();
+
+    while let Some(chunk) = field.try_next().await? {
+        if data.len() + chunk.len() > MAX_TEXT_FIELD_BYTES {
+            return Err(ApiError::PayloadTooLarge(format!("Field '{}' is too large", name)));
+        }
+        data.extend_from_slice(&chunk);
+    }
+
+    Ok(String::from_utf8_lossy(&data).into_owned())
+}
+
+/// Files saved while handling one request.
+pub struct Uploads {
+    store: Arc<dyn MediaStore>,
+    /// Key prefix, e.g. [`crate::storage::POST_PREFIX`].
+    prefix: &'static str,
+    files: Vec<(String, SavedFile)>,
+    /// Keys actually written to the store.
+    stored: Vec<String>,
+    kept: bool,
+}
+
+impl Uploads {
+    pub fn new(store: Arc<dyn MediaStore>, prefix: &'static str) -> Self {
+        Uploads { store, prefix, files: Vec::new(), stored: Vec::new(), kept: false }
+    }
+
+    /// Streams `field` into the store after checking it against `rules`.
+    /// Images are stripped of their metadata first.
+    pub async fn save(&mut self, field: &mut Field, rules: FileRules) -> Result<&SavedFile, ApiError> {
+        let name = field.name().to_string();
+        self.receive(field, rules).await?;
+        let saved = &mut self.files.last_mut().expect("just received").1;
+
+        if saved.format.kind() == MediaKind::Image {
+            let (path, format) = (saved.path.clone(), saved.format);
+            let (stripped, bytes) = web::block(move || images::strip(&path, format))
+                .await?
+                .map_err(|e| e.for_field(&name, MediaKind::Image))?;
+            tokio::fs::write(&saved.path, &bytes).await?;
+
+            if stripped != saved.format {
+                let stem = saved.key.strip_suffix(saved.format.extension()).unwrap_or(&saved.key);
+                saved.key = format!("{}{}", stem, stripped.extension());
+                saved.format = stripped;
+            }
+            saved.size = bytes.len() as u64;
+            saved.checksum = hex::encode(Sha256::digest(&bytes));
+        }
+
+        self.store.put_file(&saved.key, &saved.path, saved.format.mime()).await?;
+        self.stored.push(saved.key.clone());
+
+        println!("   ✅ File saved: {} ({} bytes)", saved.key, saved.size);
+        Ok(saved)
+    }
+
+    /// Checks `field` against `rules` like [`Uploads::save`], but only keeps
+    /// the temp file, for callers that store something derived from it.
+    pub async fn receive(&mut self, field: &mut Field, rules: FileRules) -> Result<&SavedFile, ApiError> {
+        let name = field.name().to_string();
+        let original = field.content_disposition().get_filename().map(str::to_string);
+
+        if self.files.len() >= rules.max_files {
+            return Err(ApiError::BadRequest(format!("At most {} file(s) allowed", rules.max_files)));
+        }
+
+        let unsupported = || {
+            ApiError::UnsupportedMedia(format!("'{}' must be a {}", name, rules.label()))
+        };
+
+        if let Some(ext) = original.as_deref().and_then(|f| Path::new(f).extension()).and_then(|e| e.to_str()) {
+            let ext = ext.to_ascii_lowercase();
+            if !KINDS.iter().any(|k| rules.accepts(*k) && k.extensions().contains(&ext.as_str())) {
+                return Err(unsupported());
+            }
+        }
+
+        // Buffer until the format is known; nothing touches the disk before.
+        let mut head = Vec::with_capacity(SNIFF_LEN);
+        while head.len() < SNIFF_LEN {
+            match field.try_next().await? {
+                Some(chunk) => head.extend_from_slice(&chunk),
+                None => break,
+            }
+        }
+
+        let format = sniff(&head).filter(|f| rules.accepts(f.kind())).ok_or_else(unsupported)?;
+        let max_bytes = rules.max_bytes(format.kind()).unwrap_or_default();
+        let too_large = || ApiError::PayloadTooLarge(format!("'{}' exceeds {} bytes", name, max_bytes));
+        if head.len() as u64 > max_bytes {
+            return Err(too_large());
+        }
+
+        let key = format!("{}{}", self.prefix, stored_name(original.as_deref(), format));
+        let path = std::env::temp_dir().join(format!("upload-{}", Uuid::new_v4()));
+        let mut file = tokio::fs::File::create(&path).await?;
+
+        // Registered before writing so a failure below still removes it.
+        self.files.push((name.clone(), SavedFile { key, path, format, size: 0, checksum: String::new(), video: None, image: None }));
+        let saved = &mut self.files.last_mut().expect("just pushed").1;
+
+        let mut hasher = Sha256::new();
+        file.write_all(&head).await?;
+        hasher.update(&head);
+        saved.size = head.len() as u64;
+
+        while let Some(chunk) = field.try_next().await? {
+            saved.size += chunk.len() as u64;
+            if saved.size > max_bytes {
+                return Err(too_large());
+            }
+            file.write_all(&chunk).await?;
+            hasher.update(&chunk);
+        }
+        file.flush().await?;
+        saved.checksum = hex::encode(hasher.finalize());
+
+        // A file that doesn't parse won't display either; reject it before
+        // it reaches the store.
+        let rejected = |e: ProbeError| e.for_field(&name, format.kind());
+        let path = saved.path.clone();
+        match format.kind() {
+            MediaKind::Video => {
+                saved.video = Some(web::block(move || probe::probe_video(&path, format)).await?.map_err(rejected)?);
+            }
+            MediaKind::Image => {
+                saved.image = Some(web::block(move || probe::probe_image(&path, format)).await?.map_err(rejected)?);
+            }
+        }
+
+        Ok(saved)
+    }
+
+    /// Stores generated content under `key`, removed again with the rest of
+    /// the batch unless it is kept.
+    pub async fn put_bytes(&mut self, key: String, bytes: &[u8], content_type: &str) -> Result<(), ApiError> {
+        let path = std::env::temp_dir().join(format!("upload-{}", Uuid::new_v4()));
+        tokio::fs::write(&path, bytes).await?;
+        let stored = self.store.put_file(&key, &path, content_type).await;
+        if let Err(e) = tokio::fs::remove_file(&path).await {
+            eprintln!("❌ Failed to remove temp upload {}: {}", path.display(), e);
+        }
+        stored?;
+        self.stored.push(key.clone());
+
+        println!("   ✅ File saved: {} ({} bytes)", key, bytes.len());
+        Ok(())
+    }
+
+    /// Every saved file, in upload order, whichever field it came in.
+    pub fn all(&self) -> Vec<&SavedFile> {
+        self.files.iter().map(|(_, f)| f).collect()
+    }
+
+    /// Keeps the stored objects; call once the request has succeeded.
+    pub fn keep(mut self) {
+        self.kept = true;
+    }
+}
+
+impl Drop for Uploads {
+    fn drop(&mut self) {
+        for (_, file) in &self.files {
+            match std::fs::remove_file(&file.path) {
+                Ok(()) => {}
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+                Err(e) => eprintln!("❌ Failed to remove temp upload {}: {}", file.path.display(), e),
+            }
+        }
+
+        if self.kept || self.stored.is_empty() {
+            return;
+        }
+        let store = self.store.clone();
+        let keys = std::mem::take(&mut self.stored);
+        actix_web::rt::spawn(async move {
+            for key in keys {
+                match store.delete(&key).await {
+                    Ok(()) => println!("🧹 Removed rejected upload {}", key),
+                    Err(e) => eprintln!("❌ Failed to remove rejected upload {}: {}", key, e),
+                }
+            }
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// `head` padded with zeros to [`SNIFF_LEN`], as a full read would give.
+    fn padded(head: &[u8]) -> Vec<u8> {
+        let mut bytes = head.to_vec();
+        bytes.resize(SNIFF_LEN.max(head.len()), 0);
+        bytes
+    }
+
+    #[test]
+    fn recognises_every_signature() {
+        let cases: &[(&[u8], Format)] = &[
+            (b"\xFF\xD8\xFF\xE0", Format::Jpeg),
+            (b"\xFF\xD8\xFF\xE1", Format::Jpeg),
+            (b"\x89PNG\r\n\x1a\n", Format::Png),
+            (b"RIFF\x10\0\0\0WEBP", Format::Webp),
+            (b"\x1A\x45\xDF\xA3", Format::Webm),
+            (b"\0\0\0\x20ftypisom", Format::Mp4),
+            (b"\0\0\0\x1cftypmp42", Format::Mp4),
+            (b"\0\0\0\x18ftypM4V ", Format::Mp4),
+            (b"\0\0\0\x14ftypqt  ", Format::Mov),
+            (b"\0\0\0\x08moov", Format::Mov),
+            (b"\0\0\0\x08mdat", Format::Mov),
+            (b"\0\0\0\x08wide", Format::Mov),
+            (b"\0\0\0\x08free", Format::Mov),
+        ];
+        for (head, format) in cases {
+            assert_eq!(sniff(&padded(head)), Some(*format), "sniffing {:?}", head);
+        }
+    }
+
+    #[test]
+    fn near_misses_are_not_recognised() {
+        let cases: &[&[u8]] = &[
+            b"\xFF\xD8\xFE\xE0",
+            b"\xFF\xD9\xFF\xE0",
+            b"\x89PNG\r\n\x1a\x0b",
+            b"\x88PNG\r\n\x1a\n",
+            b"RIFF\x10\0\0\0WAVE",
+            b"RIFX\x10\0\0\0WEBP",
+            b"\x1A\x45\xDF\xA2",
+            b"\0\0\0ftypisom",
+            b"\0\0\0\x20FTYPisom",
+            b"\0\0\0\x08skip",
+            b"GIF89a",
+            b"<svg xmlns=",
+            b"%PDF-1.7",
+        ];
+        for head in cases {
+            assert_eq!(sniff(&padded(head)), None, "sniffing {:?}", head);
+        }
+    }
+
+    #[test]
+    fn short_inputs_are_handled() {
+        assert_eq!(sniff(b""), None);
+        assert_eq!(sniff(b"\xFF"), None);
+        assert_eq!(sniff(b"\xFF\xD8"), None);
+        assert_eq!(sniff(b"\xFF\xD8\xFF"), Some(Format::Jpeg));
+        assert_eq!(sniff(b"\x89PNG\r\n\x1a"), None);
+        assert_eq!(sniff(b"RIFF\0\0\0\0WEB"), None);
+        assert_eq!(sniff(b"\x1A\x45\xDF"), None);
+        assert_eq!(sniff(b"\0\0\0\x08fty"), None);
+        // The brand is cut off; without `qt  ` it's MP4.
+        assert_eq!(sniff(b"\0\0\0\x14ftyp"), Some(Format::Mp4));
+        assert_eq!(sniff(b"\0\0\0\x14ftypqt"), Some(Format::Mp4));
+    }
+}
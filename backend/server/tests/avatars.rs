@@ -0,0 +1,62 @@
+//! Profile pictures uploaded at registration: the URLs the API hands out and
+//! the variants behind them.
+
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::{test, App};
+use diesel::prelude::*;
+use serde_json::Value;
+use uuid::Uuid;
+
+use common::{multipart, png, TestApp};
+use server::schema::{follows, users};
+
+#[actix_web::test]
+async fn profile_pictures_come_back_as_media_urls() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+    let viewer = t.user("public");
+
+    let email = format!("{}@example.com", Uuid::new_v4().simple());
+    let image = png();
+    let (content_type, body) = multipart(&[
+        ("name", None, b"Avatar Owner"),
+        ("email", None, email.as_bytes()),
+        ("password", None, b"correct horse battery"),
+        ("address", None, b"1 Test Street"),
+        ("phoneno", None, b"5550100"),
+        ("profile_pic", Some("me.png"), image.as_slice()),
+    ]);
+    let req = test::TestRequest::post()
+        .uri("/api/user/register")
+        .insert_header(("Content-Type", content_type))
+        .set_payload(body)
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let mut conn = t.pool().get().unwrap();
+    let owner: Uuid = users::table.filter(users::email.eq(&email)).select(users::id).first(&mut conn).unwrap();
+    diesel::insert_into(follows::table)
+        .values((follows::user_id.eq(owner), follows::target_id.eq(viewer.id), follows::status.eq("accepted")))
+        .execute(&mut conn)
+        .unwrap();
+
+    let get = |uri: String| test::TestRequest::get().uri(&uri).insert_header(viewer.auth()).to_request();
+    let profile: Value =
+        test::call_and_read_body_json(&app, get(format!("/api/user/auth/profile/{}?avatar_size=512", owner))).await;
+    let followers: Value =
+        test::call_and_read_body_json(&app, get(format!("/api/user/auth/followers/{}?avatar_size=64", viewer.id))).await;
+
+    for (url, size) in [(&profile["profile_pic"], 512), (&followers["items"][0]["profile_pic"], 64)] {
+        let url = url.as_str().unwrap();
+        assert!(url.starts_with("/media/userprofile/avatars/"), "{}", url);
+        assert!(url.ends_with(&format!("/{}.jpg", size)), "{}", url);
+
+        let res = test::call_service(&app, test::TestRequest::get().uri(url).to_request()).await;
+        assert_eq!(res.status(), StatusCode::OK, "{}", url);
+        // The upload is 4×3: every variant is its 3×3 centre, never upscaled.
+        let variant = image::load_from_memory(&test::read_body(res).await).unwrap();
+        assert_eq!((variant.width(), variant.height()), (3, 3), "{}", url);
+    }
+}
@@ -0,0 +1,108 @@
+//! Comment listings and edits respect blocks.
+
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::{test, App};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::{multipart, png, TestApp, TestUser};
+
+fn create_post(user: &TestUser) -> test::TestRequest {
+    let image = png();
+    let (content_type, body) = multipart(&[("description", None, b"a post"), ("media", Some("pic.png"), image.as_slice())]);
+
+    test::TestRequest::post()
+        .uri("/api/user/auth/posts")
+        .insert_header(user.auth())
+        .insert_header(("Content-Type", content_type))
+        .set_payload(body)
+}
+
+fn comment(user: &TestUser, post_id: Uuid, parent_id: Option<Uuid>) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri(&format!("/api/user/auth/posts/{}/comments", post_id))
+        .insert_header(user.auth())
+        .set_json(json!({ "body": "a comment", "parent_id": parent_id }))
+}
+
+fn block(user: &TestUser, target: Uuid) -> test::TestRequest {
+    test::TestRequest::post().uri(&format!("/api/user/auth/block/{}", target)).insert_header(user.auth())
+}
+
+fn list(user: &TestUser, uri: String) -> test::TestRequest {
+    test::TestRequest::get().uri(&uri).insert_header(user.auth())
+}
+
+fn id_of(body: &Value) -> Uuid {
+    body["id"].as_str().unwrap().parse().unwrap()
+}
+
+/// `(author, reply_count)` of each listed comment.
+fn listed(page: &Value) -> Vec<(Uuid, i64)> {
+    page["items"]
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|c| (c["user_id"].as_str().unwrap().parse().unwrap(), c["reply_count"].as_i64().unwrap()))
+        .collect()
+}
+
+#[actix_web::test]
+async fn comments_by_blocked_users_are_hidden() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+    let (author, viewer, friend, blocked) = (t.user("public"), t.user("public"), t.user("public"), t.user("public"));
+
+    let post: Value = test::call_and_read_body_json(&app, create_post(&author).to_request()).await;
+    let post_id = id_of(&post["post"]);
+    let top: Value = test::call_and_read_body_json(&app, comment(&friend, post_id, None).to_request()).await;
+    let top_id = id_of(&top);
+    for req in [comment(&blocked, post_id, None), comment(&blocked, post_id, Some(top_id))] {
+        assert_eq!(test::call_service(&app, req.to_request()).await.status(), StatusCode::CREATED);
+    }
+
+    assert_eq!(test::call_service(&app, block(&viewer, blocked.id).to_request()).await.status(), StatusCode::OK);
+
+    let post_uri = format!("/api/user/auth/posts/{}", post_id);
+    let comments = format!("/api/user/auth/posts/{}/comments", post_id);
+    let replies = format!("/api/user/auth/comments/{}/replies", top_id);
+
+    let page: Value = test::call_and_read_body_json(&app, list(&viewer, comments.clone()).to_request()).await;
+    assert_eq!(listed(&page), [(friend.id, 0)]);
+    let post: Value = test::call_and_read_body_json(&app, list(&viewer, post_uri.clone()).to_request()).await;
+    assert_eq!(post["comment_count"], 1, "the count matches the listing");
+    let page: Value = test::call_and_read_body_json(&app, list(&viewer, replies.clone()).to_request()).await;
+    assert_eq!(listed(&page), []);
+
+    // Everyone else still sees them.
+    let page: Value = test::call_and_read_body_json(&app, list(&author, comments).to_request()).await;
+    assert_eq!(listed(&page), [(blocked.id, 0), (friend.id, 1)]);
+    let post: Value = test::call_and_read_body_json(&app, list(&author, post_uri).to_request()).await;
+    assert_eq!(post["comment_count"], 3);
+    let page: Value = test::call_and_read_body_json(&app, list(&author, replies).to_request()).await;
+    assert_eq!(listed(&page), [(blocked.id, 0)]);
+}
+
+#[actix_web::test]
+async fn comments_cant_be_edited_once_the_post_is_out_of_reach() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+    let (author, commenter) = (t.user("public"), t.user("public"));
+
+    let post: Value = test::call_and_read_body_json(&app, create_post(&author).to_request()).await;
+    let written: Value = test::call_and_read_body_json(&app, comment(&commenter, id_of(&post["post"]), None).to_request()).await;
+
+    let edit = || {
+        test::TestRequest::patch()
+            .uri(&format!("/api/user/auth/comments/{}", id_of(&written)))
+            .insert_header(commenter.auth())
+            .set_json(json!({ "body": "edited" }))
+            .to_request()
+    };
+    assert_eq!(test::call_service(&app, edit()).await.status(), StatusCode::OK);
+
+    assert_eq!(test::call_service(&app, block(&author, commenter.id).to_request()).await.status(), StatusCode::OK);
+    assert_eq!(test::call_service(&app, edit()).await.status(), StatusCode::NOT_FOUND);
+}
@@ -0,0 +1,178 @@
+//! Setup shared by the integration tests.
+//!
+//! They run against a real Postgres database named by `TEST_DATABASE_URL`,
+//! migrated on first use. Each test creates its own users, so tests can run
+//! in parallel against the same database. Without the variable they print a
+//! note and return early.
+
+// Each test binary uses a different part of this module.
+#![allow(dead_code)]
+
+use std::sync::{Arc, Once};
+
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::http::StatusCode;
+use actix_web::{test, web};
+use diesel::prelude::*;
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use uuid::Uuid;
+
+use server::config::Config;
+use server::db::{self, DbPool};
+use server::mailer::{Mailer, MemoryMailer};
+use server::models::user::{NewUser, User};
+use server::schema::users;
+use server::storage::{LocalStore, MediaStore};
+use server::tokens::{self, ClientMeta};
+use server::AppState;
+
+const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
+static MIGRATE: Once = Once::new();
+
+pub struct TestApp {
+    pub state: AppState,
+    /// Everything the app "sent".
+    pub mailer: Arc<MemoryMailer>,
+}
+
+pub struct TestUser {
+    pub id: Uuid,
+    pub email: String,
+    pub handle: String,
+    pub token: String,
+    pub refresh_token: String,
+    pub session_id: Uuid,
+}
+
+impl TestUser {
+    /// `Authorization` header for requests made as this user.
+    pub fn auth(&self) -> (&'static str, String) {
+        ("Authorization", format!("Bearer {}", self.token))
+    }
+}
+
+impl TestApp {
+    /// `None` when `TEST_DATABASE_URL` isn't set; the test should return.
+    pub fn new() -> Option<Self> {
+        let Ok(url) = std::env::var("TEST_DATABASE_URL") else {
+            eprintln!("skipping: TEST_DATABASE_URL is not set");
+            return None;
+        };
+
+        let mut config = Config::default();
+        config.database.url = url;
+        config.database.pool_size = 4;
+        config.auth.jwt_secret = "integration-test-secret".to_string();
+        config.storage.local_root = std::env::temp_dir()
+            .join(format!("server-tests-{}", Uuid::new_v4()))
+            .to_string_lossy()
+            .into_owned();
+        config.jobs.workers = 0;
+
+        let pool = db::connection(&config.database);
+        MIGRATE.call_once(|| {
+            let mut conn = pool.get().expect("connect to TEST_DATABASE_URL");
+            conn.run_pending_migrations(MIGRATIONS).expect("apply migrations");
+        });
+
+        let mailer = Arc::new(MemoryMailer::new());
+        let store: Arc<dyn MediaStore> = Arc::new(LocalStore::new(&config.storage.local_root).expect("create local store"));
+        let state = AppState {
+            pool,
+            config: web::Data::new(config),
+            mailer: web::Data::from(mailer.clone() as Arc<dyn Mailer>),
+            store: web::Data::from(store),
+        };
+
+        Some(TestApp { state, mailer })
+    }
+
+    pub fn pool(&self) -> &DbPool {
+        &self.state.pool
+    }
+
+    /// A new user with a signed-in session. `account_type` is `public` or
+    /// `private`.
+    pub fn user(&self, account_type: &str) -> TestUser {
+        let conn = &mut self.pool().get().expect("connection");
+        let email = format!("{}@test.local", Uuid::new_v4());
+        let handle = format!("user_{}", &email[..8]);
+
+        let user: User = diesel::insert_into(users::table)
+            .values(&NewUser {
+                name: handle.clone(),
+                email: email.clone(),
+                // Tests sign in through sessions; the hash is never checked.
+                password: "!".to_string(),
+                address: "Test street".to_string(),
+                phoneno: "1234567".to_string(),
+                account_type: account_type.to_string(),
+                profile_pic: None,
+                handle,
+            })
+            .get_result(conn)
+            .expect("insert user");
+
+        self.start_session(conn, &user)
+    }
+
+    /// The same user signed in again, on another device.
+    pub fn sign_in(&self, user: &TestUser) -> TestUser {
+        let conn = &mut self.pool().get().expect("connection");
+        let user: User = users::table.find(user.id).first(conn).expect("load user");
+        self.start_session(conn, &user)
+    }
+
+    fn start_session(&self, conn: &mut PgConnection, user: &User) -> TestUser {
+        let pair = tokens::start_session(conn, &self.state.config.auth, user, ClientMeta { user_agent: None, ip_address: None })
+            .expect("start session");
+
+        TestUser {
+            id: user.id,
+            email: user.email.clone(),
+            handle: user.handle.clone(),
+            token: pair.token,
+            refresh_token: pair.refresh_token,
+            session_id: pair.session_id,
+        }
+    }
+}
+
+/// Status of the response to `req`. Unlike `test::call_service`, this also
+/// covers requests the auth middleware turns away with an error.
+pub async fn status<S, R, B>(app: &S, req: R) -> StatusCode
+where
+    S: Service<R, Response = ServiceResponse<B>, Error = actix_web::Error>,
+{
+    match test::try_call_service(app, req).await {
+        Ok(res) => res.status(),
+        Err(e) => e.as_response_error().status_code(),
+    }
+}
+
+/// A `multipart/form-data` body of `(name, filename, content)` parts, and
+/// its `Content-Type`.
+pub fn multipart(parts: &[(&str, Option<&str>, &[u8])]) -> (String, Vec<u8>) {
+    let boundary = format!("test-{}", Uuid::new_v4().simple());
+    let mut body = Vec::new();
+    for (name, filename, content) in parts {
+        body.extend_from_slice(format!("--{}\r\nContent-Disposition: form-data; name=\"{}\"", boundary, name).as_bytes());
+        if let Some(filename) = filename {
+            body.extend_from_slice(format!("; filename=\"{}\"", filename).as_bytes());
+        }
+        body.extend_from_slice(b"\r\n\r\n");
+        body.extend_from_slice(content);
+        body.extend_from_slice(b"\r\n");
+    }
+    body.extend_from_slice(format!("--{}--\r\n", boundary).as_bytes());
+    (format!("multipart/form-data; boundary={}", boundary), body)
+}
+
+/// A small PNG, for uploads.
+pub fn png() -> Vec<u8> {
+    let mut bytes = std::io::Cursor::new(Vec::new());
+    image::RgbImage::from_pixel(4, 3, image::Rgb([10, 200, 30]))
+        .write_to(&mut bytes, image::ImageFormat::Png)
+        .expect("encode png");
+    bytes.into_inner()
+}
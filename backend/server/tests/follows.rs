@@ -0,0 +1,196 @@
+//! Follow, unfollow and profile actions are bound to the token's user: ids
+//! naming anyone else are refused, whatever the request says.
+
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::{test, App};
+use diesel::prelude::*;
+use serde_json::json;
+use uuid::Uuid;
+
+use common::TestApp;
+use server::schema::{follows, users};
+
+fn follow_status(app: &TestApp, follower: Uuid, target: Uuid) -> Option<String> {
+    follows::table
+        .filter(follows::user_id.eq(follower))
+        .filter(follows::target_id.eq(target))
+        .select(follows::status)
+        .first(&mut app.pool().get().unwrap())
+        .optional()
+        .unwrap()
+}
+
+fn insert_follow(app: &TestApp, follower: Uuid, target: Uuid, status: &str) -> Uuid {
+    diesel::insert_into(follows::table)
+        .values((follows::user_id.eq(follower), follows::target_id.eq(target), follows::status.eq(status)))
+        .returning(follows::id)
+        .get_result(&mut app.pool().get().unwrap())
+        .unwrap()
+}
+
+#[actix_web::test]
+async fn follow_as_another_user_is_forbidden() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+    let (me, victim, target) = (t.user("public"), t.user("public"), t.user("public"));
+
+    let req = test::TestRequest::post()
+        .uri("/api/user/auth/follow")
+        .insert_header(me.auth())
+        .set_json(json!({ "targetId": target.id, "action": "follow", "userId": victim.id }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    assert_eq!(follow_status(&t, victim.id, target.id), None);
+    assert_eq!(follow_status(&t, me.id, target.id), None);
+}
+
+#[actix_web::test]
+async fn follow_is_recorded_for_the_token_user() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+    let (me, target) = (t.user("public"), t.user("public"));
+
+    let req = test::TestRequest::post()
+        .uri("/api/user/auth/follow")
+        .insert_header(me.auth())
+        .set_json(json!({ "targetId": target.id, "action": "follow", "userId": me.id }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(follow_status(&t, me.id, target.id).as_deref(), Some("accepted"));
+}
+
+#[actix_web::test]
+async fn unfollow_as_another_user_is_forbidden() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+    let (me, victim, target) = (t.user("public"), t.user("public"), t.user("public"));
+    insert_follow(&t, victim.id, target.id, "accepted");
+
+    let req = test::TestRequest::post()
+        .uri("/api/user/auth/follow")
+        .insert_header(me.auth())
+        .set_json(json!({ "targetId": target.id, "action": "unfollow", "userId": victim.id }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    assert_eq!(follow_status(&t, victim.id, target.id).as_deref(), Some("accepted"));
+}
+
+#[actix_web::test]
+async fn follow_across_a_block_is_forbidden() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+    let (me, blocker) = (t.user("public"), t.user("public"));
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/user/auth/block/{}", me.id))
+        .insert_header(blocker.auth())
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    let req = test::TestRequest::post()
+        .uri("/api/user/auth/follow")
+        .insert_header(me.auth())
+        .set_json(json!({ "targetId": blocker.id, "action": "follow" }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    assert_eq!(follow_status(&t, me.id, blocker.id), None);
+}
+
+#[actix_web::test]
+async fn profile_update_of_another_user_is_forbidden() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+    let (me, victim) = (t.user("public"), t.user("public"));
+
+    let req = test::TestRequest::put()
+        .uri(&format!("/api/user/auth/profile-update/{}", victim.id))
+        .insert_header(me.auth())
+        .set_json(json!({ "username": "hijacked", "loggedInUserId": victim.id }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    let name: String = users::table
+        .find(victim.id)
+        .select(users::name)
+        .first(&mut t.pool().get().unwrap())
+        .unwrap();
+    assert_ne!(name, "hijacked");
+}
+
+#[actix_web::test]
+async fn handling_a_request_addressed_to_someone_else_is_forbidden() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+    let (me, owner, requester) = (t.user("public"), t.user("private"), t.user("public"));
+    let request_id = insert_follow(&t, requester.id, owner.id, "pending");
+
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/user/auth/handle-follow-req/{}", request_id))
+        .insert_header(me.auth())
+        .set_json(json!({ "action": "approve" }))
+        .to_request();
+    let res = test::call_service(&app, req).await;
+
+    assert_eq!(res.status(), StatusCode::FORBIDDEN);
+    assert_eq!(follow_status(&t, requester.id, owner.id).as_deref(), Some("pending"));
+
+    // The owner can.
+    let req = test::TestRequest::post()
+        .uri(&format!("/api/user/auth/handle-follow-req/{}", request_id))
+        .insert_header(owner.auth())
+        .set_json(json!({ "action": "approve" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    assert_eq!(follow_status(&t, requester.id, owner.id).as_deref(), Some("accepted"));
+}
+
+#[actix_web::test]
+async fn listing_another_users_follows_is_forbidden() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+    let (me, other) = (t.user("public"), t.user("public"));
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/user/auth/request/{}", other.id))
+        .insert_header(me.auth())
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/user/auth/request/{}", me.id))
+        .insert_header(me.auth())
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn listing_another_users_follow_requests_is_forbidden() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+    let (me, owner, requester) = (t.user("public"), t.user("private"), t.user("public"));
+    insert_follow(&t, requester.id, owner.id, "pending");
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/user/auth/follow-req/{}", owner.id))
+        .insert_header(me.auth())
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::FORBIDDEN);
+
+    let req = test::TestRequest::get()
+        .uri(&format!("/api/user/auth/follow-req/{}", owner.id))
+        .insert_header(owner.auth())
+        .to_request();
+    let page: serde_json::Value = test::call_and_read_body_json(&app, req).await;
+    assert_eq!(page["items"][0]["requester_id"], requester.id.to_string());
+}
@@ -0,0 +1,130 @@
+//! The job queue: claiming, retry with backoff, dead-lettering and lease
+//! expiry.
+//!
+//! Each test works inside a transaction that is never committed, so its jobs
+//! stay invisible to the other tests sharing the database. Jobs are queued
+//! to run at the Unix epoch, which puts them ahead of anything the other
+//! tests left pending.
+
+mod common;
+
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use common::TestApp;
+use server::config::JobsConfig;
+use server::jobs::{self, worker, Job};
+use server::models::job::JobRow;
+use server::schema::jobs as jobs_table;
+
+fn settings() -> JobsConfig {
+    JobsConfig { lease_secs: 600, backoff_base_secs: 10, backoff_max_secs: 15, ..JobsConfig::default() }
+}
+
+/// A connection of its own, inside a transaction rolled back when dropped.
+fn connection() -> Option<PgConnection> {
+    TestApp::new()?;
+    let mut conn = PgConnection::establish(&std::env::var("TEST_DATABASE_URL").unwrap()).unwrap();
+    conn.begin_test_transaction().unwrap();
+    Some(conn)
+}
+
+fn epoch() -> NaiveDateTime {
+    DateTime::UNIX_EPOCH.naive_utc()
+}
+
+fn enqueue(conn: &mut PgConnection, max_attempts: i32) -> i64 {
+    let job = Job::RemovePostFiles { post_id: Uuid::new_v4(), media: vec![] };
+    jobs::enqueue_at(conn, &job, epoch(), max_attempts).unwrap()
+}
+
+fn load(conn: &mut PgConnection, id: i64) -> JobRow {
+    jobs_table::table.find(id).select(JobRow::as_select()).first(conn).unwrap()
+}
+
+fn claim(conn: &mut PgConnection) -> Option<i64> {
+    worker::claim_next(conn, &settings()).unwrap().map(|job| job.id)
+}
+
+/// Records a failed run of the claimed job `id`.
+fn fail(conn: &mut PgConnection, id: i64, err: &str) {
+    let job = load(conn, id);
+    worker::mark_failed(conn, &job, err, &settings()).unwrap();
+}
+
+/// Makes a rescheduled job runnable again, ahead of everything else.
+fn make_due(conn: &mut PgConnection, id: i64) {
+    diesel::update(jobs_table::table.find(id)).set(jobs_table::run_at.eq(epoch())).execute(conn).unwrap();
+}
+
+fn assert_about(actual: NaiveDateTime, expected: NaiveDateTime) {
+    assert!((actual - expected).num_seconds().abs() <= 2, "{} is not close to {}", actual, expected);
+}
+
+#[test]
+fn claiming_marks_the_job_running() {
+    let Some(conn) = &mut connection() else { return };
+    let id = enqueue(conn, 3);
+
+    assert_eq!(claim(conn), Some(id));
+    let job = load(conn, id);
+    assert_eq!((job.status.as_str(), job.attempts), ("running", 1));
+    assert_about(job.locked_at.unwrap(), Utc::now().naive_utc());
+
+    // A leased job isn't handed out twice.
+    assert_ne!(claim(conn), Some(id));
+
+    worker::mark_done(conn, id).unwrap();
+    let job = load(conn, id);
+    assert_eq!((job.status.as_str(), job.locked_at), ("done", None));
+}
+
+#[test]
+fn failures_back_off_then_dead_letter() {
+    let Some(conn) = &mut connection() else { return };
+    let id = enqueue(conn, 3);
+
+    // Waits double from the base and are capped at the maximum.
+    for (attempt, wait) in [(1, 10), (2, 15)] {
+        assert_eq!(claim(conn), Some(id));
+        fail(conn, id, "boom");
+
+        let job = load(conn, id);
+        assert_eq!((job.status.as_str(), job.attempts, job.locked_at), ("pending", attempt, None));
+        assert_eq!(job.last_error.as_deref(), Some("boom"));
+        assert_about(job.run_at, Utc::now().naive_utc() + Duration::seconds(wait));
+
+        // Not runnable until the wait is over.
+        assert_ne!(claim(conn), Some(id));
+        make_due(conn, id);
+    }
+
+    assert_eq!(claim(conn), Some(id));
+    fail(conn, id, "still broken");
+
+    let job = load(conn, id);
+    assert_eq!((job.status.as_str(), job.attempts), ("dead", 3));
+    assert_eq!(job.last_error.as_deref(), Some("still broken"));
+    make_due(conn, id);
+    assert_ne!(claim(conn), Some(id));
+}
+
+#[test]
+fn expired_leases_are_reclaimed() {
+    let Some(conn) = &mut connection() else { return };
+    let id = enqueue(conn, 3);
+    assert_eq!(claim(conn), Some(id));
+
+    // The worker holding it died; the lease runs out.
+    let expired = Utc::now().naive_utc() - Duration::seconds(settings().lease_secs + 1);
+    diesel::update(jobs_table::table.find(id))
+        .set(jobs_table::locked_at.eq(Some(expired)))
+        .execute(conn)
+        .unwrap();
+
+    assert_eq!(claim(conn), Some(id));
+    let job = load(conn, id);
+    assert_eq!((job.status.as_str(), job.attempts), ("running", 2));
+    assert_about(job.locked_at.unwrap(), Utc::now().naive_utc());
+}
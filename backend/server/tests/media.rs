@@ -0,0 +1,156 @@
+//! `/media/{key}` against the local store: ranges, conditional requests,
+//! HEAD and signed URLs.
+
+mod common;
+
+use actix_web::dev::ServiceResponse;
+use actix_web::http::header;
+use actix_web::http::{Method, StatusCode};
+use actix_web::{test, App};
+use uuid::Uuid;
+
+use common::{status, TestApp};
+use server::storage;
+use server::tokens;
+
+/// 1000 bytes that differ from one offset to the next.
+fn content() -> Vec<u8> {
+    (0..1000u32).map(|i| (i % 251) as u8).collect()
+}
+
+/// Stores [`content`] under a new key made by `key_for` and returns the key.
+async fn put(t: &TestApp, key_for: fn(&str) -> String) -> String {
+    let key = key_for(&format!("{}_file.mp4", Uuid::new_v4()));
+    let path = std::env::temp_dir().join(format!("media-test-{}", Uuid::new_v4()));
+    std::fs::write(&path, content()).unwrap();
+    t.state.store.put_file(&key, &path, "video/mp4").await.unwrap();
+    std::fs::remove_file(&path).unwrap();
+    key
+}
+
+fn get(uri: &str) -> test::TestRequest {
+    test::TestRequest::get().uri(uri)
+}
+
+/// The response header `name`, or `""`.
+fn value(res: &ServiceResponse, name: header::HeaderName) -> &str {
+    res.headers().get(name).and_then(|v| v.to_str().ok()).unwrap_or_default()
+}
+
+#[actix_web::test]
+async fn full_and_partial_reads() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+    // Profile pictures need no access check.
+    let uri = format!("/media/{}", put(&t, storage::profile_key).await);
+
+    let res = test::call_service(&app, get(&uri).to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(value(&res, header::CONTENT_TYPE), "video/mp4");
+    assert_eq!(value(&res, header::ACCEPT_RANGES), "bytes");
+    assert!(!value(&res, header::ETAG).is_empty());
+    assert!(!value(&res, header::LAST_MODIFIED).is_empty());
+    assert_eq!(test::read_body(res).await, content());
+
+    let res = test::call_service(&app, get(&uri).insert_header((header::RANGE, "bytes=10-19")).to_request()).await;
+    assert_eq!(res.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(value(&res, header::CONTENT_RANGE), "bytes 10-19/1000");
+    assert_eq!(test::read_body(res).await, content()[10..20]);
+
+    let res = test::call_service(&app, get(&uri).insert_header((header::RANGE, "bytes=-5")).to_request()).await;
+    assert_eq!(value(&res, header::CONTENT_RANGE), "bytes 995-999/1000");
+    assert_eq!(test::read_body(res).await, content()[995..]);
+
+    let res = test::call_service(&app, get(&uri).insert_header((header::RANGE, "bytes=5000-")).to_request()).await;
+    assert_eq!(res.status(), StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(value(&res, header::CONTENT_RANGE), "bytes */1000");
+}
+
+#[actix_web::test]
+async fn head_sends_headers_only() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+    let uri = format!("/media/{}", put(&t, storage::profile_key).await);
+
+    let res = test::call_service(&app, test::TestRequest::with_uri(&uri).method(Method::HEAD).to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(value(&res, header::CONTENT_LENGTH), "1000");
+    assert!(!value(&res, header::ETAG).is_empty());
+    assert!(test::read_body(res).await.is_empty());
+}
+
+#[actix_web::test]
+async fn conditional_requests() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+    let uri = format!("/media/{}", put(&t, storage::profile_key).await);
+
+    let res = test::call_service(&app, get(&uri).to_request()).await;
+    let (etag, modified) = (value(&res, header::ETAG).to_string(), value(&res, header::LAST_MODIFIED).to_string());
+
+    let cached = |name: header::HeaderName, value: &str| get(&uri).insert_header((name, value.to_string())).to_request();
+
+    let res = test::call_service(&app, cached(header::IF_NONE_MATCH, &etag)).await;
+    assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+    assert_eq!(value(&res, header::ETAG), etag);
+    assert!(test::read_body(res).await.is_empty());
+
+    let res = test::call_service(&app, cached(header::IF_MODIFIED_SINCE, &modified)).await;
+    assert_eq!(res.status(), StatusCode::NOT_MODIFIED);
+
+    let res = test::call_service(&app, cached(header::IF_NONE_MATCH, "\"something-else\"")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    // If-None-Match wins over If-Modified-Since.
+    let req = get(&uri)
+        .insert_header((header::IF_NONE_MATCH, "\"something-else\""))
+        .insert_header((header::IF_MODIFIED_SINCE, modified.clone()))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    // A range is only resumed while the object is still the one the client has.
+    let resume = |if_range: &str| {
+        get(&uri)
+            .insert_header((header::RANGE, "bytes=10-19"))
+            .insert_header((header::IF_RANGE, if_range.to_string()))
+            .to_request()
+    };
+    assert_eq!(test::call_service(&app, resume(&etag)).await.status(), StatusCode::PARTIAL_CONTENT);
+    assert_eq!(test::call_service(&app, resume(&modified)).await.status(), StatusCode::PARTIAL_CONTENT);
+
+    let res = test::call_service(&app, resume("\"something-else\"")).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(test::read_body(res).await, content());
+}
+
+#[actix_web::test]
+async fn signed_urls() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+    // No post owns this key, so only a signature can open it.
+    let key = put(&t, storage::post_key).await;
+    let auth = &t.state.config.auth;
+
+    assert_eq!(status(&app, get(&format!("/media/{}", key)).to_request()).await, StatusCode::NOT_FOUND);
+
+    let signed = tokens::signed_media_url(auth, &key);
+    let res = test::call_service(&app, get(&signed).to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+    assert_eq!(test::read_body(res).await, content());
+
+    // Any change to the signature, the expiry or the key breaks it.
+    let (rest, signature) = signed.rsplit_once('=').unwrap();
+    let flipped = if signature.starts_with('0') { "1" } else { "0" };
+    let tampered = format!("{}={}{}", rest, flipped, &signature[1..]);
+    let other_key = signed.replace(&key, &put(&t, storage::post_key).await);
+    let expires: i64 = signed.split("expires=").nth(1).unwrap().split('&').next().unwrap().parse().unwrap();
+    let extended = signed.replace(&format!("expires={}", expires), &format!("expires={}", expires + 60));
+
+    let mut expired_auth = auth.clone();
+    expired_auth.media_url_ttl_minutes = -1;
+    let expired = tokens::signed_media_url(&expired_auth, &key);
+
+    for uri in [tampered, other_key, extended, expired, format!("/media/{}?expires={}&signature=zz", key, expires)] {
+        assert_eq!(status(&app, get(&uri).to_request()).await, StatusCode::FORBIDDEN, "{}", uri);
+    }
+}
@@ -0,0 +1,97 @@
+//! The password reset email, from request to the rendered message.
+
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::{test, App};
+use diesel::prelude::*;
+use serde_json::json;
+
+use common::TestApp;
+use server::jobs::{self, Job, JobContext};
+use server::schema::{jobs as jobs_table, password_reset_tokens};
+
+/// Runs the queued `send_email` jobs addressed to `to`, as a worker would.
+async fn deliver(t: &TestApp, to: &str) {
+    let conn = &mut t.pool().get().unwrap();
+    let payloads: Vec<serde_json::Value> = jobs_table::table
+        .filter(jobs_table::kind.eq("send_email"))
+        .filter(jobs_table::status.eq("pending"))
+        .select(jobs_table::payload)
+        .load(conn)
+        .unwrap();
+
+    let ctx = JobContext {
+        pool: t.pool().clone(),
+        mailer: t.state.mailer.clone().into_inner(),
+        store: t.state.store.clone().into_inner(),
+        config: t.state.config.clone().into_inner(),
+    };
+    for payload in payloads.into_iter().filter(|p| p["to"] == to) {
+        let job: Job = serde_json::from_value(payload).unwrap();
+        jobs::handlers::run(&ctx, &job).await.expect("send email");
+    }
+}
+
+#[actix_web::test]
+async fn forgot_password_sends_a_working_reset_link() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+    let user = t.user("public");
+
+    let req = test::TestRequest::post()
+        .uri("/api/user/forgot-password")
+        .set_json(json!({ "email": user.email }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+
+    deliver(&t, &user.email).await;
+    let sent: Vec<_> = t.mailer.sent().into_iter().filter(|e| e.to == user.email).collect();
+    assert_eq!(sent.len(), 1, "expected one email to {}", user.email);
+    let email = &sent[0];
+
+    let token: String = password_reset_tokens::table
+        .filter(password_reset_tokens::user_id.eq(user.id))
+        .select(password_reset_tokens::token)
+        .first(&mut t.pool().get().unwrap())
+        .unwrap();
+    let link = format!("{}/reset-password?token={}", t.state.config.frontend_url, token);
+
+    assert_eq!(email.subject, "Password Reset Request");
+    assert!(email.text_body.contains(&link), "text body: {}", email.text_body);
+    assert!(email.html_body.contains(&link), "html body: {}", email.html_body);
+    assert!(email.text_body.contains("60 minutes"), "text body: {}", email.text_body);
+    assert!(!email.text_body.contains("{{"), "unrendered placeholder in: {}", email.text_body);
+
+    // The link's token sets a new password once.
+    let reset = || {
+        test::TestRequest::post()
+            .uri("/api/user/reset-password")
+            .set_json(json!({ "token": token, "new_password": "a-new-password" }))
+            .to_request()
+    };
+    assert_eq!(test::call_service(&app, reset()).await.status(), StatusCode::OK);
+    assert_eq!(test::call_service(&app, reset()).await.status(), StatusCode::BAD_REQUEST);
+
+    let req = test::TestRequest::post()
+        .uri("/api/user/login")
+        .set_json(json!({ "email": user.email, "password": "a-new-password" }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn unknown_email_sends_nothing() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+
+    let to = format!("{}@test.local", uuid::Uuid::new_v4());
+    let req = test::TestRequest::post()
+        .uri("/api/user/forgot-password")
+        .set_json(json!({ "email": to }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+
+    deliver(&t, &to).await;
+    assert!(t.mailer.sent().is_empty());
+}
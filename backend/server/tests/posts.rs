@@ -0,0 +1,100 @@
+//! Creating posts and editing their media.
+
+mod common;
+
+use actix_web::dev::ServiceResponse;
+use actix_web::http::StatusCode;
+use actix_web::{test, App};
+use diesel::prelude::*;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::{multipart, png, TestApp, TestUser};
+use server::schema::post_media;
+
+/// Creates a post with `images` PNG attachments.
+fn create_post(user: &TestUser, images: usize) -> test::TestRequest {
+    let image = png();
+    let mut parts: Vec<(&str, Option<&str>, &[u8])> = vec![("description", None, b"a post")];
+    parts.extend((0..images).map(|_| ("media", Some("pic.png"), image.as_slice())));
+    let (content_type, body) = multipart(&parts);
+
+    test::TestRequest::post()
+        .uri("/api/user/auth/posts")
+        .insert_header(user.auth())
+        .insert_header(("Content-Type", content_type))
+        .set_payload(body)
+}
+
+/// Id and media keys of the post a [`create_post`] request made.
+async fn created(resp: ServiceResponse) -> (Uuid, Vec<String>) {
+    assert_eq!(resp.status(), StatusCode::CREATED);
+    let body: Value = test::read_body_json(resp).await;
+    let post = &body["post"];
+    let keys = post["media"].as_array().unwrap().iter().map(|m| m["key"].as_str().unwrap().to_string()).collect();
+    (post["id"].as_str().unwrap().parse().unwrap(), keys)
+}
+
+fn stored_keys(t: &TestApp, post_id: Uuid) -> Vec<String> {
+    post_media::table
+        .filter(post_media::post_id.eq(post_id))
+        .order(post_media::position)
+        .select(post_media::storage_key)
+        .load(&mut t.pool().get().unwrap())
+        .unwrap()
+}
+
+#[actix_web::test]
+async fn removing_every_attachment_is_rejected() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+    let author = t.user("public");
+    let (post_id, keys) = created(test::call_service(&app, create_post(&author, 2).to_request()).await).await;
+
+    for body in [json!({ "media": [] }), json!({ "media": [], "description": "new text" })] {
+        let req = test::TestRequest::patch()
+            .uri(&format!("/api/user/auth/posts/{}", post_id))
+            .insert_header(author.auth())
+            .set_json(body)
+            .to_request();
+        assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+    }
+    assert_eq!(stored_keys(&t, post_id), keys);
+}
+
+#[actix_web::test]
+async fn media_left_out_are_removed_and_the_rest_reordered() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+    let author = t.user("public");
+    let (post_id, keys) = created(test::call_service(&app, create_post(&author, 3).to_request()).await).await;
+
+    let req = test::TestRequest::patch()
+        .uri(&format!("/api/user/auth/posts/{}", post_id))
+        .insert_header(author.auth())
+        .set_json(json!({ "media": [keys[2], keys[0]] }))
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::OK);
+    assert_eq!(stored_keys(&t, post_id), [keys[2].clone(), keys[0].clone()]);
+}
+
+#[actix_web::test]
+async fn repeated_description_is_rejected() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+    let author = t.user("public");
+
+    let image = png();
+    let (content_type, body) = multipart(&[
+        ("description", None, b"first"),
+        ("media", Some("pic.png"), &image),
+        ("description", None, b"second"),
+    ]);
+    let req = test::TestRequest::post()
+        .uri("/api/user/auth/posts")
+        .insert_header(author.auth())
+        .insert_header(("Content-Type", content_type))
+        .set_payload(body)
+        .to_request();
+    assert_eq!(test::call_service(&app, req).await.status(), StatusCode::BAD_REQUEST);
+}
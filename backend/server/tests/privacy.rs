@@ -0,0 +1,202 @@
+//! A private account's profile details, posts, follower lists and media are
+//! visible to its owner and accepted followers only. Blocked users don't see
+//! the account at all.
+
+mod common;
+
+use actix_http::Request;
+use actix_web::dev::{Service, ServiceResponse};
+use actix_web::http::StatusCode;
+use actix_web::{test, App};
+use diesel::prelude::*;
+use serde_json::Value;
+use uuid::Uuid;
+
+use common::{multipart, png, status, TestApp, TestUser};
+use server::schema::{blocks, follows};
+
+fn follow(t: &TestApp, follower: &TestUser, target: &TestUser, follow_status: &str) {
+    diesel::insert_into(follows::table)
+        .values((follows::user_id.eq(follower.id), follows::target_id.eq(target.id), follows::status.eq(follow_status)))
+        .execute(&mut t.pool().get().unwrap())
+        .unwrap();
+}
+
+fn block(t: &TestApp, blocker: &TestUser, blocked: &TestUser) {
+    diesel::insert_into(blocks::table)
+        .values((blocks::blocker_id.eq(blocker.id), blocks::blocked_id.eq(blocked.id)))
+        .execute(&mut t.pool().get().unwrap())
+        .unwrap();
+}
+
+fn get(user: &TestUser, uri: &str) -> test::TestRequest {
+    test::TestRequest::get().uri(uri).insert_header(user.auth())
+}
+
+fn create_post(user: &TestUser) -> test::TestRequest {
+    let image = png();
+    let (content_type, body) = multipart(&[("description", None, b"a post"), ("media", Some("pic.png"), image.as_slice())]);
+
+    test::TestRequest::post()
+        .uri("/api/user/auth/posts")
+        .insert_header(user.auth())
+        .insert_header(("Content-Type", content_type))
+        .set_payload(body)
+}
+
+/// A private account with one post, and everyone who might look at it.
+struct Cast {
+    owner: TestUser,
+    follower: TestUser,
+    /// Asked to follow, not yet accepted.
+    pending: TestUser,
+    stranger: TestUser,
+    /// Blocked by the owner.
+    blocked: TestUser,
+    /// Blocked the owner.
+    blocker: TestUser,
+    post_id: Uuid,
+    media_key: String,
+}
+
+impl Cast {
+    /// Viewers paired with whether they get full access.
+    fn viewers(&self) -> [(&'static str, &TestUser, bool); 6] {
+        [
+            ("owner", &self.owner, true),
+            ("follower", &self.follower, true),
+            ("pending", &self.pending, false),
+            ("stranger", &self.stranger, false),
+            ("blocked", &self.blocked, false),
+            ("blocker", &self.blocker, false),
+        ]
+    }
+
+    fn is_blocked(&self, viewer: &TestUser) -> bool {
+        viewer.id == self.blocked.id || viewer.id == self.blocker.id
+    }
+}
+
+/// Sets up a [`Cast`] around a new private account.
+async fn cast(t: &TestApp, app: &impl Service<Request, Response = ServiceResponse, Error = actix_web::Error>) -> Cast {
+    let owner = t.user("private");
+    let (follower, pending, stranger, blocked, blocker) =
+        (t.user("public"), t.user("public"), t.user("public"), t.user("public"), t.user("public"));
+    follow(t, &follower, &owner, "accepted");
+    follow(t, &pending, &owner, "pending");
+    block(t, &owner, &blocked);
+    block(t, &blocker, &owner);
+
+    let created: Value = test::call_and_read_body_json(app, create_post(&owner).to_request()).await;
+    let post = &created["post"];
+    Cast {
+        post_id: post["id"].as_str().unwrap().parse().unwrap(),
+        media_key: post["media"][0]["key"].as_str().unwrap().to_string(),
+        owner,
+        follower,
+        pending,
+        stranger,
+        blocked,
+        blocker,
+    }
+}
+
+#[actix_web::test]
+async fn profile_details_need_full_access() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+    let cast = cast(&t, &app).await;
+    let uri = format!("/api/user/auth/profile/{}", cast.owner.id);
+
+    for (who, viewer, full) in cast.viewers() {
+        if cast.is_blocked(viewer) {
+            assert_eq!(status(&app, get(viewer, &uri).to_request()).await, StatusCode::NOT_FOUND, "{}", who);
+            continue;
+        }
+        let profile: Value = test::call_and_read_body_json(&app, get(viewer, &uri).to_request()).await;
+        assert_eq!(profile["canViewContent"], full, "{}", who);
+        assert_eq!(profile["email"].is_string(), full, "{}", who);
+        assert_eq!(profile["phoneNo"].is_string(), full, "{}", who);
+        // Name and counts are shown either way.
+        assert_eq!(profile["PostsCount"], 1, "{}", who);
+    }
+}
+
+#[actix_web::test]
+async fn posts_and_follower_lists_need_full_access() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+    let cast = cast(&t, &app).await;
+
+    for uri in [
+        format!("/api/user/auth/users/{}/posts", cast.owner.id),
+        format!("/api/user/auth/followers/{}", cast.owner.id),
+        format!("/api/user/auth/followings/{}", cast.owner.id),
+    ] {
+        for (who, viewer, full) in cast.viewers() {
+            let expected = match (full, cast.is_blocked(viewer)) {
+                (true, _) => StatusCode::OK,
+                (false, true) => StatusCode::NOT_FOUND,
+                (false, false) => StatusCode::FORBIDDEN,
+            };
+            assert_eq!(status(&app, get(viewer, &uri).to_request()).await, expected, "{} on {}", who, uri);
+        }
+    }
+
+    let post = format!("/api/user/auth/posts/{}", cast.post_id);
+    for (who, viewer, full) in cast.viewers() {
+        assert_eq!(status(&app, get(viewer, &post).to_request()).await == StatusCode::OK, full, "{}", who);
+    }
+}
+
+#[actix_web::test]
+async fn listings_leave_out_posts_without_full_access() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+    let cast = cast(&t, &app).await;
+
+    for (who, viewer, full) in cast.viewers() {
+        let page: Value = test::call_and_read_body_json(&app, get(viewer, "/api/user/auth/getpost?limit=50").to_request()).await;
+        let listed = page["items"].as_array().unwrap().iter().any(|p| p["id"] == cast.post_id.to_string());
+        assert_eq!(listed, full, "{}", who);
+    }
+}
+
+#[actix_web::test]
+async fn media_needs_full_access() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+    let cast = cast(&t, &app).await;
+    let uri = format!("/media/{}", cast.media_key);
+
+    for (who, viewer, full) in cast.viewers() {
+        let expected = match (full, cast.is_blocked(viewer)) {
+            (true, _) => StatusCode::OK,
+            (false, true) => StatusCode::NOT_FOUND,
+            (false, false) => StatusCode::FORBIDDEN,
+        };
+        assert_eq!(status(&app, get(viewer, &uri).to_request()).await, expected, "{}", who);
+    }
+
+    let anonymous = test::TestRequest::get().uri(&uri).to_request();
+    assert_eq!(status(&app, anonymous).await, StatusCode::FORBIDDEN);
+}
+
+#[actix_web::test]
+async fn public_accounts_are_visible_to_strangers() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+    let (owner, stranger) = (t.user("public"), t.user("public"));
+    let created: Value = test::call_and_read_body_json(&app, create_post(&owner).to_request()).await;
+    let key = created["post"]["media"][0]["key"].as_str().unwrap();
+
+    for uri in [
+        format!("/api/user/auth/users/{}/posts", owner.id),
+        format!("/api/user/auth/followers/{}", owner.id),
+        format!("/media/{}", key),
+    ] {
+        assert_eq!(status(&app, get(&stranger, &uri).to_request()).await, StatusCode::OK, "{}", uri);
+    }
+    let anonymous = test::TestRequest::get().uri(&format!("/media/{}", key)).to_request();
+    assert_eq!(status(&app, anonymous).await, StatusCode::OK);
+}
@@ -0,0 +1,96 @@
+//! [`S3Store`] against a real S3-compatible service.
+//!
+//! Ignored by default. Start MinIO with a bucket, then run
+//!
+//! ```sh
+//! TEST_S3_ENDPOINT=http://127.0.0.1:9000 cargo test --test s3 -- --ignored
+//! ```
+//!
+//! `TEST_S3_BUCKET` defaults to `test`, `TEST_S3_ACCESS_KEY` and
+//! `TEST_S3_SECRET_KEY` to MinIO's `minioadmin`, and `TEST_S3_REGION` to
+//! `us-east-1`. Objects are written under a fresh prefix and deleted again.
+
+use std::time::Duration;
+
+use futures_util::TryStreamExt as _;
+use uuid::Uuid;
+
+use server::config::S3Config;
+use server::storage::{ByteRange, MediaObject, MediaStore, S3Store, StoreError};
+
+fn config() -> Option<S3Config> {
+    let Ok(endpoint) = std::env::var("TEST_S3_ENDPOINT") else {
+        eprintln!("skipping: TEST_S3_ENDPOINT is not set");
+        return None;
+    };
+    let var = |name: &str, default: &str| std::env::var(name).unwrap_or_else(|_| default.to_string());
+
+    Some(S3Config {
+        endpoint,
+        bucket: var("TEST_S3_BUCKET", "test"),
+        region: var("TEST_S3_REGION", "us-east-1"),
+        access_key: var("TEST_S3_ACCESS_KEY", "minioadmin"),
+        secret_key: var("TEST_S3_SECRET_KEY", "minioadmin"),
+        path_style: true,
+        presign_ttl_secs: 900,
+    })
+}
+
+async fn body(object: MediaObject) -> Vec<u8> {
+    object
+        .body
+        .try_fold(Vec::new(), |mut data, chunk| async move {
+            data.extend_from_slice(&chunk);
+            Ok(data)
+        })
+        .await
+        .expect("read body")
+}
+
+#[actix_web::test]
+#[ignore = "needs an S3-compatible service; see the module docs"]
+async fn put_get_range_stat_presign_and_delete() {
+    let Some(config) = config() else { return };
+    let store = S3Store::new(&config).expect("create store");
+
+    // Spaces and `+` check that keys are encoded the same way when signing
+    // and when sending.
+    let key = format!("server-tests/{}/a clip+1.mp4", Uuid::new_v4());
+    let content: Vec<u8> = (0..1000u32).map(|i| (i % 251) as u8).collect();
+    let path = std::env::temp_dir().join(format!("s3-test-{}", Uuid::new_v4()));
+    std::fs::write(&path, &content).unwrap();
+
+    store.put_file(&key, &path, "video/mp4").await.expect("put");
+    std::fs::remove_file(&path).unwrap();
+
+    let meta = store.stat(&key).await.expect("stat");
+    assert_eq!(meta.size, 1000);
+    assert_eq!(meta.content_type, "video/mp4");
+    assert!(meta.etag.is_some_and(|etag| !etag.is_empty() && !etag.starts_with('"')));
+
+    let whole = store.get(&key, None).await.expect("get");
+    assert_eq!((whole.len, whole.range), (1000, None));
+    assert_eq!(body(whole).await, content);
+
+    let part = store.get(&key, Some(ByteRange::From { start: 10, end: Some(19) })).await.expect("get range");
+    assert_eq!((part.len, part.range, part.meta.size), (10, Some((10, 19)), 1000));
+    assert_eq!(body(part).await, &content[10..20]);
+
+    let tail = store.get(&key, Some(ByteRange::Suffix(5))).await.expect("get suffix");
+    assert_eq!(tail.range, Some((995, 999)));
+    assert_eq!(body(tail).await, &content[995..]);
+
+    let past_end = store.get(&key, Some(ByteRange::From { start: 5000, end: None })).await;
+    assert!(matches!(past_end, Err(StoreError::RangeNotSatisfiable(1000))), "got {:?}", past_end.err());
+
+    let url = store.presigned_url(&key, Duration::from_secs(60)).await.expect("presign").expect("S3 presigns");
+    let resp = reqwest::get(&url).await.expect("fetch presigned URL");
+    assert_eq!(resp.status(), reqwest::StatusCode::OK);
+    assert_eq!(resp.bytes().await.unwrap(), content);
+
+    store.delete(&key).await.expect("delete");
+    assert!(matches!(store.stat(&key).await, Err(StoreError::NotFound)));
+    assert!(matches!(store.get(&key, None).await, Err(StoreError::NotFound)));
+    // Deleting again still succeeds.
+    store.delete(&key).await.expect("delete missing");
+}
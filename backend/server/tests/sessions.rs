@@ -0,0 +1,152 @@
+//! Refresh token rotation, reuse detection and session revocation.
+
+mod common;
+
+use actix_web::http::StatusCode;
+use actix_web::{test, App};
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use common::{status, TestApp, TestUser};
+use server::schema::sessions;
+
+fn refresh(refresh_token: &str) -> test::TestRequest {
+    test::TestRequest::post()
+        .uri("/api/user/refresh")
+        .set_json(json!({ "refresh_token": refresh_token }))
+}
+
+/// Any authenticated request; fails once the access token's session is gone.
+fn list_sessions(token: &str) -> test::TestRequest {
+    test::TestRequest::get()
+        .uri("/api/user/auth/sessions")
+        .insert_header(("Authorization", format!("Bearer {}", token)))
+}
+
+fn revoked_at(t: &TestApp, session_id: Uuid) -> Option<chrono::NaiveDateTime> {
+    sessions::table
+        .find(session_id)
+        .select(sessions::revoked_at)
+        .first(&mut t.pool().get().unwrap())
+        .unwrap()
+}
+
+fn post_as(user: &TestUser, uri: &str) -> test::TestRequest {
+    test::TestRequest::post().uri(uri).insert_header(user.auth())
+}
+
+#[actix_web::test]
+async fn refresh_rotates_the_token_pair() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+    let user = t.user("public");
+
+    let pair: Value = test::call_and_read_body_json(&app, refresh(&user.refresh_token).to_request()).await;
+    let (token, next) = (pair["token"].as_str().unwrap(), pair["refresh_token"].as_str().unwrap());
+    assert_eq!(pair["session_id"], json!(user.session_id));
+    assert_ne!(next, user.refresh_token);
+
+    assert_eq!(status(&app, list_sessions(token).to_request()).await, StatusCode::OK);
+    // The new refresh token rotates again.
+    assert_eq!(status(&app, refresh(next).to_request()).await, StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn reusing_a_retired_refresh_token_revokes_the_session() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+    let user = t.user("public");
+
+    let pair: Value = test::call_and_read_body_json(&app, refresh(&user.refresh_token).to_request()).await;
+    let (token, next) = (pair["token"].as_str().unwrap(), pair["refresh_token"].as_str().unwrap());
+
+    // The retired token comes back: someone else holds a copy.
+    let res = test::call_service(&app, refresh(&user.refresh_token).to_request()).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+    assert!(revoked_at(&t, user.session_id).is_some());
+
+    // Neither holder keeps the session.
+    assert_eq!(status(&app, refresh(next).to_request()).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(status(&app, list_sessions(token).to_request()).await, StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn unknown_refresh_tokens_are_refused() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+
+    let res = test::call_service(&app, refresh("not-a-token").to_request()).await;
+    assert_eq!(res.status(), StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn logout_locks_out_the_sessions_tokens() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+    let user = t.user("public");
+
+    let res = test::call_service(&app, post_as(&user, "/api/user/auth/logout").to_request()).await;
+    assert_eq!(res.status(), StatusCode::OK);
+
+    assert_eq!(status(&app, list_sessions(&user.token).to_request()).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(status(&app, refresh(&user.refresh_token).to_request()).await, StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn expired_sessions_lock_out_their_tokens() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+    let user = t.user("public");
+
+    diesel::update(sessions::table.find(user.session_id))
+        .set(sessions::expires_at.eq(Utc::now().naive_utc() - Duration::minutes(1)))
+        .execute(&mut t.pool().get().unwrap())
+        .unwrap();
+
+    assert_eq!(status(&app, list_sessions(&user.token).to_request()).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(status(&app, refresh(&user.refresh_token).to_request()).await, StatusCode::UNAUTHORIZED);
+}
+
+#[actix_web::test]
+async fn sessions_can_only_be_revoked_by_their_owner() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+    let (user, other) = (t.user("public"), t.user("public"));
+    let laptop = t.sign_in(&user);
+
+    let revoke = |caller: &TestUser, session_id: Uuid| {
+        test::TestRequest::delete()
+            .uri(&format!("/api/user/auth/sessions/{}", session_id))
+            .insert_header(caller.auth())
+            .to_request()
+    };
+
+    assert_eq!(status(&app, revoke(&other, laptop.session_id)).await, StatusCode::NOT_FOUND);
+    assert!(revoked_at(&t, laptop.session_id).is_none());
+
+    assert_eq!(status(&app, revoke(&user, laptop.session_id)).await, StatusCode::OK);
+    assert_eq!(status(&app, list_sessions(&laptop.token).to_request()).await, StatusCode::UNAUTHORIZED);
+    assert_eq!(status(&app, list_sessions(&user.token).to_request()).await, StatusCode::OK);
+}
+
+#[actix_web::test]
+async fn logout_all_ends_every_session() {
+    let Some(t) = TestApp::new() else { return };
+    let app = test::init_service(App::new().configure(|cfg| t.state.configure(cfg))).await;
+    let (user, bystander) = (t.user("public"), t.user("public"));
+    let laptop = t.sign_in(&user);
+
+    let sessions: Value = test::call_and_read_body_json(&app, list_sessions(&user.token).to_request()).await;
+    assert_eq!(sessions["sessions"].as_array().unwrap().len(), 2);
+
+    let res: Value = test::call_and_read_body_json(&app, post_as(&user, "/api/user/auth/logout-all").to_request()).await;
+    assert_eq!(res["revoked"], 2);
+
+    for session in [&user, &laptop] {
+        assert_eq!(status(&app, list_sessions(&session.token).to_request()).await, StatusCode::UNAUTHORIZED);
+        assert_eq!(status(&app, refresh(&session.refresh_token).to_request()).await, StatusCode::UNAUTHORIZED);
+    }
+    assert_eq!(status(&app, list_sessions(&bystander.token).to_request()).await, StatusCode::OK);
+}
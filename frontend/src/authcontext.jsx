@@ -1,7 +1,50 @@
 import React, { createContext, useState, useEffect } from "react";
+import axios from "axios";
 
 export const AuthContext = createContext();
 
+const API_USER = "http://127.0.0.1:8081/api/user";
+
+// Access tokens are short-lived: on a 401, trade the refresh token for a new
+// pair once and replay the request.
+let refreshing = null;
+
+axios.interceptors.response.use(
+  (res) => res,
+  async (error) => {
+    const original = error.config;
+    const refreshToken = localStorage.getItem("refresh_token");
+
+    if (
+      error.response?.status !== 401 ||
+      !refreshToken ||
+      original._retried ||
+      original.url?.endsWith("/refresh")
+    ) {
+      return Promise.reject(error);
+    }
+
+    original._retried = true;
+    try {
+      refreshing =
+        refreshing ||
+        axios.post(`${API_USER}/refresh`, { refresh_token: refreshToken }).finally(() => {
+          refreshing = null;
+        });
+      const { data } = await refreshing;
+      localStorage.setItem("token", data.token);
+      localStorage.setItem("refresh_token", data.refresh_token);
+      original.headers = { ...original.headers, Authorization: `Bearer ${data.token}` };
+      return axios(original);
+    } catch (refreshError) {
+      localStorage.removeItem("token");
+      localStorage.removeItem("refresh_token");
+      localStorage.removeItem("user");
+      return Promise.reject(error);
+    }
+  }
+);
+
 export const AuthProvider = ({ children }) => {
   const [token, setToken] = useState(null);
   const [user, setUser] = useState(null);
@@ -13,17 +56,27 @@ export const AuthProvider = ({ children }) => {
     if (savedUser) setUser(JSON.parse(savedUser));
   }, []);
 
-  const login = (token, user) => {
+  const login = (token, user, refreshToken) => {
     setToken(token);
     setUser(user);
     localStorage.setItem("token", token);
     localStorage.setItem("user", JSON.stringify(user));
+    if (refreshToken) localStorage.setItem("refresh_token", refreshToken);
   };
 
   const logout = () => {
+    const savedToken = localStorage.getItem("token");
+    if (savedToken) {
+      axios
+        .post(`${API_USER}/auth/logout`, null, {
+          headers: { Authorization: `Bearer ${savedToken}` },
+        })
+        .catch(() => {});
+    }
     setToken(null);
     setUser(null);
     localStorage.removeItem("token");
+    localStorage.removeItem("refresh_token");
     localStorage.removeItem("user");
   };
 
@@ -17,6 +17,7 @@ function Home() {
 
   const usersPerChunk = 6;
   const [offset, setOffset] = useState(0);
+  const [cursor, setCursor] = useState(null);
   const [hasMore, setHasMore] = useState(true);
   const initialLoadDone = useRef(false);
 
@@ -47,14 +48,15 @@ function Home() {
     try {
       currentOffset === 0 ? setLoading(true) : setLoadingMore(true);
       const token = localStorage.getItem("token");
-      const page = Math.floor(currentOffset / usersPerChunk) + 1;
+      const params = { limit: usersPerChunk };
+      if (currentOffset > 0 && cursor) params.cursor = cursor;
 
       const res = await axios.get(
-        `http://127.0.0.1:8081/api/user/auth/get-users?page=${page}&limit=${usersPerChunk}`,
-        { headers: { Authorization: `Bearer ${token}` } }
+        "http://127.0.0.1:8081/api/user/auth/get-users",
+        { params, headers: { Authorization: `Bearer ${token}` } }
       );
 
-      const fetchedUsers = res.data.users || [];
+      const fetchedUsers = res.data.items || [];
 
       setUsers((prev) => {
         const combined = [...prev, ...fetchedUsers];
@@ -62,7 +64,8 @@ function Home() {
       });
 
       setOffset((prev) => prev + fetchedUsers.length);
-      if (fetchedUsers.length < usersPerChunk) setHasMore(false);
+      setCursor(res.data.next_cursor);
+      if (!res.data.next_cursor) setHasMore(false);
 
       if (currentOffset === 0) {
         const followRes = await axios.get(
@@ -88,15 +91,14 @@ function Home() {
     setButtonLoading((prev) => ({ ...prev, [targetUser.id]: true }));
     try {
       const token = localStorage.getItem("token");
-      let action = "follow";
-      let isRequest = false;
-
-      if (following.includes(targetUser.id)) action = "unfollow";
-      else if (targetUser.account_type === "private") isRequest = true;
+      const action =
+        following.includes(targetUser.id) || pendingRequests.includes(targetUser.id)
+          ? "unfollow"
+          : "follow";
 
       const res = await axios.post(
         "http://127.0.0.1:8081/api/user/auth/follow",
-        { userId: user.id, targetId: targetUser.id, action, isRequest },
+        { targetId: targetUser.id, action },
         { headers: { Authorization: `Bearer ${token}` } }
       );
 
@@ -104,7 +106,7 @@ function Home() {
         if (action === "unfollow") {
           setFollowing((prev) => prev.filter((id) => id !== targetUser.id));
           setPendingRequests((prev) => prev.filter((id) => id !== targetUser.id));
-        } else if (isRequest) {
+        } else if (res.data.status === "pending") {
           setPendingRequests((prev) => [...prev, targetUser.id]);
         } else {
           setFollowing((prev) => [...prev, targetUser.id]);
@@ -114,7 +116,7 @@ function Home() {
       }
     } catch (err) {
       console.error("Follow/unfollow error:", err);
-      alert("Something went wrong");
+      alert(err.response?.data?.message || "Something went wrong");
     } finally {
       setButtonLoading((prev) => ({ ...prev, [targetUser.id]: false }));
     }
@@ -198,7 +200,7 @@ function Home() {
                 <img
                   src={
                     u.profile_pic
-                      ? `http://127.0.0.1:8081/profile_pic/${u.profile_pic}`
+                      ? `http://127.0.0.1:8081/media/${u.profile_pic}`
                       : "/default-profile.png"
                   }
                   alt={u.name}
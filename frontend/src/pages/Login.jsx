@@ -27,7 +27,7 @@ function Login() {
         return;
       }
 
-      login(data.token, data.user);
+      login(data.token, data.user, data.refresh_token);
       toaster.success(" Login successful!");
       setTimeout(() => {
           navigate("/home");
@@ -5,6 +5,12 @@ import "react-quill/dist/quill.snow.css";
 import axios from "axios";
 import {toaster} from "../Globaltoaster.jsx";
 
+// ✅ Videos and photos share one carousel, in the order they're picked
+const ACCEPTED_TYPES = [
+  "video/mp4", "video/webm", "video/quicktime",
+  "image/jpeg", "image/png", "image/webp",
+];
+
 export default function VideoInput() {
   const navigate = useNavigate();
   const [uploads, setUploads] = useState([]);
@@ -19,13 +25,13 @@ export default function VideoInput() {
 
     const MAX_SIZE_MB = 500; // 500MB limit per file
     const invalidFiles = files.filter(
-      (file) => file.type !== "video/mp4" || file.size > MAX_SIZE_MB * 1024 * 1024
+      (file) => !ACCEPTED_TYPES.includes(file.type) || file.size > MAX_SIZE_MB * 1024 * 1024
     );
 
     if (invalidFiles.length > 0) {
       const invalidNames = invalidFiles.map((f) => f.name).join(", ");
       toaster.error(
-        ` Only MP4 files under ${MAX_SIZE_MB}MB are allowed. Invalid: ${invalidNames}`
+        ` Only videos and photos under ${MAX_SIZE_MB}MB are allowed. Invalid: ${invalidNames}`
       );
       return;
     }
@@ -34,6 +40,7 @@ export default function VideoInput() {
     const newUploads = files.map((file) => ({
       id: URL.createObjectURL(file),
       name: file.name,
+      isImage: file.type.startsWith("image/"),
       progress: 0,
       uploaded: false,
     }));
@@ -79,12 +86,12 @@ export default function VideoInput() {
     }
 
     if (fileList.length === 0) {
-      toaster.error(" Please choose at least one MP4 video file.");
+      toaster.error(" Please choose at least one video or photo.");
       return;
     }
 
     const formData = new FormData();
-    fileList.forEach((file) => formData.append("videos", file));
+    fileList.forEach((file) => formData.append("media", file));
     formData.append("description", description);
 
     try {
@@ -148,12 +155,12 @@ export default function VideoInput() {
 
         {/* 📹 File Upload */}
         <div className="mb-3">
-          <h4>Upload MP4 Videos (max 500 MB each)</h4>
+          <h4>Upload Videos and Photos (max 500 MB each)</h4>
           <input
             className="form-control"
             type="file"
             multiple
-            accept=".mp4"
+            accept={ACCEPTED_TYPES.join(",")}
             onChange={handleFileChange}
           />
         </div>
@@ -166,11 +173,15 @@ export default function VideoInput() {
                 <div className="card-body">
                   {upload.uploaded ? (
                     <>
-                      <video
-                        className="w-100 mb-2 rounded"
-                        controls
-                        src={upload.id}
-                      />
+                      {upload.isImage ? (
+                        <img className="w-100 mb-2 rounded" src={upload.id} alt={upload.name} />
+                      ) : (
+                        <video
+                          className="w-100 mb-2 rounded"
+                          controls
+                          src={upload.id}
+                        />
+                      )}
                       <p className="text-success text-center mb-0">✅ Upload complete!</p>
                     </>
                   ) : (
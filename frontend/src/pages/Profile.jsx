@@ -28,15 +28,20 @@ function Profile() {
 
   const [pendingRequests, setPendingRequests] = useState([]);
   const [followersList, setFollowersList] = useState([]);
-  const [followersPage, setFollowersPage] = useState(1);
+  const [followersCursor, setFollowersCursor] = useState(null);
   const [hasMoreFollowers, setHasMoreFollowers] = useState(true);
   const [loadingFollowers, setLoadingFollowers] = useState(false);
 
   const [followingList, setFollowingList] = useState([]);
-  const [followingPage, setFollowingPage] = useState(1);
+  const [followingCursor, setFollowingCursor] = useState(null);
   const [hasMoreFollowing, setHasMoreFollowing] = useState(true);
   const [loadingFollowing, setLoadingFollowing] = useState(false);
 
+  const [postsList, setPostsList] = useState([]);
+  const [postsCursor, setPostsCursor] = useState(null);
+  const [postsCount, setPostsCount] = useState(0);
+  const [loadingPosts, setLoadingPosts] = useState(false);
+
   const itemsPerPage = 6;
   const [showFollowers, setShowFollowers] = useState(false);
   const [showFollowing, setShowFollowing] = useState(false);
@@ -53,6 +58,7 @@ function Profile() {
         const url = `${API_BASE}/profile/${profileId}`;
 
         const res = await axios.get(url, {
+          params: { avatar_size: 512 },
           headers: { Authorization: `Bearer ${token}` },
         });
 
@@ -60,6 +66,7 @@ function Profile() {
         setProfile(profileData);
         setFollowersCount(profileData.FollowersCount || 0);
         setFollowingCount(profileData.FollowingCount || 0);
+        setPostsCount(profileData.PostsCount || 0);
 
         setFormData({
           username: profileData.username || "",
@@ -73,9 +80,9 @@ function Profile() {
         if (isOwner && profileData.accountType?.toLowerCase() === "private") {
           const requestsRes = await axios.get(
             `${API_BASE}/follow-req/${profileData.id}`,
-            { headers: { Authorization: `Bearer ${token}` } }
+            { params: { limit: 50 }, headers: { Authorization: `Bearer ${token}` } }
           );
-          setPendingRequests(requestsRes.data.pendingRequests || []);
+          setPendingRequests(requestsRes.data.items || []);
         }
       } catch (err) {
         console.error("Error fetching profile:", err);
@@ -87,20 +94,49 @@ function Profile() {
     fetchProfile();
   }, [id, token, isOwner, user?.id]);
 
+  // ✅ Posts
+  const fetchPosts = async (cursor = null) => {
+    if (!profile?.id || !profile.canViewContent) return;
+    setLoadingPosts(true);
+    try {
+      const res = await axios.get(`${API_BASE}/users/${profile.id}/posts`, {
+        params: cursor ? { cursor, limit: itemsPerPage } : { limit: itemsPerPage },
+        headers: { Authorization: `Bearer ${token}` },
+      });
+      const data = res.data.items || [];
+      setPostsList((prev) => (cursor ? [...prev, ...data] : data));
+      setPostsCursor(res.data.next_cursor);
+      setPostsCount(res.data.total ?? 0);
+    } catch (err) {
+      console.error("Error fetching posts:", err);
+    } finally {
+      setLoadingPosts(false);
+    }
+  };
+
+  useEffect(() => {
+    fetchPosts();
+  }, [profile?.id, profile?.canViewContent]);
+
   // ✅ Followers
-  const fetchFollowers = async (page = 1) => {
+  const fetchFollowers = async (cursor = null) => {
     if (!profile?.id) return;
     setLoadingFollowers(true);
     try {
       const res = await axios.get(
-        `${API_BASE}/followers/${profile.id}?page=${page}&limit=${itemsPerPage}`,
-        { headers: { Authorization: `Bearer ${token}` } }
+        `${API_BASE}/followers/${profile.id}`,
+        {
+          params: cursor
+            ? { cursor, limit: itemsPerPage, avatar_size: 64 }
+            : { limit: itemsPerPage, avatar_size: 64 },
+          headers: { Authorization: `Bearer ${token}` },
+        }
       );
 
-      const data = res.data.followers || [];
-      setFollowersList((prev) => (page === 1 ? data : [...prev, ...data]));
-      setHasMoreFollowers(data.length === itemsPerPage);
-      setFollowersPage(page);
+      const data = res.data.items || [];
+      setFollowersList((prev) => (cursor ? [...prev, ...data] : data));
+      setHasMoreFollowers(Boolean(res.data.next_cursor));
+      setFollowersCursor(res.data.next_cursor);
     } catch (err) {
       console.error("Error fetching followers:", err);
     } finally {
@@ -109,19 +145,24 @@ function Profile() {
   };
 
   // ✅ Following
-  const fetchFollowing = async (page = 1) => {
+  const fetchFollowing = async (cursor = null) => {
     if (!profile?.id) return;
     setLoadingFollowing(true);
     try {
       const res = await axios.get(
-        `${API_BASE}/followings/${profile.id}?page=${page}&limit=${itemsPerPage}`,
-        { headers: { Authorization: `Bearer ${token}` } }
+        `${API_BASE}/followings/${profile.id}`,
+        {
+          params: cursor
+            ? { cursor, limit: itemsPerPage, avatar_size: 64 }
+            : { limit: itemsPerPage, avatar_size: 64 },
+          headers: { Authorization: `Bearer ${token}` },
+        }
       );
       console.log("Following data:", res.data);
-      const data = res.data.following || [];
-      setFollowingList((prev) => (page === 1 ? data : [...prev, ...data]));
-      setHasMoreFollowing(data.length === itemsPerPage);
-      setFollowingPage(page);
+      const data = res.data.items || [];
+      setFollowingList((prev) => (cursor ? [...prev, ...data] : data));
+      setHasMoreFollowing(Boolean(res.data.next_cursor));
+      setFollowingCursor(res.data.next_cursor);
     } catch (err) {
       console.error("Error fetching following:", err);
     } finally {
@@ -152,7 +193,6 @@ function Profile() {
           accountType: formData.accountType,
           phoneNo: formattedPhone,
           address: formData.address,
-          loggedInUserId: user.id,
         },
         { headers: { Authorization: `Bearer ${token}` } }
       );
@@ -172,7 +212,7 @@ function Profile() {
     try {
       await axios.post(
         `${API_BASE}/handle-follow-req/${requestId}`,
-        { ownerId: user.id, action },
+        { action },
         { headers: { Authorization: `Bearer ${token}` } }
       );
       setPendingRequests((prev) => prev.filter((r) => r.id !== requestId));
@@ -197,21 +237,11 @@ function Profile() {
     }
 
     try {
-      console.log("Follow API called ->", {
-        userId: user.id,
-        targetId: targetUserId,
-        action,
-        isRequest: false,
-      });
+      console.log("Follow API called ->", { targetId: targetUserId, action });
 
       const res = await axios.post(
         `${API_BASE}/follow`,
-        {
-          userId: user.id,        
-          targetId: targetUserId,  
-          action,                  
-          isRequest: false,        
-        },
+        { targetId: targetUserId, action },
         {
           headers: {
             Authorization: `Bearer ${token}`,
@@ -294,7 +324,7 @@ function Profile() {
                     <img
                       src={
                         profile.profile_pic
-                          ? `http://127.0.0.1:8081/profile_pic/${profile.profile_pic}`
+                          ? `http://127.0.0.1:8081/media/${profile.profile_pic}`
                           : "/default-profile.png"
                       }
                       alt={profile.username}
@@ -326,11 +356,15 @@ function Profile() {
                     </div>
 
                     <div className="d-flex gap-4 mb-3">
+                      <div className="text-center">
+                        <div className="fs-4 fw-bold text-dark">{postsCount}</div>
+                        <small className="text-muted">Posts</small>
+                      </div>
                       <div
                         className="text-center cursor-pointer"
                         style={{ cursor: "pointer" }}
                         onClick={() => {
-                          fetchFollowers(1);
+                          fetchFollowers();
                           setShowFollowers(true);
                           setShowFollowing(false);
                         }}
@@ -342,7 +376,7 @@ function Profile() {
                         className="text-center cursor-pointer"
                         style={{ cursor: "pointer" }}
                         onClick={() => {
-                          fetchFollowing(1);
+                          fetchFollowing();
                           setShowFollowing(true);
                           setShowFollowers(false);
                         }}
@@ -493,6 +527,65 @@ function Profile() {
               </div>
             </div>
 
+            {/* Posts Grid */}
+            <div className="card border-0 shadow-sm mb-4">
+              <div className="card-header bg-white">
+                <h5 className="mb-0">
+                  <i className="bi bi-camera-video me-2"></i>Posts
+                </h5>
+              </div>
+              <div className="card-body">
+                {!profile.canViewContent ? (
+                  <div className="text-center py-4 text-muted">
+                    <i className="bi bi-lock-fill fs-1 d-block mb-2"></i>
+                    This account is private
+                  </div>
+                ) : postsList.length === 0 && !loadingPosts ? (
+                  <div className="text-center py-4 text-muted">
+                    <i className="bi bi-camera-video-off fs-1 d-block mb-2"></i>
+                    No posts yet
+                  </div>
+                ) : (
+                  <>
+                    <div className="row g-2">
+                      {postsList.map((post) => (
+                        <div key={post.id} className="col-4">
+                          <div className="ratio ratio-1x1 bg-dark rounded overflow-hidden">
+                            {post.media?.[0]?.kind === "image" && (
+                              <img
+                                src={`http://127.0.0.1:8081${post.media[0].url}`}
+                                className="w-100 h-100"
+                                style={{ objectFit: "cover" }}
+                                alt=""
+                              />
+                            )}
+                            {post.media?.[0]?.kind === "video" && (
+                              <video
+                                src={`http://127.0.0.1:8081${post.media[0].url}`}
+                                className="w-100 h-100"
+                                style={{ objectFit: "cover" }}
+                                muted
+                                controls
+                              />
+                            )}
+                          </div>
+                        </div>
+                      ))}
+                    </div>
+                    {postsCursor && (
+                      <button
+                        className="btn btn-outline-secondary w-100 mt-3"
+                        onClick={() => fetchPosts(postsCursor)}
+                        disabled={loadingPosts}
+                      >
+                        <i className="bi bi-arrow-down-circle me-1"></i>Load More
+                      </button>
+                    )}
+                  </>
+                )}
+              </div>
+            </div>
+
             {/* Followers List */}
             {showFollowers && (
               <div className="card border-0 shadow-sm mb-4">
@@ -524,7 +617,7 @@ function Profile() {
                                 <img
                                   src={
                                     f.profile_pic
-                                      ? `http://127.0.0.1:8081/profile_pic/${f.profile_pic}`
+                                      ? `http://127.0.0.1:8081/media/${f.profile_pic}`
                                       : "/default-profile.png"
                                   }
                                   alt={f.username}
@@ -544,7 +637,7 @@ function Profile() {
                       {hasMoreFollowers && (
                         <button
                           className="btn btn-outline-primary w-100 mt-3"
-                          onClick={() => fetchFollowers(followersPage + 1)}
+                          onClick={() => fetchFollowers(followersCursor)}
                           disabled={loadingFollowers}
                         >
                           <i className="bi bi-arrow-down-circle me-1"></i>Load More
@@ -597,7 +690,7 @@ function Profile() {
                                   <img
                                     src={
                                       f.profile_pic
-                                        ? `http://127.0.0.1:8081/profile_pic/${f.profile_pic}`
+                                        ? `http://127.0.0.1:8081/media/${f.profile_pic}`
                                         : "/default-profile.png"
                                     }
                                     alt={f.username || f.name}
@@ -634,7 +727,7 @@ function Profile() {
                       {hasMoreFollowing && (
                         <button
                           className="btn btn-outline-success w-100 mt-3"
-                          onClick={() => fetchFollowing(followingPage + 1)}
+                          onClick={() => fetchFollowing(followingCursor)}
                           disabled={loadingFollowing}
                         >
                           <i className="bi bi-arrow-down-circle me-1"></i>Load More
@@ -674,7 +767,7 @@ function Profile() {
                             <img
                               src={
                                 req.profile_pic
-                                  ? `http://127.0.0.1:8081/profile_pic/${req.profile_pic}`
+                                  ? `http://127.0.0.1:8081/media/${req.profile_pic}`
                                   : "/default-profile.png"
                               }
                               alt={req.username}
@@ -129,7 +129,7 @@ function Register() {
             />
             <input
               type="file"
-              accept="image/jpeg, image/png"
+              accept="image/jpeg, image/png, image/webp"
               id="profileUpload"
               style={{ display: "none" }}
               onChange={(e) => setProfilePic(e.target.files[0])}
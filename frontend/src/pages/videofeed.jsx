@@ -1,40 +1,109 @@
 import React, { useEffect, useState } from "react";
 import { useNavigate } from "react-router-dom";
 import axios from "axios";
-import DOMPurify from "dompurify";
 
 
 export default function VideoFeed() {
   const [posts, setPosts] = useState([]);
+  const [nextCursor, setNextCursor] = useState(null);
+  const [loadingMore, setLoadingMore] = useState(false);
   const navigate = useNavigate();
 
-  // ✅ Fetch posts from backend
+  // ✅ Fetch a page of the home feed from backend
+  const fetchPosts = async (cursor = null) => {
+    try {
+      const token = localStorage.getItem("token");
+
+      const res = await axios.get("http://127.0.0.1:8081/api/user/auth/feed", {
+                  params: cursor ? { cursor } : {},
+                  headers: {
+                    "Content-Type": "application/json",
+                    Authorization: `Bearer ${token}`,
+                  },
+                });
+
+      console.log("Fetched posts:", res.data);
+      setPosts((prev) => (cursor ? [...prev, ...res.data.items] : res.data.items));
+      setNextCursor(res.data.next_cursor);
+    } catch (err) {
+      console.error("Error fetching posts:", err);
+    }
+  };
+
   useEffect(() => {
-    const fetchPosts = async () => {
-      try {
-        const token = localStorage.getItem("token");
-
-        const res = await axios.get("http://127.0.0.1:8081/api/user/auth/getpost", {
-                    headers: {
-                      "Content-Type": "application/json",
-                      Authorization: `Bearer ${token}`,
-                    },
-                  });
-
-        console.log("Fetched posts:", res.data);
-        setPosts(res.data);
-      } catch (err) {
-        console.error("Error fetching posts:", err);
-      }
-    };
     fetchPosts();
   }, []);
 
+  const toggleLike = async (post) => {
+    try {
+      const token = localStorage.getItem("token");
+      const url = `http://127.0.0.1:8081/api/user/auth/posts/${post.id}/like`;
+      const config = { headers: { Authorization: `Bearer ${token}` } };
+      const res = post.liked_by_me
+        ? await axios.delete(url, config)
+        : await axios.post(url, null, config);
+
+      setPosts((prev) =>
+        prev.map((p) =>
+          p.id === post.id
+            ? { ...p, liked_by_me: res.data.liked_by_me, like_count: res.data.like_count }
+            : p
+        )
+      );
+    } catch (err) {
+      console.error("Error toggling like:", err);
+    }
+  };
+
+  // ✅ Description text with hashtags highlighted and mentions linked
+  const renderDescription = (post) => {
+    const parts = [];
+    let pos = 0;
+    (post.entities || []).forEach((e, i) => {
+      parts.push(post.description.slice(pos, e.start));
+      const text = post.description.slice(e.start, e.end);
+      if (e.kind === "mention" && e.user_id) {
+        parts.push(
+          <a key={i} href="#" onClick={(ev) => { ev.preventDefault(); navigate(`/profile/${e.user_id}`); }}>
+            {text}
+          </a>
+        );
+      } else if (e.kind === "hashtag") {
+        parts.push(<span key={i} className="text-primary">{text}</span>);
+      } else {
+        parts.push(text);
+      }
+      pos = e.end;
+    });
+    parts.push(post.description.slice(pos));
+    return parts;
+  };
+
+  // ✅ Duration and size come with the post, so nothing is fetched up front
+  const formatDuration = (ms) => {
+    const total = Math.round(ms / 1000);
+    const seconds = String(total % 60).padStart(2, "0");
+    return `${Math.floor(total / 60)}:${seconds}`;
+  };
+
+  // Bootstrap's ratio box, sized to the first slide; 16:9 until it's known
+  const aspectRatio = (post) => {
+    const first = post.media[0];
+    return first?.width && first?.height ? { "--bs-aspect-ratio": `${(first.height / first.width) * 100}%` } : undefined;
+  };
+
+  const loadMore = async () => {
+    if (!nextCursor || loadingMore) return;
+    setLoadingMore(true);
+    await fetchPosts(nextCursor);
+    setLoadingMore(false);
+  };
+
   return (
     <div className="container py-4">
       {/* Header */}
       <div className="d-flex justify-content-between align-items-center mb-4">
-        <h2 className="fw-bold text-primary mb-0">📹 Latest Video Posts</h2>
+        <h2 className="fw-bold text-primary mb-0">📹 Latest Posts</h2>
         <button
           className="btn btn-primary rounded-pill px-4 fw-semibold"
           onClick={() => navigate("/post")}
@@ -59,7 +128,7 @@ export default function VideoFeed() {
                   <img
                     src={
                       post.profile_pic
-                        ? `http://127.0.0.1:8081/profile_pic/${post.profile_pic}`
+                        ? `http://127.0.0.1:8081/media/${post.profile_pic}`
                         : "https://via.placeholder.com/50"
                     }
                     alt="Profile"
@@ -77,31 +146,46 @@ export default function VideoFeed() {
                 </div>
 
                 {/* ✅ Video Carousel */}
-                {Array.isArray(post.videos) && post.videos.length > 0 && (
+                {Array.isArray(post.media) && post.media.length > 0 && (
                   <div
                     id={`carousel-${post.post_id}`}
                     className="carousel slide"
                     data-bs-ride="carousel"
                   >
-                    <div className="carousel-inner ratio ratio-16x9 bg-dark">
-                      {post.videos.map((video, index) => (
+                    <div className="carousel-inner ratio ratio-16x9 bg-dark" style={aspectRatio(post)}>
+                      {post.media.map((media, index) => (
                         <div
-                          key={index}
+                          key={media.key}
                           className={`carousel-item ${
                             index === 0 ? "active" : ""
                           }`}
                         >
-                          <video
-                            src={`http://127.0.0.1:8081/video/${video}`}
-                            className="d-block w-100"
-                            controls
-                          />
+                          {media.kind === "image" ? (
+                            <img
+                              src={`http://127.0.0.1:8081${media.url}`}
+                              className="d-block w-100 h-100"
+                              style={{ objectFit: "contain" }}
+                              alt=""
+                            />
+                          ) : (
+                            <video
+                              src={`http://127.0.0.1:8081${media.url}`}
+                              className="d-block w-100 h-100"
+                              preload="none"
+                              controls
+                            />
+                          )}
+                          {media.kind === "video" && media.duration_ms && (
+                            <span className="badge bg-dark bg-opacity-75 position-absolute top-0 end-0 m-2">
+                              {formatDuration(media.duration_ms)}
+                            </span>
+                          )}
                         </div>
                       ))}
                     </div>
 
                     {/* ✅ Carousel Controls */}
-                    {post.videos.length > 1 && (
+                    {post.media.length > 1 && (
                       <>
                         <button
                           className="carousel-control-prev"
@@ -134,15 +218,30 @@ export default function VideoFeed() {
 
                 {/* Description */}
                 <div className="card-body">
-                  <div
-                    className="card-text"
-                    dangerouslySetInnerHTML={{
-                      __html: DOMPurify.sanitize(post.description),
-                    }}
-                  ></div>
+                  <button
+                    className={`btn btn-sm rounded-pill mb-2 ${
+                      post.liked_by_me ? "btn-danger" : "btn-outline-danger"
+                    }`}
+                    onClick={() => toggleLike(post)}
+                  >
+                    {post.liked_by_me ? "❤️" : "🤍"} {post.like_count}
+                  </button>
+                  <div className="card-text">{renderDescription(post)}</div>
                 </div>
               </div>
             ))}
+
+            {nextCursor && (
+              <div className="text-center mb-4">
+                <button
+                  className="btn btn-outline-primary rounded-pill px-4"
+                  onClick={loadMore}
+                  disabled={loadingMore}
+                >
+                  {loadingMore ? "Loading..." : "Load more"}
+                </button>
+              </div>
+            )}
           </div>
         </div>
       )}